@@ -1,15 +1,12 @@
 //#![allow(unused)]
 
 
-use std::collections::HashMap;
 use std::f32::consts::PI;
 use crate::neuro::*;
 use crate::timer::*;
 use crate::util::*;
-use macroquad::prelude::*;
-use macroquad::rand::*;
+use crate::math::*;
 use rapier2d::geometry::*;
-use rapier2d::na::Isometry2;
 use rapier2d::na::Vector2;
 use rapier2d::prelude::{RigidBody, RigidBodyHandle};
 use crate::settings::*;
@@ -18,24 +15,99 @@ use crate::misc::*;
 use crate::sketch::*;
 use crate::phyx::physics::Physics;
 use crate::phyx::physics_misc::PhysicsMaterial;
-use ::rand::prelude::*;
+use crate::rng::SimRng;
+use crate::spatial::SpatialGrid;
+use crate::environment::Environment;
+use crate::terrain::{Biome, SCENT_CHANNELS};
+use crate::carcass::CarcassBox;
+
+#[doc = r"Labels of input nodes of agent brain. Sensors added in newer versions are appended at the end,
+so agents loaded from older saves get them as new unconnected input nodes."]
+pub const SENSOR_LABELS: [&str; 37] = [
+    "CON", "ENY", "RES", "HP", "ENG", "TGL", "TGR", "DST", 
+    "DNG", "FAM", "REL", "RER", "RED", "PAI", "WAL", "H2O",
+    "RED", "GRE", "BLU", "WAL", "E-R", "E-G", "E-B", "SEA",
+    "DAY", "ALT", "SLP", "SLR", "MEA", "SC1", "SC2", "SC3",
+    "SR1", "SR2", "SR3", "HEA", "HDR"
+];
+
+#[doc = r"Labels of output nodes of agent brain. Like sensors, newer effectors are appended at the end."]
+pub const EFFECTOR_LABELS: [&str; 13] = [
+    "MOV", "LFT", "RGT", "ATK", 
+    "EAT", "RUN", "RED", "GRE", "BLU",
+    "EM1", "EM2", "EM3", "SPK"
+];
+
+#[doc = r"Labels of scent inputs: intensity and side (positive when scent is stronger on the right) of every channel."]
+const SCENT_SENSORS: [(&str, &str); SCENT_CHANNELS] = [("SC1", "SR1"), ("SC2", "SR2"), ("SC3", "SR3")];
+const SCENT_EFFECTORS: [&str; SCENT_CHANNELS] = ["EM1", "EM2", "EM3"];
+
+#[doc = r"Snapshot of agent surroundings collected during perception phase."]
+#[derive(Clone, Debug)]
+pub struct Perception {
+    pub contacts: Vec<(RigidBodyHandle, f32)>,
+    pub contact_agent: bool,
+    pub contact_plant: bool,
+    pub contact_carcass: bool,
+    pub enemy: Option<RigidBodyHandle>,
+    pub enemy_family: Option<bool>,
+    pub enemy_mood: Color,
+    pub plant: Option<RigidBodyHandle>,
+    pub season: f32,
+    pub daylight: f32,
+    pub vision_scale: f32,
+    pub wall_contact: bool,
+    pub wall_dist: Option<f32>,
+    pub hearing: f32,
+    pub hearing_dir: f32,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Perception {
+
+    pub fn new() -> Self {
+        Self {
+            contacts: vec![],
+            contact_agent: false,
+            contact_plant: false,
+            contact_carcass: false,
+            enemy: None,
+            enemy_family: None,
+            enemy_mood: Color::new(0.0, 0.0, 0.0, 1.0),
+            plant: None,
+            season: 0.5,
+            daylight: 0.5,
+            vision_scale: 1.0,
+            wall_contact: false,
+            wall_dist: None,
+            hearing: 0.0,
+            hearing_dir: 0.0,
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Agent {
     pub pos: Vec2,
-    rot: f32,
+    pub rot: f32,
     mass: f32,
-    vel: f32,
+    pub vel: f32,
     ang_vel: f32,
     pub size: f32,
-    vision_range: f32,
-    vision_angle: f32,
+    pub vision_range: f32,
+    pub vision_angle: f32,
+    pub hearing_range: f32,
     pub max_eng: f32,
     pub eng: f32,
     pub max_hp: f32,
     pub hp: f32,
-    color: Color,
-    color_second: Color,
+    pub color: Color,
+    pub color_second: Color,
     shape: SharedShape,
     timer_analize: Timer,
     timer_contact: Timer,
@@ -47,6 +119,7 @@ pub struct Agent {
     pub contacts: Vec<(RigidBodyHandle, f32)>,
     pub contact_agent: bool,
     pub contact_plant: bool,
+    pub contact_carcass: bool,
     pub enemy: Option<RigidBodyHandle>,
     pub enemy_family: Option<bool>,
     pub enemy_position: Option<Vec2>,
@@ -57,7 +130,7 @@ pub struct Agent {
     pub plant_position: Option<Vec2>,
     pub plant_dir: Option<f32>,
     pub rbh: RigidBodyHandle,
-    colliders: Vec<ColliderHandle>,
+    pub colliders: Vec<ColliderHandle>,
     pub neuro_map: NeuroMap,
     pub childs: usize,
     pub kills: usize,
@@ -72,24 +145,39 @@ pub struct Agent {
     pub shell: i32,
     pub mutations: i32,
     pub eyes: i32,
+    pub ears: i32,
     pub mood: Color,
     ancestors: Ancestors,
     pub eng_cost: EnergyCost,
     blocked: f32,
-    attack_visual: bool,
-    eat_visual: bool,
+    pub attack_visual: bool,
+    pub eat_visual: bool,
     water: i32,
+    season: f32,
+    daylight: f32,
+    vision_scale: f32,
+    wall_dist: Option<f32>,
+    altitude: i32,
+    slope: Vec2,
+    scent: [f32; SCENT_CHANNELS],
+    scent_gradient: [Vec2; SCENT_CHANNELS],
+    pub emission: [f32; SCENT_CHANNELS],
+    pub sound: f32,
+    hearing: f32,
+    hearing_dir: f32,
+    biome: Biome,
+    pub killer: Option<RigidBodyHandle>,
 }
 
 
 impl Agent {
     
-    pub fn new(physics: &mut Physics) -> Self {
-        let settings = get_settings();
-        let size = rand::gen_range(settings.agent_size_min, settings.agent_size_max) as f32;
+    pub fn new(physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Self {
+        let size = rng.gen_range(settings.agent_size_min, settings.agent_size_max) as f32;
         let rot = 0.0; //random_rotation();
-        let eyes = gen_range(0, 10);
-        let pos = random_position(settings.world_w as f32, settings.world_h as f32);
+        let eyes = rng.gen_range(0, 10);
+        let ears = rng.gen_range(0, 10);
+        let pos = random_position(settings.world_w as f32, settings.world_h as f32, rng);
         let shape = SharedShape::ball(size);
         let rbh = physics.add_dynamic_object(
             &pos, 
@@ -98,31 +186,21 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             },
             false,
         );
-        let color = random_color();
-        let color_second = random_color();
+        let color = random_color(rng);
+        let color_second = random_color(rng);
         let mut network = Network::new(1.0);
-        let inp_labs = vec![
-            "CON", "ENY", "RES", "HP", "ENG", "TGL", "TGR", "DST", 
-            "DNG", "FAM", "REL", "RER", "RED", "PAI", "WAL", "H2O",
-            "RED", "GRE", "BLU", "WAL", "E-R", "E-G", "E-B"
-        ];
-        let out_labs = vec![
-            "MOV", "LFT", "RGT", "ATK", 
-            "EAT", "RUN", "RED", "GRE", "BLU"
-        ];
+        let inp_labs = SENSOR_LABELS.to_vec();
+        let out_labs = EFFECTOR_LABELS.to_vec();
         let hid = settings.hidden_nodes_num;
         let hid_layers = settings.hidden_layers_num;
-        let hid_range = 0..=hid;
-        let layers_range = 0..=hid_layers;
-        //let h = thread_rng().gen_range(hid_range);
-        let l = thread_rng().gen_range(layers_range);
+        let l = rng.gen_range(0, hid_layers+1);
         let mut deep = vec![];
         for _ in 0..l {
-            let node_num = thread_rng().gen_range(hid_range.clone());
+            let node_num = rng.gen_range(0, hid+1);
             deep.push(node_num);
         }
         network.build(
@@ -131,7 +209,8 @@ impl Agent {
             deep, 
             out_labs.len(), 
             out_labs, 
-            settings.neurolink_rate
+            settings.neurolink_rate,
+            rng,
         );
         let input_pairs = network.get_input_pairs();
         let output_pairs = network.get_output_pairs();
@@ -145,8 +224,9 @@ impl Agent {
             vel: 0.0,
             ang_vel: 0.0,
             size,
-            vision_range:  Self::calc_vision_range(eyes),
+            vision_range:  Self::calc_vision_range(eyes, settings),
             vision_angle: Self::calc_vision_angle(eyes),
+            hearing_range: Self::calc_hearing_range(ears, settings),
             max_hp: 100.0,
             hp: 100.0,
             max_eng: 0.0,
@@ -154,8 +234,8 @@ impl Agent {
             color,
             color_second,
             shape,
-            timer_analize: Timer::new(settings.neuro_duration, true, true, true),
-            timer_contact: Timer::new(0.07, true, true, true),
+            timer_analize: Timer::new_random(settings.neuro_duration, true, true, rng),
+            timer_contact: Timer::new_random(0.07, true, true, rng),
             network,
             alife: true,
             lifetime: 0.0,
@@ -173,22 +253,24 @@ impl Agent {
             contacts: Vec::new(),
             contact_agent: false,
             contact_plant: false,
+            contact_carcass: false,
             rbh,
             colliders: vec![],
             neuro_map,
             childs: 0,
             kills: 0,
-            specie: create_name(4),
+            specie: create_name(4, rng),
             attacking: false,
             eating: false,
             points: 0.0,
             pain: 0.0,
             run: false,
-            speed: gen_range(0, 10),
-            power: gen_range(0, 10),
-            shell: gen_range(0, 10),
-            mutations: gen_range(0, 10),
+            speed: rng.gen_range(0, 10),
+            power: rng.gen_range(0, 10),
+            shell: rng.gen_range(0, 10),
+            mutations: rng.gen_range(0, 10),
             eyes,
+            ears,
             mood: Color::new(0.0, 0.0, 0.0, 1.0),
             ancestors: Ancestors::new(),
             eng_cost: EnergyCost::default(),
@@ -196,9 +278,23 @@ impl Agent {
             attack_visual: false,
             eat_visual: false,
             water: 0,
+            season: 0.5,
+            daylight: 0.5,
+            vision_scale: 1.0,
+            wall_dist: None,
+            altitude: 0,
+            slope: Vec2::ZERO,
+            scent: [0.0; SCENT_CHANNELS],
+            scent_gradient: [Vec2::ZERO; SCENT_CHANNELS],
+            emission: [0.0; SCENT_CHANNELS],
+            sound: 0.0,
+            hearing: 0.0,
+            hearing_dir: 0.0,
+            biome: Biome::default(),
+            killer: None,
         };
         agent.ancestors.add_ancestor(Ancestor::new(&agent.specie, agent.generation as i32, 0));
-        agent.calc_hp();
+        agent.calc_hp(settings);
         let limb = SharedShape::ball(size/3.0);
         let left: Vec2 = Vec2::from_angle(rot-PI-PI/2.5)*size*1.0;
         let right: Vec2 = Vec2::from_angle(rot-PI+PI/2.5)*size*1.0;
@@ -210,7 +306,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         let colh_right = physics.add_collider(
@@ -221,7 +317,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         agent.colliders.push(colh_left);
@@ -229,8 +325,7 @@ impl Agent {
         return agent;
     }
 
-    pub fn calc_vision_range(eyes: i32) -> f32 {
-        let settings = get_settings();
+    pub fn calc_vision_range(eyes: i32, settings: &Settings) -> f32 {
         return 120.0 + settings.agent_vision_range*(eyes as f32)/10.0;
     }
 
@@ -238,6 +333,10 @@ impl Agent {
         return 1.8*PI * ((11.0 - eyes as f32)/11.0);
     }
 
+    pub fn calc_hearing_range(ears: i32, settings: &Settings) -> f32 {
+        return settings.agent_hearing_range*(ears.max(0) as f32)/10.0;
+    }
+
     pub fn get_mood(&self) -> Color {
         return self.mood.to_owned();
     }
@@ -246,38 +345,142 @@ impl Agent {
         return self.network.get_nodes_links_number();
     }
 
-    fn mod_specie(&mut self, time: f64) {
-        let settings = get_settings();
-        let n = self.ancestors.get_ancestors().len() as i32;
-        let factor = settings.rare_specie_mod;
-        let r = specie_mod_rate(factor, n);
-        if rand::gen_range(0, r) == 0 {
-            let s = create_name(1);
-            let i = rand::gen_range(0, 3)*2;
-            let mut name = self.specie.to_owned();
-            name.replace_range(i..=i+1, &s);
-            self.specie = name.to_owned();
-            self.ancestors.add_ancestor(Ancestor::new(&self.specie, self.generation as i32, time.round() as i32));
-            if random_unit_unsigned() < 0.25 {
-                self.color_second = random_color();
-            } else if random_unit_unsigned() < 0.25 {
-                self.color = random_color();
-            }
-        }
+    pub fn change_specie(&mut self, name: &str, time: f64) {
+        self.specie = name.to_owned();
+        self.ancestors.add_ancestor(Ancestor::new(&self.specie, self.generation as i32, time.round() as i32));
+    }
 
+    #[doc = r"Agent becomes founder of new specie, which sometimes gets new colors."]
+    pub fn found_specie(&mut self, name: &str, time: f64, rng: &mut SimRng) {
+        self.change_specie(name, time);
+        if random_unit_unsigned(rng) < 0.25 {
+            self.color_second = random_color(rng);
+        } else if random_unit_unsigned(rng) < 0.25 {
+            self.color = random_color(rng);
+        }
     }
 
     pub fn ancestors(&self) -> Vec<Ancestor> {
         return self.ancestors.get_ancestors();
     }
 
-    pub fn from_sketch(sketch: AgentSketch, physics: &mut Physics, time: f64) -> Agent {
-        let settings = get_settings();
-        let pos = vec2(sketch.pos[0], sketch.pos[1])+random_unit_vec2()*100.0;
+    pub fn mates(&self) -> Vec<Ancestor> {
+        return self.ancestors.get_mates();
+    }
+
+    pub fn ready_to_reproduce(&self, settings: &Settings) -> bool {
+        return self.repro_time >= settings.repro_time && (self.eng/self.max_eng) >= settings.born_eng_min;
+    }
+
+    pub fn from_sketch(sketch: AgentSketch, physics: &mut Physics, _time: f64, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) -> Agent {
+        let pos = vec2(sketch.pos[0], sketch.pos[1])+random_unit_vec2(rng)*100.0;
+        let network = sketch.network.from_sketch(rng);
+        let timer_analize = Timer::new_random(settings.neuro_duration, true, true, rng);
+        let timer_contact = Timer::new_random(0.07, true, true, rng);
+        let mut agent = Self::build(&sketch, pos, 0.0, sketch.generation + 1, network, timer_analize, timer_contact, physics);
+        agent.add_missing_sensors(rng);
+        agent.add_missing_effectors(rng);
+        agent.mutate(settings, mutations, rng);
+        agent.calc_hp(settings);
+        return agent;
+    }
+
+    #[doc = r"Agent loaded from simulation save: same position, genes, network and runtime state, without mutation and without drawing from rng.
+Only agents saved by older versions, which have no runtime state, get default state and missing sensors added with rng."]
+    pub fn restore(sketch: AgentSketch, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Agent {
+        let pos = vec2(sketch.pos[0], sketch.pos[1]);
+        let mut network = sketch.network.from_sketch(rng);
+        let (rot, body, timer_analize, timer_contact) = match &sketch.state {
+            Some(state) => {
+                network.set_node_values(&state.node_values);
+                (state.rot, state.body, state.timer_analize, state.timer_contact)
+            },
+            None => (PI, [pos.x, pos.y, PI], Timer::new(settings.neuro_duration, true, true), Timer::new(0.07, true, true)),
+        };
+        // heading of agent is rotation of its body turned by PI
+        let mut agent = Self::build(&sketch, vec2(body[0], body[1]), body[2]-PI, sketch.generation, network, timer_analize, timer_contact, physics);
+        agent.pos = pos;
+        agent.rot = rot;
+        agent.points = sketch.points;
+        agent.calc_hp(settings);
+        agent.vision_angle = Self::calc_vision_angle(agent.eyes);
+        agent.vision_range = Self::calc_vision_range(agent.eyes, settings);
+        agent.hearing_range = Self::calc_hearing_range(agent.ears, settings);
+        match sketch.state {
+            Some(state) => {
+                agent.vel = state.vel;
+                agent.ang_vel = state.ang_vel;
+                agent.eng = state.eng;
+                agent.hp = state.hp;
+                agent.lifetime = state.lifetime;
+                agent.repro_time = state.repro_time;
+                agent.childs = state.childs;
+                agent.kills = state.kills;
+                agent.pain = state.pain;
+                agent.run = state.run;
+                agent.attacking = state.attacking;
+                agent.eating = state.eating;
+                agent.blocked = state.blocked;
+                agent.mood = Color::from(state.mood);
+                agent.emission = state.emission;
+                agent.sound = state.sound;
+                agent.water = state.water;
+                agent.altitude = state.altitude;
+                agent.slope = Vec2::from(state.slope);
+                agent.biome = state.biome;
+                agent.scent = state.scent;
+                agent.scent_gradient = state.scent_gradient.map(Vec2::from);
+            },
+            None => {
+                agent.add_missing_sensors(rng);
+                agent.add_missing_effectors(rng);
+            },
+        }
+        return agent;
+    }
+
+    #[doc = r"Runtime state stored in simulation save."]
+    pub fn get_state(&self, physics: &Physics, settings: &Settings) -> AgentState {
+        let body = physics.get_object_state(self.rbh, settings);
+        return AgentState {
+            rot: self.rot,
+            body: [body.position.x, body.position.y, body.rotation],
+            vel: self.vel,
+            ang_vel: self.ang_vel,
+            eng: self.eng,
+            hp: self.hp,
+            lifetime: self.lifetime,
+            repro_time: self.repro_time,
+            childs: self.childs,
+            kills: self.kills,
+            pain: self.pain,
+            run: self.run,
+            attacking: self.attacking,
+            eating: self.eating,
+            blocked: self.blocked,
+            mood: self.mood.to_array(),
+            emission: self.emission,
+            sound: self.sound,
+            water: self.water,
+            altitude: self.altitude,
+            slope: self.slope.to_array(),
+            biome: self.biome,
+            scent: self.scent,
+            scent_gradient: self.scent_gradient.map(|g| g.to_array()),
+            timer_analize: self.timer_analize,
+            timer_contact: self.timer_contact,
+            node_values: self.network.get_node_values(),
+        };
+    }
+
+    #[doc = r"Agent body with physics object built from genes of sketch, before any mutation."]
+    #[allow(clippy::too_many_arguments)]
+    fn build(sketch: &AgentSketch, pos: Vec2, rot: f32, generation: u32, network: Network, timer_analize: Timer, timer_contact: Timer, physics: &mut Physics) -> Agent {
         let color = Color::new(sketch.color[0], sketch.color[1], sketch.color[2], sketch.color[3]);
         let color_second = Color::new(sketch.color_second[0], sketch.color_second[1], sketch.color_second[2], sketch.color_second[3]);
         let size = sketch.size;
         let eyes = sketch.eyes;
+        let ears = sketch.ears;
         let shape = match sketch.shape {
             MyShapeType::Ball => {
                 SharedShape::ball(size)
@@ -289,15 +492,12 @@ impl Agent {
                 SharedShape::ball(sketch.size)
             },
         };
-        let rot = 0.0; //random_rotation();
-        let gen = sketch.generation + 1;
-        let network = sketch.network.from_sketch();
         let rbh = physics.add_dynamic_object(
             &pos, 
-            0.0, 
+            rot, 
             shape.clone(), 
             PhysicsMaterial::default(), 
-            InteractionGroups { memberships: Group::GROUP_1, filter: Group::GROUP_2 | Group::GROUP_1 },
+            InteractionGroups { memberships: Group::GROUP_1, filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3 },
             false,
         );
         let mut agent = Agent {
@@ -309,6 +509,7 @@ impl Agent {
             size,
             vision_range: 0.0,
             vision_angle: 0.0,
+            hearing_range: 0.0,
             max_hp: 100.0,
             hp: 100.0,
             max_eng: 0.0,
@@ -316,13 +517,13 @@ impl Agent {
             color,
             color_second,
             shape,
-            timer_analize: Timer::new(settings.neuro_duration, true, true, true),
-            timer_contact: Timer::new(0.07, true, true, true),
+            timer_analize,
+            timer_contact,
             network,
             alife: true,
             lifetime: 0.0,
             repro_time: 0.0,
-            generation: gen,
+            generation,
             enemy: None,
             enemy_family: None,
             enemy_position: None,
@@ -335,6 +536,7 @@ impl Agent {
             contacts: Vec::new(),
             contact_agent: false,
             contact_plant: false,
+            contact_carcass: false,
             rbh,
             colliders: vec![],
             neuro_map: sketch.neuro_map.clone(),
@@ -351,6 +553,7 @@ impl Agent {
             shell: sketch.shell,
             mutations: sketch.mutations,
             eyes,
+            ears,
             mood: Color::new(0.0, 0.0, 0.0, 1.0),
             ancestors: sketch.ancestors.to_owned(),
             eng_cost: EnergyCost::default(),
@@ -358,13 +561,24 @@ impl Agent {
             attack_visual: false,
             eat_visual: false,
             water: 0,
+            season: 0.5,
+            daylight: 0.5,
+            vision_scale: 1.0,
+            wall_dist: None,
+            altitude: 0,
+            slope: Vec2::ZERO,
+            scent: [0.0; SCENT_CHANNELS],
+            scent_gradient: [Vec2::ZERO; SCENT_CHANNELS],
+            emission: [0.0; SCENT_CHANNELS],
+            sound: 0.0,
+            hearing: 0.0,
+            hearing_dir: 0.0,
+            biome: Biome::default(),
+            killer: None,
         };
-        agent.mod_specie(time);
-        agent.mutate();
-        agent.calc_hp();
         let limb = SharedShape::ball(size/3.0);
-        let left: Vec2 = Vec2::from_angle(rot-PI-PI/2.5)*size*1.0;
-        let right: Vec2 = Vec2::from_angle(rot-PI+PI/2.5)*size*1.0;
+        let left: Vec2 = Vec2::from_angle(-PI-PI/2.5)*size*1.0;
+        let right: Vec2 = Vec2::from_angle(-PI+PI/2.5)*size*1.0;
         let colh_left = physics.add_collider(
             agent.rbh, 
             &left, 
@@ -373,7 +587,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         let colh_right = physics.add_collider(
@@ -384,7 +598,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         agent.colliders.push(colh_left);
@@ -392,131 +606,176 @@ impl Agent {
         return agent;
     }
 
-    pub fn draw(&self, selected: bool, font: &Font, physics: &Physics) {
-        let settings = get_settings();
-        if settings.agent_eng_bar {
-            let e = self.eng/self.max_eng;
-            let hp = self.hp/self.max_hp;
-            self.draw_status_bar(hp, GREEN, RED, Vec2::new(0.0, self.size*1.5+4.0));
-            self.draw_status_bar(e, SKYBLUE, YELLOW, Vec2::new(0.0, self.size*1.5+6.0));
-        }
-        self.draw_limbs(physics);
-        self.draw_body();
-        self.draw_front();
-        self.draw_eyes(selected);
-        if selected {
-            self.draw_info(&font);
-            self.draw_target(selected);
-        } else {
-            self.draw_info(&font);
+    #[doc = r"Add input nodes for sensors unknown to agents saved by older versions."]
+    fn add_missing_sensors(&mut self, rng: &mut SimRng) {
+        for label in SENSOR_LABELS.iter() {
+            if !self.neuro_map.sensors.contains_key(*label) {
+                let key = self.network.add_input_node(label, rng);
+                self.neuro_map.add_sensor(label, key);
+            }
         }
-    }    
-
-    fn draw_limbs(&self, physics: &Physics) {
-        let colh_l = physics.core.colliders.get(self.colliders[0]).unwrap();
-        let colh_r = physics.core.colliders.get(self.colliders[1]).unwrap();
-        let rl = colh_l.shared_shape().as_ball().unwrap().radius;
-        let rr = colh_r.shared_shape().as_ball().unwrap().radius;
-        let (pos_l, _rot_l) = iso_to_vec2_rot(colh_l.position());
-        let (pos_r, _rot_r) = iso_to_vec2_rot(colh_r.position());
-        draw_circle(pos_l.x, pos_l.y, rl, GRAY);
-        draw_circle(pos_r.x, pos_r.y, rr, GRAY);
-    }
-
-    fn move_limbs(&mut self, physics: &mut Physics) {
-        self.move_limb_left(physics);
-        self.move_limb_right(physics);
     }
 
-    fn move_limb_left(&self, physics: &mut Physics) {
-        let colh_l = physics.core.colliders.get_mut(self.colliders[0]).unwrap();
-        let (pos_l, _rot_l) = iso_to_vec2_rot(colh_l.position());
-        let mut vec_l = (pos_l-self.pos).normalize_or_zero();
-        vec_l = vec_l.rotate(Vec2::from_angle(0.5))*self.size*1.5;
-        colh_l.set_position(Isometry2::new(Vector2::new(vec_l.x+self.pos.x, vec_l.y+self.pos.y), 0.0));
-    }    
-
-    fn move_limb_right(&self, physics: &mut Physics) {
-        let colh_r = physics.core.colliders.get_mut(self.colliders[1]).unwrap();
-        let (pos_r, _rot_r) = iso_to_vec2_rot(colh_r.position());
-        let mut vec_r = (pos_r - self.pos).normalize_or_zero();
-        vec_r = Vec2::from_angle(-0.5).rotate(vec_r)*self.size*1.5;
-        colh_r.set_position(Isometry2::new(Vector2::new(vec_r.x+self.pos.x, vec_r.y+self.pos.y), 0.0));
-    }   
-
-    fn draw_body(&self) {
-        let x0 = self.pos.x;
-        let y0 = self.pos.y;
-        let rv = Vec2::from_angle(self.rot+PI);
-        let x1 = x0+rv.x*self.size;
-        let y1 = y0+rv.y*self.size;
-        let shell = self.size + (self.shell as f32)*0.4;
-        draw_circle(x0, y0, shell, LIGHTGRAY);
-        draw_circle(x1, y1, shell*0.6, LIGHTGRAY);
-        draw_circle(x1, y1, self.size*0.6, self.color_second);
-        draw_circle(x0, y0, self.size, self.color);
-        draw_circle(x0, y0, self.size/2.0, self.mood);
-        if self.run {
-            let mut shadow = self.color;
-            shadow.a = 0.6;
-            let xs0 = x0 + rv.x * self.size * self.vel;
-            let ys0 = y0 + rv.y * self.size * self.vel;
-            let xs1 = x1 + rv.x * self.size * 1.3 * self.vel;
-            let ys1 = y1 + rv.y * self.size * 1.3 * self.vel;
-            let xs2 = x1 + rv.x * self.size * 1.7 * self.vel;
-            let ys2 = y1 + rv.y * self.size * 1.7 * self.vel;
-            draw_circle(xs2, ys2, self.size*0.5, shadow);
-            draw_circle(xs1, ys1, self.size*0.8, shadow);
-            draw_circle(xs0, ys0, self.size, shadow);
+    #[doc = r"Add output nodes for effectors unknown to agents saved by older versions."]
+    fn add_missing_effectors(&mut self, rng: &mut SimRng) {
+        for label in EFFECTOR_LABELS.iter() {
+            if !self.neuro_map.effectors.contains_key(*label) {
+                let key = self.network.add_output_node(label, rng);
+                self.neuro_map.add_effector(label, key);
+            }
         }
     }
 
-    pub fn update(&mut self, other: &HashMap<RigidBodyHandle, Agent>, physics: &mut Physics) -> bool {
-        let dt = dt()*sim_speed();
+    #[doc = r"Advance agent clocks. Returns true when brain should be evaluated in this tick."]
+    pub fn update_timers(&mut self, settings: &Settings) -> bool {
+        let dt = dt();
         self.lifetime += dt;
-        if self.repro_time < get_settings().repro_time {
+        if self.repro_time < settings.repro_time {
             self.repro_time += dt;
         }
-        if self.timer_analize.update(dt) {
-            self.update_contacts(other, physics);
-            self.watch(physics);
-            self.update_enemy_mood(other);
-            self.analize();
-            //self.contacts_clear();
+        return self.timer_analize.update(dt);
+    }
+
+    #[doc = r"Read-only perception phase: contacts, closest agent and plant seen by agent. Safe to run in parallel."]
+    pub fn perceive(&self, other: &StableMap<RigidBodyHandle, Agent>, carcasses: &CarcassBox, physics: &Physics, grid: &SpatialGrid, env: &Environment, settings: &Settings) -> Perception {
+        let mut perception = Perception::new();
+        perception.season = env.season;
+        perception.daylight = env.daylight;
+        perception.vision_scale = env.vision;
+        let vision_range = self.vision_range * perception.vision_scale;
+        let mut contacts = physics.get_contacts_set(self.rbh, self.size);
+        contacts.extend(grid.contacts_across_edges(self.rbh, self.pos, self.size));
+        for contact in contacts {
+            if other.contains_key(&contact) {
+                perception.contact_agent = true;
+            } else if carcasses.contains(&contact) {
+                perception.contact_carcass = true;
+            } else {
+                perception.contact_plant = true;
+            }
+            if let Some(pos2) = physics.get_object_position(contact) {
+                let mut rel_pos = world_offset(&self.pos, &pos2, settings);
+                rel_pos = rel_pos.normalize_or_zero();
+                let target_angle = rel_pos.angle_between(Vec2::from_angle(self.rot));
+                perception.contacts.push((contact, target_angle));
+            }
+        }
+        let direction = Vec2::from_angle(self.rot);
+        let in_sight = |offset: Vec2, size: f32| {
+            let dist = offset.length();
+            return match physics.cast_ray_to_fixed(&self.pos, &offset.normalize_or_zero(), dist) {
+                Some(wall_dist) => wall_dist >= dist - size,
+                None => true,
+            };
+        };
+        perception.enemy = grid.closest_agent(
+            self.rbh, 
+            self.pos, 
+            vision_range, 
+            self.vision_angle, 
+            direction,
+            settings,
+            in_sight,
+        );
+        perception.plant = grid.closest_plant(
+            self.rbh, 
+            self.pos, 
+            vision_range, 
+            self.vision_angle, 
+            direction,
+            in_sight,
+        );
+        perception.wall_contact = physics.touches_fixed(&self.pos, self.size*1.1);
+        perception.wall_dist = physics.cast_ray_to_fixed(&self.pos, &direction, vision_range);
+        match perception.enemy.and_then(|rbh| other.get(&rbh)) {
+            Some(enemy) => {
+                perception.enemy_family = Some(self.specie == enemy.specie);
+                perception.enemy_mood = enemy.get_mood();
+            },
+            None => {},
+        }
+        if self.hearing_range > 0.0 {
+            let mut heard: StableSet<RigidBodyHandle> = StableSet::default();
+            for image in world_images(&self.pos, self.hearing_range, settings) {
+                heard.extend(physics.get_agents_in_range(&image, self.hearing_range, self.rbh));
+            }
+            for rbh in heard {
+                let Some(speaker) = other.get(&rbh) else { continue; };
+                if speaker.sound <= 0.0 { continue; }
+                let rel_pos = world_offset(&self.pos, &speaker.pos, settings);
+                let loudness = speaker.sound * (1.0 - rel_pos.length()/self.hearing_range).max(0.0);
+                if loudness > perception.hearing {
+                    perception.hearing = loudness;
+                    perception.hearing_dir = rel_pos.angle_between(direction)/PI;
+                }
+            }
+        }
+        return perception;
+    }
+
+    #[doc = r"Brain phase: apply perception and evaluate neural network. Changes only agent own state, so it may run in parallel."]
+    pub fn think(&mut self, perception: &Perception, physics: &Physics, settings: &Settings) {
+        self.contacts = perception.contacts.clone();
+        self.contact_agent = perception.contact_agent;
+        self.contact_plant = perception.contact_plant;
+        self.contact_carcass = perception.contact_carcass;
+        self.enemy = perception.enemy;
+        self.enemy_family = perception.enemy_family;
+        self.enemy_mood = Some(perception.enemy_mood);
+        self.plant = perception.plant;
+        self.season = perception.season;
+        self.daylight = perception.daylight;
+        self.vision_scale = perception.vision_scale;
+        self.wall_dist = perception.wall_dist;
+        self.hearing = perception.hearing;
+        self.hearing_dir = perception.hearing_dir;
+        if perception.wall_contact {
+            self.blocked = 1.0;
         }
+        self.update_enemy_position(physics, settings);
+        self.analize(settings);
+    }
 
-        self.update_physics(physics);
+    #[doc = r"Serial phase: movement, energy and health. Returns false if agent died."]
+    pub fn update(&mut self, physics: &mut Physics, settings: &Settings) -> bool {
+        self.update_physics(physics, settings);
         if self.pos.x.is_nan() || self.pos.y.is_nan() {
             self.alife = false;
             return self.alife;
         }
-        self.calc_energy();
-        self.calc_health();
+        self.calc_energy(settings);
+        self.calc_health(settings);
         return self.alife;
     }
 
-    fn update_enemy_mood(&mut self, other: &HashMap<RigidBodyHandle, Agent>) {
-        match self.enemy {
-            None => {
-                self.enemy_mood = Some(Color::new(0.0, 0.0, 0.0, 1.0));
-            },
-            Some(rbh) => {
-                match other.get(&rbh) {
-                    None => {
-                        self.enemy_mood = Some(Color::new(0.0, 0.0, 0.0, 1.0));
-                    },
-                    Some(agent) => {
-                        self.enemy_mood = Some(agent.get_mood());
-                    },
-                }
-            },
-        }
-    }
-
     pub fn set_water_tile(&mut self, water: i32) {
         self.water = water;
     }
 
+    pub fn set_altitude_tile(&mut self, altitude: i32, slope: Vec2) {
+        self.altitude = altitude;
+        self.slope = slope;
+    }
+
+    pub fn set_scent_tile(&mut self, scent: [f32; SCENT_CHANNELS], gradient: [Vec2; SCENT_CHANNELS]) {
+        self.scent = scent;
+        self.scent_gradient = gradient;
+    }
+
+    pub fn set_biome_tile(&mut self, biome: Biome) {
+        self.biome = biome;
+    }
+
+    pub fn get_biome(&self) -> Biome {
+        return self.biome;
+    }
+
+    #[doc = r"Slope of terrain in direction of movement: positive uphill, negative downhill."]
+    fn climb(&self) -> f32 {
+        let dir = Vec2::from_angle(self.rot);
+        return (dir * self.vel).dot(self.slope);
+    }
+
     pub fn eat(&self) -> Vec<RigidBodyHandle> {
         let mut hits: Vec<RigidBodyHandle> = vec![];
         for (rbh, ang) in self.contacts.to_vec() {
@@ -538,8 +797,9 @@ impl Agent {
         return hits;
     }
 
-    fn prep_input(&mut self) {
+    fn prep_input(&mut self, settings: &Settings) {
         let contact: f32;
+        let vision_range = self.vision_range * self.vision_scale;
         let contact_agent = self.contact_agent as i32 as f32;
         let contact_plant = self.contact_plant as i32 as f32;
         if self.contacts.len() > 0 {
@@ -552,7 +812,7 @@ impl Agent {
             None => 0.0,
             Some(pos2) => {
                 let dist = pos2.distance(self.pos);
-                dist/self.vision_range
+                dist/vision_range
             },
         };
         let tg_ang = match self.enemy_dir {
@@ -579,7 +839,7 @@ impl Agent {
             None => 0.0,
             Some(pos2) => {
                 let dist = pos2.distance(self.pos);
-                dist/self.vision_range
+                dist/vision_range
             },
         };
         let res_ang = match self.plant_dir {
@@ -607,7 +867,11 @@ impl Agent {
                 f
             }
         };
-        let wall = self.blocked;
+        let wall_sight = match self.wall_dist {
+            None => 0.0,
+            Some(dist) => 1.0 - clamp(dist/vision_range, 0.0, 1.0),
+        };
+        let wall = self.blocked.max(wall_sight);
         self.blocked = 0.0;
         let eng = self.eng/self.max_eng;
         let hp = self.hp/self.max_hp;
@@ -634,6 +898,7 @@ impl Agent {
         self.neuro_map.set_signal("CON", contact);
         self.neuro_map.set_signal("ENY", contact_agent);
         self.neuro_map.set_signal("RES", contact_plant);
+        self.neuro_map.set_signal("MEA", self.contact_carcass as i32 as f32);
         self.neuro_map.set_signal("HP", hp);
         self.neuro_map.set_signal("ENG", eng);
         self.neuro_map.set_signal("TGL", tgl);
@@ -653,14 +918,27 @@ impl Agent {
         self.neuro_map.set_signal("E-R", e_r);
         self.neuro_map.set_signal("E-G", e_g);
         self.neuro_map.set_signal("E-B", e_b);
-        self.pain = clamp(self.pain - get_settings().neuro_duration/2.0, 0.0, 1.0);
+        self.neuro_map.set_signal("SEA", self.season);
+        self.neuro_map.set_signal("DAY", self.daylight);
+        let dir = Vec2::from_angle(self.rot);
+        self.neuro_map.set_signal("ALT", self.altitude as f32 / 100.0);
+        self.neuro_map.set_signal("SLP", clamp(dir.dot(self.slope) / 10.0, -1.0, 1.0));
+        self.neuro_map.set_signal("SLR", clamp(dir.perp().dot(self.slope) / 10.0, -1.0, 1.0));
+        for (ch, (intensity, side)) in SCENT_SENSORS.iter().enumerate() {
+            let scent = self.scent[ch];
+            self.neuro_map.set_signal(intensity, scent / (1.0 + scent));
+            self.neuro_map.set_signal(side, clamp(dir.perp().dot(self.scent_gradient[ch]) / (1.0 + scent), -1.0, 1.0));
+        }
+        self.neuro_map.set_signal("HEA", self.hearing);
+        self.neuro_map.set_signal("HDR", self.hearing_dir);
+        self.pain = clamp(self.pain - settings.neuro_duration/2.0, 0.0, 1.0);
         
     }
 
-    fn analize(&mut self) {
+    fn analize(&mut self, settings: &Settings) {
 
         self.network.deactivate_nodes();
-        self.prep_input();
+        self.prep_input(settings);
         self.neuro_map.send_signals(&mut self.network);
         self.network.calc();
         self.neuro_map.recv_actions(&self.network);
@@ -702,151 +980,21 @@ impl Agent {
         self.mood.r = (self.mood.r+r)/2.0;
         self.mood.g = (self.mood.g+g)/2.0;
         self.mood.b = (self.mood.b+b)/2.0;
-    }
-
-    fn draw_front(&self) {
-        let mut yaw_color = LIGHTGRAY;
-        let mut left: Vec2 = Vec2::from_angle(self.rot-PI/10.0);
-        let mut right: Vec2 = Vec2::from_angle(self.rot+PI/10.0);
-        let l0 = self.pos + left * self.size;
-        let r0 = self.pos + right * self.size;
-        let mut l1 = self.pos + left * self.size*1.5;
-        let mut r1 = self.pos + right * self.size*1.5;
-        if self.attacking {
-            yaw_color = RED;
-            if self.attack_visual {
-                l1 = self.pos + left * self.size*2.0;
-                r1 = self.pos + right * self.size*2.0;
-            }
-            draw_line(l0.x, l0.y, l1.x, l1.y, self.size/3.0, yaw_color);
-            draw_line(r0.x, r0.y, r1.x, r1.y, self.size/3.0, yaw_color);
-        } else if self.eating {
-            yaw_color = BLUE;
-            if self.eat_visual {
-                left = Vec2::from_angle(self.rot-PI/18.0);
-                right = Vec2::from_angle(self.rot+PI/18.0);
-            }
-            l1 = self.pos + left * self.size*1.1;
-            r1 = self.pos + right * self.size*1.1;
-            draw_circle(l1.x, l1.y, self.size*0.33, yaw_color);
-            draw_circle(r1.x, r1.y, self.size*0.33, yaw_color);
-        } else {
-            draw_line(l0.x, l0.y, l1.x, l1.y, self.size/3.0, yaw_color);
-            draw_line(r0.x, r0.y, r1.x, r1.y, self.size/3.0, yaw_color);
-        }
-    }
-
-    fn draw_eyes(&self, selected: bool) {
-        let ang = self.vision_angle/2.0;
-        let mut small_vision = get_settings().agent_vision_range*get_settings().peripheral_vision;
-        let range = self.vision_range;
-        small_vision = clamp(small_vision, 0.0, range);
-        let left_vision_border = Vec2::from_angle(self.rot - ang);
-        let right_vision_border = Vec2::from_angle(self.rot + ang);
-        let mut color = LIGHTGRAY;
-        if self.eating { color = BLUE; }
-        if self.attacking { color = RED; }
-        let eye_l = Vec2::from_angle(self.rot - PI / 3.0) * self.size*0.66;
-        let eye_r = Vec2::from_angle(self.rot + PI / 3.0) * self.size*0.66;
-        let xl = self.pos.x + eye_l.x;
-        let yl = self.pos.y + eye_l.y;
-        let xr = self.pos.x + eye_r.x;
-        let yr = self.pos.y + eye_r.y;
-        let s = self.size*0.33;
-        let vl0 = self.pos + left_vision_border*small_vision;
-        let vr0 = self.pos + right_vision_border*small_vision;
-        let vl1 = self.pos + left_vision_border*range;
-        let vr1 = self.pos + right_vision_border*range;
-        draw_circle(xl, yl, s, color);
-        draw_circle(xr, yr, s, color);
-        if selected {
-            draw_line(vl0.x, vl0.y, vl1.x, vl1.y, 0.5, SKYBLUE);
-            draw_line(vr0.x, vr0.y, vr1.x, vr1.y, 0.5, SKYBLUE);
-            draw_smooth_arc(range, self.pos, self.rot, self.vision_angle/2.0, 10.0, 0.5, SKYBLUE);
-            draw_smooth_arc(small_vision, self.pos, self.rot+PI, PI-ang, 10.0, 0.5, SKYBLUE);
-        }
-    }
-
-    fn draw_target(&self, _selected: bool) {
-        self.enemy.inspect(|_| {
-            self.enemy_position.inspect(|enemy_position| {
-                let v0l = Vec2::from_angle(self.rot - PI / 2.0) * self.size;
-                let v0r = Vec2::from_angle(self.rot + PI / 2.0) * self.size;
-                let x0l = self.pos.x + v0l.x;
-                let y0l = self.pos.y + v0l.y;
-                let x0r = self.pos.x + v0r.x;
-                let y0r = self.pos.y + v0r.y;
-                let x1 = enemy_position.x;
-                let y1 = enemy_position.y;
-                draw_line(x0l, y0l, x1, y1, 2.0, self.color);
-                draw_line(x0r, y0r, x1, y1, 2.0, self.color);
-            });
-        });
-        self.plant.inspect(|_| {
-            self.plant_position.inspect(|plant_position| {
-                let v0l = Vec2::from_angle(self.rot - PI / 2.0) * self.size;
-                let v0r = Vec2::from_angle(self.rot + PI / 2.0) * self.size;
-                let x0l = self.pos.x + v0l.x;
-                let y0l = self.pos.y + v0l.y;
-                let x0r = self.pos.x + v0r.x;
-                let y0r = self.pos.y + v0r.y;
-                let x1 = plant_position.x;
-                let y1 = plant_position.y;
-                draw_line(x0l, y0l, x1, y1, 1.0, self.color);
-                draw_line(x0r, y0r, x1, y1, 1.0, self.color);
-            });
-        });
-    }
-
-    fn draw_info(&self, font: &Font) {
-        let settings = get_settings();
-        let x0 = self.pos.x;
-        let y0 = self.pos.y;
-        let text_cfg = TextParams {
-            font: *font,
-            font_size: 10,
-            color: WHITE,
-            ..Default::default()
-        };
-        //let mut info: String;
-        let mut info = "".to_string();
-        let mut info_gen = "".to_string();
-        if settings.show_specie {
-            info = format!("{}", self.specie.to_uppercase());
-        } 
-        if settings.show_generation {
-            info_gen = format!("{}", self.generation);
-        }
-        let mut row = 1;
-        if settings.show_specie {
-            let txt_center = get_text_center(&info, Some(*font), 10, 1.0, 0.0);
-            draw_text_ex(&info, x0 - txt_center.x, y0 - txt_center.y + self.size * 2.0 + 10.0, text_cfg.clone());
-            row += 1;
-        }
-        if settings.show_generation {
-            let txt_center = get_text_center(&info_gen, Some(*font), 10, 1.0, 0.0);
-            draw_text_ex(&info_gen, x0 - txt_center.x, y0 - txt_center.y + self.size * 2.0 + (10.0*row as f32), text_cfg.clone());
+        for (ch, label) in SCENT_EFFECTORS.iter().enumerate() {
+            self.emission[ch] = clamp(self.neuro_map.get_action(label), 0.0, 1.0);
         }
+        self.sound = clamp(self.neuro_map.get_action("SPK"), 0.0, 1.0);
     }
 
-    fn draw_status_bar(&self, percent: f32, color1: Color, color2: Color, offset: Vec2) {
-        let xc = self.pos.x + offset.x; let yc = self.pos.y + offset.y;
-        let x0 = xc-10.0; let y0 = yc -0.75;
-        let w = 20.0*percent;
-        draw_rectangle(x0, y0, 20.0, 1.5, color2);
-        draw_rectangle(x0, y0, w, 1.5, color1);
-    }
-
-    fn update_physics(&mut self, physics: &mut Physics) {
-        let settings = get_settings();
-        self.update_enemy_position(physics);
-        let physics_data = physics.get_object_state(self.rbh);
+    fn update_physics(&mut self, physics: &mut Physics, settings: &Settings) {
+        self.update_enemy_position(physics, settings);
+        let physics_data = physics.get_object_state(self.rbh, settings);
         self.pos = physics_data.position;
         self.rot = physics_data.rotation;
         self.mass = physics_data.mass;
         match physics.get_object_mut(self.rbh) {
             Some(body) => {
-                let dt = dt()*sim_speed();
+                let dt = dt();
                 let dir = Vec2::from_angle(self.rot);
                 let v =(self.speed as f32 + 15.0-(self.shell as f32*0.25)) * settings.agent_speed;
                 let mut vel = dir * self.vel * v * dt;
@@ -857,19 +1005,27 @@ impl Agent {
                 if self.run && self.water == 0 {
                     vel *= 1.5;
                 }
+                vel = vel / (1.0 + settings.slope_speed * self.slope.length());
                 let rot = (self.ang_vel * (settings.agent_rotate/(self.shell as f32 * 0.5))) * dt ;
                 body.set_linvel(Vector2::new(vel.x, vel.y), true);
                 body.set_angvel(rot, true);
-                self.check_edges(body);
+                self.check_edges(body, settings);
                 //self.move_limbs(physics);
             }
             None => {}
         }
     }
 
-    fn check_edges(&mut self, body: &mut RigidBody) {
-        let settings = get_settings();
+    fn check_edges(&mut self, body: &mut RigidBody, settings: &Settings) {
         let (mut raw_pos, rot ) = iso_to_vec2_rot(body.position());
+        if settings.topology == WorldTopology::TORUS {
+            let wrapped = wrap_around(&raw_pos, settings);
+            if wrapped != raw_pos {
+                body.set_position(make_isometry(wrapped.x, wrapped.y, rot), true);
+                self.pos = wrapped;
+            }
+            return;
+        }
         let mut out_of_edge: f32 = 0.0;
         if raw_pos.x <= 0.0 {
             raw_pos.x = 0.0;
@@ -893,11 +1049,12 @@ impl Agent {
         }
     }
 
-    fn update_enemy_position(&mut self, physics: &Physics) {
+    #[doc = r"Positions of enemy and plant are stored as seen from agent, so in torus world they may lie outside world edges."]
+    fn update_enemy_position(&mut self, physics: &Physics, settings: &Settings) {
         if let Some(rb) = self.enemy {
             if let Some(enemy_position) = physics.get_object_position(rb) {
-                self.enemy_position = Some(enemy_position);
-                let rel_pos = enemy_position - self.pos;
+                let rel_pos = world_offset(&self.pos, &enemy_position, settings);
+                self.enemy_position = Some(self.pos + rel_pos);
                 let enemy_dir = rel_pos.angle_between(Vec2::from_angle(self.rot))/(1.0*PI);
                 self.enemy_dir = Some(enemy_dir);
                 if let Some(enemy_size) = physics.get_object_size(rb) {
@@ -914,8 +1071,8 @@ impl Agent {
         }
         if let Some(rb) = self.plant {
             if let Some(plant_position) = physics.get_object_position(rb) {
-                self.plant_position = Some(plant_position);
-                let rel_pos = plant_position - self.pos;
+                let rel_pos = world_offset(&self.pos, &plant_position, settings);
+                self.plant_position = Some(self.pos + rel_pos);
                 let plant_dir = rel_pos.angle_between(Vec2::from_angle(self.rot))/(1.0*PI);
                 self.plant_dir = Some(plant_dir);
             } else {
@@ -929,62 +1086,7 @@ impl Agent {
         }
     }
 
-    fn update_contacts(&mut self, other: &HashMap<RigidBodyHandle, Agent>, physics: &mut Physics) {
-        self.contacts_clear();
-        let contacts = physics.get_contacts_set(self.rbh, self.size);
-        for contact in contacts {
-            if other.contains_key(&contact) {
-                self.contact_agent = true;
-            } else {
-                self.contact_plant = true;
-            }
-            if let Some(pos2) = physics.get_object_position(contact) {
-                let mut rel_pos = pos2 - self.pos;
-                rel_pos = rel_pos.normalize_or_zero();
-                let target_angle = rel_pos.angle_between(Vec2::from_angle(self.rot));
-                self.contacts.push((contact, target_angle));
-            }
-        }
-    }
-
-    fn contacts_clear(&mut self) {
-        self.contacts.clear();
-        self.contact_agent = false;
-        self.contact_plant = false;
-    }
-
-    fn watch(&mut self, physics: &Physics) {
-        let direction = Vec2::from_angle(self.rot);
-        if let Some(tg) = physics.get_closest_agent(
-            self.rbh, 
-            self.vision_range, 
-            self.vision_angle, 
-            direction
-        ) {
-            self.enemy = Some(tg);
-        } else {
-            self.enemy_family = None;
-            self.enemy = None;
-            self.enemy_position = None;
-            self.enemy_dir = None;
-        }
-        if let Some(tg) = physics.get_closest_plant(
-            self.rbh, 
-            self.vision_range, 
-            self.vision_angle, 
-            direction
-        ) {
-            self.plant = Some(tg);
-        } else {
-            self.plant = None;
-            self.plant_position = None;
-            self.plant_dir = None;
-        }
-        self.update_enemy_position(physics);
-    }
-
-    fn calc_energy(&mut self) {
-        let settings = get_settings();
+    fn calc_energy(&mut self, settings: &Settings) {
         let base_cost = settings.base_energy_cost;
         let move_cost = settings.move_energy_cost;
         let attack_cost = settings.attack_energy_cost;
@@ -999,6 +1101,8 @@ impl Agent {
         if self.run {
             move_loss *= 2.0;
         }
+        move_loss *= self.biome.move_cost();
+        move_loss += self.climb().max(0.0) * size_cost * settings.climb_energy_cost;
         let attack_loss = match self.attacking {
             true => attack_cost * self.power as f32,
             false => 0.0,
@@ -1015,9 +1119,9 @@ impl Agent {
         self.check_alife();
     }
 
-    fn calc_health(&mut self) {
+    fn calc_health(&mut self, settings: &Settings) {
         let e = self.eng/self.max_eng;
-        if e >= get_settings().eng_bias {
+        if e >= settings.eng_bias {
             self.hp += e * dt() * 1.0;
         } else {
             self.hp += (e - 1.0) * dt() * 2.5; 
@@ -1036,6 +1140,11 @@ impl Agent {
         return !self.alife;
     }
 
+    #[doc = r"Energy bound in body, paid by parents at birth and returned to world as carcass and nutrients after death."]
+    pub fn body_eng(&self, settings: &Settings) -> f32 {
+        return self.max_eng * settings.body_eng;
+    }
+
     pub fn add_energy(&mut self, e: f32) {
         self.eng += e;
         if self.eng > self.max_eng {
@@ -1052,10 +1161,10 @@ impl Agent {
         self.check_alife();
     }
 
-    fn mutate_one(v: i32, m: f32) -> i32 {
+    fn mutate_one(v: i32, m: f32, rng: &mut SimRng) -> i32 {
         let mut vm: i32 = v;
-        if random_unit_unsigned() < m {
-            let r = rand::gen_range(0, 2);
+        if random_unit_unsigned(rng) < m {
+            let r = rng.gen_range(0, 2);
             if r == 1 {
                 vm += 1;
             } else if r == 0 {
@@ -1066,10 +1175,10 @@ impl Agent {
         return vm;
     }
 
-    fn mutate_one_clamp(v: i32, m: f32, min: i32, max: i32) -> i32 {
+    fn mutate_one_clamp(v: i32, m: f32, min: i32, max: i32, rng: &mut SimRng) -> i32 {
         let mut vm: i32 = v;
-        if random_unit_unsigned() < m {
-            let r = rand::gen_range(0, 2);
+        if random_unit_unsigned(rng) < m {
+            let r = rng.gen_range(0, 2);
             if r == 1 {
                 vm += 1;
             } else if r == 0 {
@@ -1080,43 +1189,82 @@ impl Agent {
         return vm;
     }
 
-    pub fn mutate(&mut self) {
-        let settings = get_settings();
+    pub fn mutate(&mut self, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) {
         let m = ((self.mutations - 5) as f32) / 20.0;
         let mut_rate = settings.mutations + settings.mutations * m;
-        self.mutations = Self::mutate_one(self.mutations, mut_rate);
+        self.mutations = Self::mutate_one(self.mutations, mut_rate, rng);
         self.size = Self::mutate_one_clamp(
             self.size as i32, 
             mut_rate, 
-            get_settings().agent_size_min, 
-            get_settings().agent_size_max
+            settings.agent_size_min, 
+            settings.agent_size_max,
+            rng,
         ) as f32;
-        self.power = Self::mutate_one(self.power, mut_rate);
-        self.speed = Self::mutate_one(self.speed, mut_rate);
-        self.shell = Self::mutate_one(self.shell, mut_rate);
-        self.eyes = Self::mutate_one(self.eyes, mut_rate);
-        self.network.mutate(m);
-        self.calc_hp();
+        self.power = Self::mutate_one(self.power, mut_rate, rng);
+        self.speed = Self::mutate_one(self.speed, mut_rate, rng);
+        self.shell = Self::mutate_one(self.shell, mut_rate, rng);
+        self.eyes = Self::mutate_one(self.eyes, mut_rate, rng);
+        self.ears = Self::mutate_one(self.ears, mut_rate, rng);
+        self.network.mutate(m, settings, mutations, rng);
+        self.calc_hp(settings);
         self.vision_angle = Self::calc_vision_angle(self.eyes);
-        self.vision_range = Self::calc_vision_range(self.eyes);
+        self.vision_range = Self::calc_vision_range(self.eyes, settings);
+        self.hearing_range = Self::calc_hearing_range(self.ears, settings);
     }
 
-    fn calc_hp(&mut self) {
-        let settings = get_settings();
+    fn calc_hp(&mut self, settings: &Settings) {
         let eng = self.size * settings.size_to_hp + settings.base_hp as f32;
         self.max_eng = eng;
         self.eng = eng*settings.born_eng;
     }
 
-    pub fn replicate(&self, physics: &mut Physics, time: f64) -> Agent {
+    pub fn replicate(&self, physics: &mut Physics, time: f64, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) -> Agent {
+        let network = self.network.replicate(rng);
+        return self.offspring(None, network, physics, time, settings, mutations, rng);
+    }
+
+    #[doc = r"Sexual reproduction: child network is crossover of both parents networks and each trait comes from random parent. Disjoint network genes are inherited from self."]
+    pub fn mate(&self, partner: &Agent, physics: &mut Physics, time: f64, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) -> Agent {
+        let sketch = self.network.get_sketch().crossover(&partner.network.get_sketch(), rng);
+        let network = sketch.from_sketch(rng);
+        return self.offspring(Some(partner), network, physics, time, settings, mutations, rng);
+    }
+
+    fn mix_gene<T: Copy>(gene1: T, gene2: T, rng: &mut SimRng) -> T {
+        if random_unit_unsigned(rng) < 0.5 {
+            return gene1;
+        } else {
+            return gene2;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn offspring(&self, partner: Option<&Agent>, network: Network, physics: &mut Physics, time: f64, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) -> Agent {
+        let (size, power, speed, shell, mutations_rate, eyes, ears, generation) = match partner {
+            Some(mate) => (
+                Self::mix_gene(self.size, mate.size, rng),
+                Self::mix_gene(self.power, mate.power, rng),
+                Self::mix_gene(self.speed, mate.speed, rng),
+                Self::mix_gene(self.shell, mate.shell, rng),
+                Self::mix_gene(self.mutations, mate.mutations, rng),
+                Self::mix_gene(self.eyes, mate.eyes, rng),
+                Self::mix_gene(self.ears, mate.ears, rng),
+                self.generation.max(mate.generation) + 1,
+            ),
+            None => (self.size, self.power, self.speed, self.shell, self.mutations, self.eyes, self.ears, self.generation + 1),
+        };
+        let mut ancestors = self.ancestors.to_owned();
+        if let Some(mate) = partner {
+            ancestors.add_mate(Ancestor::new(&mate.specie, mate.generation as i32, time.round() as i32));
+        }
         let color = self.color.to_owned();
         let color_second = self.color_second.to_owned();
-        let shape = SharedShape::ball(self.size);
+        let shape = SharedShape::ball(size);
         let rot = 0.0; //random_rotation();
-        let pos = self.pos + random_unit_vec2()*100.0;
+        let pos = self.pos + random_unit_vec2(rng)*100.0;
         let interactions = InteractionGroups::new(
             Group::GROUP_1, 
-            Group::GROUP_2 | Group::GROUP_1 
+            Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
         );
         let rbh = physics.add_dynamic_object(
             &pos, 
@@ -1126,7 +1274,6 @@ impl Agent {
             interactions, 
             false
         );
-        let network = self.network.replicate();
         let input_pairs = network.get_input_pairs();
         let output_pairs = network.get_output_pairs();
         let mut neuro_map = NeuroMap::new();
@@ -1138,9 +1285,10 @@ impl Agent {
             mass: 0.0,
             vel: 0.0,
             ang_vel: 0.0,
-            size: self.size,
+            size,
             vision_range: 0.0,
             vision_angle: 0.0,
+            hearing_range: 0.0,
             max_hp: 100.0,
             hp: 100.0,
             max_eng: 0.0,
@@ -1154,7 +1302,7 @@ impl Agent {
             alife: true,
             lifetime: 0.0,
             repro_time: 0.0,
-            generation: self.generation + 1,
+            generation,
             enemy: None,
             enemy_family: None,
             enemy_position: None,
@@ -1167,6 +1315,7 @@ impl Agent {
             contacts: Vec::new(),
             contact_agent: false,
             contact_plant: false,
+            contact_carcass: false,
             rbh,
             colliders: vec![],
             neuro_map,
@@ -1178,22 +1327,36 @@ impl Agent {
             points: 0.0,
             pain: 0.0,
             run: false,
-            power: self.power,
-            speed: self.speed,
-            shell: self.shell,
-            mutations: self.mutations,
-            eyes: self.eyes,
+            power,
+            speed,
+            shell,
+            mutations: mutations_rate,
+            eyes,
+            ears,
             mood: Color::new(0.0, 0.0, 0.0, 1.0),
-            ancestors: self.ancestors.to_owned(),
+            ancestors,
             eng_cost: EnergyCost::default(),
             blocked: 0.0,
             attack_visual: false,
             eat_visual: false,
             water: 0,
+            season: 0.5,
+            daylight: 0.5,
+            vision_scale: 1.0,
+            wall_dist: None,
+            altitude: 0,
+            slope: Vec2::ZERO,
+            scent: [0.0; SCENT_CHANNELS],
+            scent_gradient: [Vec2::ZERO; SCENT_CHANNELS],
+            emission: [0.0; SCENT_CHANNELS],
+            sound: 0.0,
+            hearing: 0.0,
+            hearing_dir: 0.0,
+            biome: Biome::default(),
+            killer: None,
         };
-        agent.mod_specie(time);
-        agent.mutate();
-        agent.calc_hp();
+        agent.mutate(settings, mutations, rng);
+        agent.calc_hp(settings);
         let yaw = SharedShape::ball(agent.size/3.0);
         let left: Vec2 = Vec2::from_angle(rot-PI-PI/2.5) * (agent.size)*1.0;
         let right: Vec2 = Vec2::from_angle(rot-PI+PI/2.5) * (agent.size)*1.0;
@@ -1205,7 +1368,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         let colh_right = physics.add_collider(
@@ -1216,7 +1379,7 @@ impl Agent {
             PhysicsMaterial::agent(), 
             InteractionGroups { 
                 memberships: Group::GROUP_1, 
-                filter: Group::GROUP_2 | Group::GROUP_1 
+                filter: Group::GROUP_2 | Group::GROUP_1 | Group::GROUP_3
             }
         );
         agent.colliders.push(colh_left);
@@ -1244,7 +1407,9 @@ impl Agent {
             shell: self.shell,
             mutations: self.mutations,
             eyes: self.eyes,
+            ears: self.ears,
             ancestors: self.ancestors.to_owned(),
+            state: None,
         }
     }
 
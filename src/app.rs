@@ -0,0 +1,409 @@
+//#![allow(unused)]
+
+use std::f32::consts::PI;
+use macroquad::prelude::*;
+use rapier2d::prelude::RigidBodyHandle;
+use evolve_core::sim::Simulation;
+use evolve_core::settings::*;
+use evolve_core::signals::*;
+use evolve_core::util::*;
+use evolve_core::obstacle::ObstacleShape;
+use crate::camera::*;
+use crate::dbg::MacroRapierDebugger;
+use crate::draw::*;
+use crate::gui_util::*;
+use crate::monit::PerformanceMonitor;
+use crate::net_draw::draw_network;
+use crate::ui::*;
+
+
+#[doc = r"Window frontend of simulation: rendering, camera, user input and egui interface."]
+pub struct App {
+    pub sim: Simulation,
+    pub font: Font,
+    pub camera: Camera2D,
+    pub ui: UISystem,
+    user_action: UserAction,
+    select_phase: f32,
+    pub selected: Option<RigidBodyHandle>,
+    pub mouse_state: MouseState,
+    wall_start: Option<Vec2>,
+    monitor: PerformanceMonitor,
+    n: usize,
+}
+
+impl App {
+
+    pub fn new(font: Font) -> Self {
+        let sim = Simulation::new(Settings::default());
+        let camera = create_camera(&sim.settings);
+        Self {
+            sim,
+            font,
+            camera,
+            ui: UISystem::new(),
+            user_action: UserAction::new(),
+            select_phase: 0.0,
+            selected: None,
+            mouse_state: MouseState { pos: Vec2::NAN },
+            wall_start: None,
+            monitor: PerformanceMonitor::new(1.0),
+            n: 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.check_signals();
+        self.check_settings();
+        self.sim.advance(get_frame_time());
+        self.calc_selection_time();
+        self.monitor.monitor();
+        self.sim.sim_state.fps = self.monitor.fps();
+        self.sim.sim_state.dt = self.monitor.dt();
+    }
+
+    fn check_signals(&mut self) {
+        if self.sim.signals.save_selected {
+            self.sim.signals.save_selected = false;
+            match self.selected {
+                Some(handle) => {
+                    self.sim.save_encoded_agent(handle);
+                },
+                None => {},
+            }
+        }
+        if self.sim.signals.new_sim || self.sim.signals.load_sim_name.is_some() {
+            self.selected = None;
+            self.select_phase = 0.0;
+        }
+    }
+
+    pub fn draw(&self) {
+        //set_default_camera();
+        set_camera(&self.camera);
+        clear_background(color_u8!(0,0,0,255));
+        draw_rectangle_lines(0.0, 0.0, self.sim.world_size.x, self.sim.world_size.y, 3.0, WHITE);
+        self.draw_terrain();
+        self.draw_obstacles();
+        self.draw_plants();
+        self.draw_carcasses();
+        //self.draw_grid();
+        self.draw_agents();
+        if self.sim.settings.show_network {
+            match self.selected {
+                Some(selected) => {
+                    match self.sim.agents.get(selected) {
+                        Some(selected_agent) => {
+                            let phase = self.sim.sim_state.sim_time % 1.0;
+                            draw_network(
+                                selected_agent, 
+                                phase as f32, 
+                                self.camera.target, 
+                                self.camera.zoom
+                            );
+                        },
+                        None => {},
+                    }
+                },
+                None => {},
+            }
+        }
+    }
+
+    pub fn debug_physic(&mut self) {
+        if self.sim.settings.debug {
+            self.sim.physics.debug_render(&mut MacroRapierDebugger);
+        }
+    }
+
+    pub fn draw_terrain(&self) {
+        let settings = &self.sim.settings;
+        self.sim.terrain.draw(settings.show_cells, settings.terrain_edit);
+        if settings.show_scent {
+            self.sim.terrain.draw_scent();
+        }
+    }
+
+    fn draw_obstacles(&self) {
+        for (_, obstacle) in self.sim.obstacles.get_iter() {
+            obstacle.draw();
+        }
+        if let Some(start) = self.wall_start {
+            let cursor = self.camera.screen_to_world(self.mouse_state.pos);
+            draw_line(start.x, start.y, cursor.x, cursor.y, 2.0, YELLOW);
+        }
+    }
+
+    fn draw_plants(&self) {
+        let settings = &self.sim.settings;
+        for (_, res) in self.sim.plants.get_iter() {
+            res.draw(settings.show_plant_rad);
+        }
+    }
+
+    fn draw_carcasses(&self) {
+        for (_, carcass) in self.sim.carcasses.get_iter() {
+            carcass.draw();
+        }
+    }
+
+    fn draw_agents(&self) {
+        for (id, agent) in self.sim.agents.get_iter() {
+            let mut draw_field_of_view: bool = false;
+            if self.selected.is_some() {
+                if *id == self.selected.unwrap() {
+                    draw_field_of_view = true;
+                };
+            }
+            agent.draw(draw_field_of_view, &self.font, &self.sim.physics, &self.sim.settings);
+        }
+
+        match self.selected {
+            Some(selected) => {
+                match self.sim.agents.get(selected) {
+                    Some(selected_agent) => {
+                        let pos = Vec2::new(selected_agent.pos.x, selected_agent.pos.y);
+                        let s = selected_agent.size;
+                        draw_circle_lines(
+                            pos.x,
+                            pos.y,
+                            2.0 * s + (self.select_phase.sin() * s * 0.5),
+                            1.0,
+                            ORANGE,
+                        );
+                    },
+                    None => {},
+                }
+            },
+            None => {},
+        }
+    }
+
+    pub fn input(&mut self) {
+        let (mouse_x, mouse_y) = mouse_position();
+        self.mouse_state.pos = Vec2::new(mouse_x, mouse_y);
+        let cursor = self.camera.screen_to_world(self.mouse_state.pos);
+        self.sim.terrain.set_brush_size(self.sim.settings.brush_size as u32);
+        self.sim.terrain.set_cursor_vec2(cursor);
+        self.mouse_input();
+        self.keyboard_input();
+        control_camera(&mut self.camera, &self.sim.settings);
+    }
+
+    fn keyboard_input(&mut self) {
+        if is_key_pressed(KeyCode::Tab) {
+            match self.sim.settings.select_mode {
+                SelectMode::RANDOM => {
+                    self.random_selection();
+                },
+                SelectMode::POINTS => {
+                    self.points_selection();
+                },
+                SelectMode::LIFETIME => {
+                    self.lifetime_selection();
+                },
+                _ => {
+                    self.random_selection();
+                }
+            }
+        }
+        if is_key_pressed(KeyCode::Kp6) {
+            let mut n = self.n + 1;
+            n = clamp(n, 0, self.sim.agents.count()-1);
+            self.select_n(n);
+        }
+        if is_key_pressed(KeyCode::Kp4) {
+            let mut n = self.n - 1;
+            n = clamp(n, 0, self.sim.agents.count()-1);
+            self.select_n(n);
+        }
+        if is_key_pressed(KeyCode::Kp5) {
+            self.select_n(0);
+        }
+    }
+
+    fn select_n(&mut self, n: usize) {
+        self.n = n;
+        match self.sim.agents.get_iter().nth(self.n) {
+            Some((id, _)) => {
+                self.selected = Some(*id);
+            },
+            None => {
+            },
+        }
+    }
+
+    fn mouse_input(&mut self) {
+        if !matches!(self.user_action, UserAction::WallAdd) {
+            self.wall_start = None;
+        }
+        match self.user_action {
+            UserAction::Idle => {
+                if is_mouse_button_released(MouseButton::Left) {
+                    if !self.ui.pointer_over {
+                        self.selected = None;
+                        let (mouse_posx, mouse_posy) = mouse_position();
+                        let mouse_pos = Vec2::new(mouse_posx, mouse_posy);
+                        let rel_coords = self.camera.screen_to_world(mouse_pos);
+                        for (id, agent) in self.sim.agents.get_iter() {
+                            if contact_mouse(rel_coords, agent.pos, agent.size) {
+                                self.selected = Some(*id);
+                                break;
+                            }
+                        }
+                        if self.selected.is_some() { return; }
+                        for (id, plant) in self.sim.plants.get_iter() {
+                            if contact_mouse(rel_coords, plant.get_base().pos, plant.get_base().size) {
+                                self.selected = Some(*id);
+                                break;
+                            }
+                        }
+                    }
+                }
+            },
+            UserAction::WaterAdd => {
+                if self.ui.pointer_over {
+
+                } else if is_mouse_button_released(MouseButton::Left) {
+                    self.sim.terrain.add_water_at_cursor(50);
+                } else if is_mouse_button_released(MouseButton::Right) {
+                    self.sim.terrain.add_water_at_cursor(-50);
+                }
+            },
+            UserAction::TerrainAdd => {
+                if self.ui.pointer_over {
+                } else if is_mouse_button_released(MouseButton::Left) {
+                    self.sim.terrain.add_terrain_at_cursor(10);
+                } else if is_mouse_button_released(MouseButton::Right) {
+                    self.sim.terrain.add_terrain_at_cursor(-10);
+                }
+            },
+            UserAction::WallAdd => {
+                let cursor = self.camera.screen_to_world(self.mouse_state.pos);
+                if self.ui.pointer_over {
+                } else if is_mouse_button_pressed(MouseButton::Left) {
+                    self.wall_start = Some(cursor);
+                } else if is_mouse_button_released(MouseButton::Left) {
+                    match self.wall_start.take() {
+                        Some(start) if start.distance(cursor) >= 5.0 => {
+                            self.sim.add_obstacle(ObstacleShape::Segment { a: [start.x, start.y], b: [cursor.x, cursor.y] });
+                        },
+                        _ => {},
+                    }
+                } else if is_mouse_button_released(MouseButton::Right) {
+                    self.sim.remove_obstacle_at(cursor, 10.0);
+                }
+            },
+            UserAction::SpringAdd => {
+                if self.ui.pointer_over {
+                } else if is_mouse_button_released(MouseButton::Left) {
+                    self.sim.terrain.toggle_spring_at_cursor(5.0);
+                } else if is_mouse_button_released(MouseButton::Right) {
+                    self.sim.terrain.toggle_spring_at_cursor(-5.0);
+                }
+            },
+            UserAction::BiomePaint(biome) => {
+                if self.ui.pointer_over {
+                } else if is_mouse_button_down(MouseButton::Left) {
+                    self.sim.terrain.paint_biome_at_cursor(Some(biome));
+                } else if is_mouse_button_down(MouseButton::Right) {
+                    self.sim.terrain.paint_biome_at_cursor(None);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn check_settings(&mut self) {
+        let follow_mode = self.sim.settings.follow_mode;
+        if follow_mode && self.selected.is_some() {
+            match self.selected {
+                None => {},
+                Some(sel) => {
+                    match self.sim.agents.get(sel) {
+                        None => {},
+                        Some(agent) => {
+                            let pos = agent.pos;
+                            self.camera.target = pos;
+                        },
+                    }
+                }
+            }
+        } else if follow_mode && self.selected.is_none() {
+            self.random_selection();
+        } else if self.selected.is_some() {
+            if !self.sim.agents.agents.contains_key(&self.selected.unwrap()) {
+                self.points_selection();
+            }
+        }
+    }
+
+    fn calc_selection_time(&mut self) {
+        self.select_phase += self.sim.sim_state.dt * 4.0;
+        self.select_phase = self.select_phase % (2.0 * PI as f32);
+    }
+
+    pub fn process_ui(&mut self) {
+        let selected_agent = match self.selected {
+            Some(selected) => {
+                self.sim.agents.get(selected)
+            },
+            None => None,
+        };
+        let selected_plant = match self.selected {
+            Some(selected) => {
+                self.sim.plants.get(selected)
+            },
+            None => None,
+        };
+        self.ui.ui_process(
+            self.sim.simulation_name.clone(),
+            &self.sim.sim_state, 
+            &mut self.sim.signals, 
+            &mut self.sim.settings,
+            &self.sim.mutation_stats,
+            &self.camera, 
+            selected_agent, 
+            selected_plant, 
+            &self.sim.ranking, 
+            &self.sim.statistics,
+            &mut self.user_action
+        );
+    }
+
+    pub fn draw_ui(&self) {
+        self.ui.ui_draw();
+    }
+
+    fn random_selection(&mut self) {
+        let n = self.sim.agents.count();
+        let r = rand::gen_range(0, n);
+        let keys: Vec<&RigidBodyHandle> = self.sim.agents.agents.keys().collect();
+        self.selected = Some(*keys[r]);
+    }
+
+    fn points_selection(&mut self) {
+        let mut selected: Option<RigidBodyHandle> = None;
+        let mut points = 0.0;
+        for (handle, agent) in self.sim.agents.get_iter() {
+            if agent.points > points {
+                selected = Some(*handle);
+                points = agent.points;
+            }
+        }
+        self.selected = selected;
+    }
+
+    fn lifetime_selection(&mut self) {
+        let mut selected: Option<RigidBodyHandle> = None;
+        let mut lifetime = 0.0;
+        for (handle, agent) in self.sim.agents.get_iter() {
+            if agent.lifetime > lifetime {
+                selected = Some(*handle);
+                lifetime = agent.points;
+            }
+        }
+        self.selected = selected;
+    }
+
+}
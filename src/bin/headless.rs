@@ -0,0 +1,12 @@
+use std::env;
+use std::process;
+use evolve_core::headless::*;
+
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(msg) = HeadlessConfig::from_args(&args).and_then(run_headless) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}
@@ -1,9 +1,8 @@
 
 use macroquad::prelude::*;
-use crate::settings::*;
+use evolve_core::settings::*;
 
-pub fn create_camera() -> Camera2D {
-    let settings = get_settings();
+pub fn create_camera(settings: &Settings) -> Camera2D {
     let world_w = settings.world_w as f32;
     let world_h = settings.world_h as f32;
     let camera2d = Camera2D {
@@ -20,8 +19,7 @@ pub fn create_camera() -> Camera2D {
     return camera2d;
 }
 
-pub fn control_camera(camera: &mut Camera2D /* , screen_ratio: f32 */) {
-    let settings = get_settings();
+pub fn control_camera(camera: &mut Camera2D, settings: &Settings /* , screen_ratio: f32 */) {
     let world_w = settings.world_w as f32;
     let world_h = settings.world_h as f32;
     if is_key_pressed(KeyCode::KpAdd) {
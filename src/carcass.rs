@@ -0,0 +1,149 @@
+//#![allow(unused)]
+
+use std::f32::consts::PI;
+use crate::math::*;
+use rapier2d::prelude::*;
+use crate::util::*;
+use crate::settings::*;
+use crate::phyx::physics::Physics;
+use crate::phyx::physics_misc::PhysicsMaterial;
+use crate::sketch::CarcassSketch;
+
+
+#[doc = r"Body left by dead agent. Can be eaten like plant and decays over time."]
+#[derive(Clone, Copy, Debug)]
+pub struct Carcass {
+    pub pos: Vec2,
+    pub rot: f32,
+    pub size: f32,
+    pub max_eng: f32,
+    pub eng: f32,
+    pub rbh: RigidBodyHandle,
+    decay: f32,
+}
+
+impl Carcass {
+
+    pub fn new(pos: Vec2, rot: f32, size: f32, eng: f32, physics: &mut Physics, settings: &Settings) -> Self {
+        let rbh = Self::add_body(pos, rot, size, physics);
+        Self {
+            pos,
+            rot,
+            size,
+            max_eng: eng,
+            eng,
+            rbh,
+            decay: eng / settings.carcass_lifetime.max(1.0),
+        }
+    }
+
+    #[doc = r"Carcass loaded from simulation save, with body where it was saved."]
+    pub fn from_sketch(sketch: &CarcassSketch, physics: &mut Physics) -> Self {
+        let rbh = Self::add_body(vec2(sketch.body[0], sketch.body[1]), sketch.body[2]-PI, sketch.size, physics);
+        Self {
+            pos: vec2(sketch.pos[0], sketch.pos[1]),
+            rot: sketch.rot,
+            size: sketch.size,
+            max_eng: sketch.max_eng,
+            eng: sketch.eng,
+            rbh,
+            decay: sketch.decay,
+        }
+    }
+
+    pub fn get_sketch(&self, physics: &Physics, settings: &Settings) -> CarcassSketch {
+        let body = physics.get_object_state(self.rbh, settings);
+        return CarcassSketch {
+            pos: [self.pos.x, self.pos.y],
+            rot: self.rot,
+            body: [body.position.x, body.position.y, body.rotation],
+            size: self.size,
+            max_eng: self.max_eng,
+            eng: self.eng,
+            decay: self.decay,
+        };
+    }
+
+    fn add_body(pos: Vec2, rot: f32, size: f32, physics: &mut Physics) -> RigidBodyHandle {
+        return physics.add_dynamic_object(
+            &pos,
+            rot,
+            SharedShape::ball(size),
+            PhysicsMaterial::carcass(),
+            InteractionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_3),
+            true
+        );
+    }
+
+    #[doc = r"Move with physics body and decay. Returns amount of energy lost by decay."]
+    pub fn update(&mut self, physics: &mut Physics, settings: &Settings) -> f32 {
+        let physics_data = physics.get_object_state(self.rbh, settings);
+        self.pos = physics_data.position;
+        self.rot = physics_data.rotation;
+        if settings.topology == WorldTopology::TORUS {
+            let wrapped = wrap_around(&self.pos, settings);
+            if wrapped != self.pos {
+                match physics.get_object_mut(self.rbh) {
+                    Some(body) => body.set_position(make_isometry(wrapped.x, wrapped.y, self.rot), true),
+                    None => {},
+                }
+                self.pos = wrapped;
+            }
+        }
+        let decayed = (self.decay * dt()).min(self.eng.max(0.0));
+        self.eng -= decayed;
+        return decayed;
+    }
+
+    pub fn drain_eng(&mut self, eng_loss: f32) {
+        self.eng -= eng_loss;
+    }
+
+    pub fn is_alive(&self) -> bool {
+        return self.eng > 0.0;
+    }
+}
+
+#[doc = r"Carcasses of dead agents present in simulation."]
+pub struct CarcassBox {
+    pub carcasses: StableMap<RigidBodyHandle, Carcass>,
+}
+
+impl Default for CarcassBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CarcassBox {
+
+    pub fn new() -> Self {
+        Self {
+            carcasses: StableMap::default(),
+        }
+    }
+
+    pub fn add_carcass(&mut self, carcass: Carcass) {
+        self.carcasses.insert(carcass.rbh, carcass);
+    }
+
+    pub fn get(&self, id: RigidBodyHandle) -> Option<&Carcass> {
+        return self.carcasses.get(&id);
+    }
+
+    pub fn contains(&self, id: &RigidBodyHandle) -> bool {
+        return self.carcasses.contains_key(id);
+    }
+
+    pub fn get_iter(&self) -> impl Iterator<Item = (&RigidBodyHandle, &Carcass)> {
+        return self.carcasses.iter();
+    }
+
+    pub fn get_iter_mut(&mut self) -> impl Iterator<Item = (&RigidBodyHandle, &mut Carcass)> {
+        return self.carcasses.iter_mut();
+    }
+
+    pub fn count(&self) -> usize {
+        return self.carcasses.len();
+    }
+}
@@ -1,17 +1,19 @@
 //#![allow(unused)]
 use std::collections::hash_map::{Iter, IterMut};
-use std::collections::HashMap;
 use crate::util::*;
+use crate::rng::SimRng;
 use crate::phyx::physics::Physics;
 use crate::agent::*;
 use crate::plant::*;
-use macroquad::prelude::*;
+use crate::terrain::Terrain;
 use rapier2d::prelude::RigidBodyHandle;
 use crate::settings::*;
+use crate::statistics::MutationStats;
+use crate::events::*;
+use crate::species::SpeciesRegistry;
 
 pub trait PhysicsObject {
     fn new() -> Self;
-    fn draw(&self, selected: bool, font: &Font);
     fn update(&mut self, dt: f32, physics: &mut Physics) -> bool;
     fn update_physics(&mut self, physics: &mut Physics);
     fn link_physics_handle(&mut self, handle: RigidBodyHandle);
@@ -19,14 +21,14 @@ pub trait PhysicsObject {
 
 #[derive(Clone)]
 pub struct AgentsSet {
-    agents: HashMap<RigidBodyHandle, Agent>,
+    agents: StableMap<RigidBodyHandle, Agent>,
 }
 
 impl AgentsSet {
 
     pub fn new() -> Self {
         Self {
-            agents: HashMap::new(),
+            agents: StableMap::default(),
         }
     }
 
@@ -42,11 +44,11 @@ impl AgentsSet {
         return self.agents.get_mut(rbh);
     }
 
-    pub fn get_iter(&self) -> Iter<RigidBodyHandle, Agent> {
+    pub fn get_iter(&self) -> Iter<'_, RigidBodyHandle, Agent> {
         return self.agents.iter();
     }
 
-    pub fn get_iter_mut(&mut self) -> IterMut<RigidBodyHandle, Agent> {
+    pub fn get_iter_mut(&mut self) -> IterMut<'_, RigidBodyHandle, Agent> {
         return self.agents.iter_mut();
     }
 
@@ -57,46 +59,72 @@ impl AgentsSet {
 }
 
 pub struct AgentBox {
-    pub agents: HashMap<RigidBodyHandle, Agent>,
+    pub agents: StableMap<RigidBodyHandle, Agent>,
 }
 
 impl AgentBox {
     pub fn new() -> Self {
         Self {
-            agents: HashMap::new(),
+            agents: StableMap::default(),
         }
     }
 
-    pub fn add_many_agents(&mut self, agents_num: usize, physics_world: &mut Physics) -> (i32, i32) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_many_agents(&mut self, agents_num: usize, physics_world: &mut Physics, time: f64, settings: &Settings, species: &mut SpeciesRegistry, rng: &mut SimRng, events: &mut EventQueue) -> (i32, i32) {
         let mut n = 0; let mut l = 0;
         for _ in 0..agents_num {
-            let agent = Agent::new(physics_world);
-            let (n0, l0) = self.add_agent(agent);
+            let mut agent = Agent::new(physics_world, settings, rng);
+            species.assign(&mut agent, time, settings, rng);
+            events.push(time, SimEvent::Birth { agent: agent.rbh, parent: None, partner: None, specie: agent.specie.to_owned(), pos: agent.pos, cause: BirthCause::Spontaneous });
+            let (n0, l0) = self.add_agent(agent, settings, rng);
             n += n0; l += l0;
         }
         return (n, l);
     }
 
-    pub fn populate(&mut self, physics: &mut Physics, time: f64) -> (i32, i32, i32) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn populate(&mut self, physics: &mut Physics, time: f64, settings: &Settings, mutations: &mut MutationStats, species: &mut SpeciesRegistry, rng: &mut SimRng, events: &mut EventQueue) -> (i32, i32, i32) {
         let mut counter: i32 = 0; let mut n = 0; let mut l = 0;
-        let settings = get_settings();
-        let mut newborns: Vec<Agent> = vec![];
-        for (_, agent) in self.get_iter_mut() {
-            if agent.repro_time >= settings.repro_time && (agent.eng/agent.max_eng) >= settings.born_eng_min {
-                let newbie = agent.replicate(physics, time).to_owned();
-                newborns.push(newbie);
-                agent.childs += 1;
-                agent.points += settings.repro_points;
-                agent.eng -= settings.born_eng_cost*agent.max_eng;
-                agent.repro_time = 0.0;
-            }
+        let mut newborns: Vec<(RigidBodyHandle, Option<RigidBodyHandle>, String, Agent)> = vec![];
+        match settings.reproduction {
+            ReproductionMode::ASEXUAL => {
+                for (rbh, agent) in self.get_iter_mut() {
+                    if agent.ready_to_reproduce(settings) {
+                        let newbie = agent.replicate(physics, time, settings, mutations, rng).to_owned();
+                        Self::pay_for_birth(agent, newbie.body_eng(settings), settings);
+                        newborns.push((*rbh, None, agent.specie.to_owned(), newbie));
+                    }
+                }
+            },
+            ReproductionMode::SEXUAL => {
+                for (parent, partner) in self.find_mates(settings) {
+                    let agent = &self.agents[&parent];
+                    let newbie = agent.mate(&self.agents[&partner], physics, time, settings, mutations, rng);
+                    let body = newbie.body_eng(settings);
+                    newborns.push((parent, Some(partner), agent.specie.to_owned(), newbie));
+                    for rbh in [parent, partner] {
+                        Self::pay_for_birth(self.agents.get_mut(&rbh).unwrap(), body/2.0, settings);
+                    }
+                }
+            },
         }
         loop {
             match newborns.pop() {
-                Some(newbie) => {
+                Some((parent, partner, parent_specie, mut newbie)) => {
                     counter += 1;
-                    let (n0, l0) = self.add_agent(newbie);
+                    species.assign(&mut newbie, time, settings, rng);
+                    let rbh = newbie.rbh;
+                    let (n0, l0) = self.add_agent(newbie, settings, rng);
                     n += n0; l += l0;
+                    let agent = &self.agents[&rbh];
+                    let cause = match partner {
+                        Some(_) => BirthCause::Mating,
+                        None => BirthCause::Reproduction,
+                    };
+                    events.push(time, SimEvent::Birth { agent: rbh, parent: Some(parent), partner, specie: agent.specie.to_owned(), pos: agent.pos, cause });
+                    if agent.specie != parent_specie {
+                        events.push(time, SimEvent::Speciation { agent: rbh, specie: agent.specie.to_owned(), ancestor_specie: parent_specie, pos: agent.pos });
+                    }
                 },
                 None => {
                     break;
@@ -106,11 +134,49 @@ impl AgentBox {
         return (counter, n, l);
     }
 
-    pub fn add_agent(&mut self, mut agent: Agent) -> (i32, i32) {
-        let settings = get_settings();
-        while agent.pos.x >= settings.world_w as f32 || agent.pos.y >= settings.world_h as f32 || agent.pos.x <= 0.0 || agent.pos.y <= 0.0 {
-            agent.pos = random_position(settings.world_w as f32, settings.world_h as f32);
-        }     
+    #[doc = r"Parent pays born_eng_cost share of its energy and its part of child body."]
+    fn pay_for_birth(agent: &mut Agent, body: f32, settings: &Settings) {
+        agent.childs += 1;
+        agent.points += settings.repro_points;
+        agent.eng -= settings.born_eng_cost*agent.max_eng + body;
+        agent.repro_time = 0.0;
+    }
+
+    #[doc = r"Pairs of touching agents ready to reproduce whose networks are within mating_distance compatibility distance. Agent with more points goes first in pair."]
+    fn find_mates(&self, settings: &Settings) -> Vec<(RigidBodyHandle, RigidBodyHandle)> {
+        let mut mates: Vec<(RigidBodyHandle, RigidBodyHandle)> = vec![];
+        let mut taken: StableSet<RigidBodyHandle> = StableSet::default();
+        for (rbh, agent) in self.get_iter() {
+            if taken.contains(rbh) || !agent.ready_to_reproduce(settings) {
+                continue;
+            }
+            let genome = agent.network.get_genome();
+            for (other, _) in agent.contacts.iter() {
+                if taken.contains(other) || other == rbh {
+                    continue;
+                }
+                match self.agents.get(other) {
+                    Some(partner) if partner.ready_to_reproduce(settings) && genome.distance(&partner.network.get_genome(), settings) <= settings.mating_distance => {
+                        taken.insert(*rbh);
+                        taken.insert(*other);
+                        if partner.points > agent.points {
+                            mates.push((*other, *rbh));
+                        } else {
+                            mates.push((*rbh, *other));
+                        }
+                        break;
+                    },
+                    _ => {},
+                }
+            }
+        }
+        return mates;
+    }
+
+    pub fn add_agent(&mut self, mut agent: Agent, settings: &Settings, rng: &mut SimRng) -> (i32, i32) {
+        if agent.pos.x >= settings.world_w as f32 || agent.pos.y >= settings.world_h as f32 || agent.pos.x <= 0.0 || agent.pos.y <= 0.0 {
+            agent.pos = random_position(settings.world_w as f32, settings.world_h as f32, rng);
+        }
         let nl_num = agent.get_nodes_links_num();       
         self.agents.insert(agent.rbh, agent); 
         return nl_num;
@@ -124,11 +190,11 @@ impl AgentBox {
         self.agents.remove(&id);
     }
 
-    pub fn get_iter(&self) -> Iter<RigidBodyHandle, Agent> {
+    pub fn get_iter(&self) -> Iter<'_, RigidBodyHandle, Agent> {
         return self.agents.iter();
     }
 
-    pub fn get_iter_mut(&mut self) -> IterMut<RigidBodyHandle, Agent> {
+    pub fn get_iter_mut(&mut self) -> IterMut<'_, RigidBodyHandle, Agent> {
         return self.agents.iter_mut();
     }
 
@@ -141,41 +207,43 @@ impl AgentBox {
 
 
 pub struct PlantBox {
-    pub plants: HashMap<RigidBodyHandle, Plant>,
+    pub plants: StableMap<RigidBodyHandle, Box<dyn PlantType>>,
 }
 
 impl PlantBox {
     pub fn new() -> Self {
         Self {
-            plants: HashMap::new(),
+            plants: StableMap::default(),
         }
     }
 
-    pub fn add_many_plants(&mut self, plants_num: usize, physics_world: &mut Physics) {
+    pub fn add_many_plants(&mut self, plants_num: usize, physics_world: &mut Physics, terrain: &Terrain, settings: &Settings, rng: &mut SimRng) {
         for _ in 0..plants_num {
             //let num = self.count() as i32;
-            let plant = Plant::new(physics_world);
+            let pos = terrain.random_plant_position(settings, rng);
+            let kind = PlantKind::random(settings, rng);
+            let plant = new_plant(kind, pos, physics_world, settings, rng);
             _ = self.add_plant(plant);
         }
     }
 
-    pub fn add_plant(&mut self, plant: Plant) {
+    pub fn add_plant(&mut self, plant: Box<dyn PlantType>) {
         self.plants.insert(plant.get_body_handle(), plant);
     }
 
-    pub fn get(&self, id: RigidBodyHandle) -> Option<&Plant> {
-        return self.plants.get(&id);
+    pub fn get(&self, id: RigidBodyHandle) -> Option<&dyn PlantType> {
+        return self.plants.get(&id).map(|plant| plant.as_ref());
     }
 
     pub fn remove(&mut self, id: RigidBodyHandle) {
         self.plants.remove(&id);
     }
 
-    pub fn get_iter(&self) -> Iter<RigidBodyHandle, Plant> {
+    pub fn get_iter(&self) -> Iter<'_, RigidBodyHandle, Box<dyn PlantType>> {
         return self.plants.iter();
     }
 
-    pub fn get_iter_mut(&mut self) -> IterMut<RigidBodyHandle, Plant> {
+    pub fn get_iter_mut(&mut self) -> IterMut<'_, RigidBodyHandle, Box<dyn PlantType>> {
         return self.plants.iter_mut();
     }
 
@@ -187,4 +255,68 @@ impl PlantBox {
 
 /* pub struct PlantsList<'a> {
     pub plants: &'a Vec<&'a impl PlantType>,
-} */
\ No newline at end of file
+} */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready_agent(physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Agent {
+        let mut agent = Agent::new(physics, settings, rng);
+        agent.repro_time = settings.repro_time;
+        agent.eng = agent.max_eng;
+        return agent;
+    }
+
+    #[test]
+    fn mates_are_chosen_by_network_distance_not_name() {
+        let settings = Settings::default();
+        let mut rng = SimRng::new(1);
+        let mut physics = Physics::new();
+        let mut parent = ready_agent(&mut physics, &settings, &mut rng);
+        let mut twin = ready_agent(&mut physics, &settings, &mut rng);
+        twin.network = parent.network.replicate(&mut rng);
+        let mut stranger = ready_agent(&mut physics, &settings, &mut rng);
+        stranger.specie = parent.specie.to_owned();
+        assert!(parent.network.compatibility(&stranger.network, &settings) > settings.mating_distance);
+        let (p, t, s) = (parent.rbh, twin.rbh, stranger.rbh);
+        parent.contacts = vec![(s, 0.0)];
+        let mut agents = AgentBox::new();
+        for agent in [parent, twin, stranger] {
+            agents.agents.insert(agent.rbh, agent);
+        }
+        assert!(agents.find_mates(&settings).is_empty());
+        agents.agents.get_mut(&p).unwrap().contacts = vec![(s, 0.0), (t, 0.0)];
+        let mates = agents.find_mates(&settings);
+        assert_eq!(mates.len(), 1);
+        assert!(mates[0] == (p, t) || mates[0] == (t, p));
+    }
+
+    #[test]
+    fn crossover_child_links_connect_its_own_nodes() {
+        let mut settings = Settings::default();
+        settings.mut_add_node = 0.5;
+        settings.mut_del_node = 0.3;
+        settings.mut_add_link = 0.5;
+        settings.mut_del_link = 0.3;
+        let mut rng = SimRng::new(2);
+        let mut physics = Physics::new();
+        let mut mutations = MutationStats::new(1.0, 1.0);
+        let founder = Agent::new(&mut physics, &settings, &mut rng);
+        let mut checked = 0;
+        for _ in 0..30 {
+            let mut a = founder.replicate(&mut physics, 0.0, &settings, &mut mutations, &mut rng);
+            let mut b = founder.replicate(&mut physics, 0.0, &settings, &mut mutations, &mut rng);
+            for _ in 0..3 {
+                a = a.replicate(&mut physics, 0.0, &settings, &mut mutations, &mut rng);
+                b = b.replicate(&mut physics, 0.0, &settings, &mut mutations, &mut rng);
+            }
+            let child = a.mate(&b, &mut physics, 0.0, &settings, &mut mutations, &mut rng);
+            for link in child.network.links.values() {
+                assert!(child.network.nodes.contains_key(&link.node_from) && child.network.nodes.contains_key(&link.node_to));
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+}
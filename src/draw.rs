@@ -0,0 +1,377 @@
+//#![allow(unused)]
+
+use std::f32::consts::PI;
+use macroquad::prelude::*;
+use evolve_core::agent::Agent;
+use evolve_core::plant::{PlantKind, PlantType};
+use evolve_core::terrain::{Terrain, SCENT_CHANNELS};
+use evolve_core::carcass::Carcass;
+use evolve_core::obstacle::*;
+use evolve_core::phyx::physics::Physics;
+use evolve_core::settings::*;
+use evolve_core::util::*;
+use crate::gui_util::*;
+
+
+pub trait AgentDraw {
+    fn draw(&self, selected: bool, font: &Font, physics: &Physics, settings: &Settings);
+    fn draw_limbs(&self, physics: &Physics);
+    fn draw_body(&self);
+    fn draw_front(&self);
+    fn draw_eyes(&self, selected: bool, settings: &Settings);
+    fn draw_target(&self, _selected: bool);
+    fn draw_info(&self, font: &Font, settings: &Settings);
+    fn draw_status_bar(&self, percent: f32, color1: Color, color2: Color, offset: Vec2);
+}
+
+impl AgentDraw for Agent {
+
+    fn draw(&self, selected: bool, font: &Font, physics: &Physics, settings: &Settings) {
+        if settings.agent_eng_bar {
+            let e = self.eng/self.max_eng;
+            let hp = self.hp/self.max_hp;
+            self.draw_status_bar(hp, GREEN, RED, Vec2::new(0.0, self.size*1.5+4.0));
+            self.draw_status_bar(e, SKYBLUE, YELLOW, Vec2::new(0.0, self.size*1.5+6.0));
+        }
+        self.draw_limbs(physics);
+        self.draw_body();
+        self.draw_front();
+        self.draw_eyes(selected, settings);
+        if selected {
+            self.draw_info(&font, settings);
+            self.draw_target(selected);
+        } else {
+            self.draw_info(&font, settings);
+        }
+    }
+
+    fn draw_limbs(&self, physics: &Physics) {
+        let colh_l = physics.core.colliders.get(self.colliders[0]).unwrap();
+        let colh_r = physics.core.colliders.get(self.colliders[1]).unwrap();
+        let rl = colh_l.shared_shape().as_ball().unwrap().radius;
+        let rr = colh_r.shared_shape().as_ball().unwrap().radius;
+        let (pos_l, _rot_l) = iso_to_vec2_rot(colh_l.position());
+        let (pos_r, _rot_r) = iso_to_vec2_rot(colh_r.position());
+        draw_circle(pos_l.x, pos_l.y, rl, GRAY);
+        draw_circle(pos_r.x, pos_r.y, rr, GRAY);
+    }
+
+    fn draw_body(&self) {
+        let x0 = self.pos.x;
+        let y0 = self.pos.y;
+        let rv = Vec2::from_angle(self.rot+PI);
+        let x1 = x0+rv.x*self.size;
+        let y1 = y0+rv.y*self.size;
+        let shell = self.size + (self.shell as f32)*0.4;
+        draw_circle(x0, y0, shell, LIGHTGRAY);
+        draw_circle(x1, y1, shell*0.6, LIGHTGRAY);
+        draw_circle(x1, y1, self.size*0.6, core_color(self.color_second));
+        draw_circle(x0, y0, self.size, core_color(self.color));
+        draw_circle(x0, y0, self.size/2.0, core_color(self.mood));
+        if self.run {
+            let mut shadow = core_color(self.color);
+            shadow.a = 0.6;
+            let xs0 = x0 + rv.x * self.size * self.vel;
+            let ys0 = y0 + rv.y * self.size * self.vel;
+            let xs1 = x1 + rv.x * self.size * 1.3 * self.vel;
+            let ys1 = y1 + rv.y * self.size * 1.3 * self.vel;
+            let xs2 = x1 + rv.x * self.size * 1.7 * self.vel;
+            let ys2 = y1 + rv.y * self.size * 1.7 * self.vel;
+            draw_circle(xs2, ys2, self.size*0.5, shadow);
+            draw_circle(xs1, ys1, self.size*0.8, shadow);
+            draw_circle(xs0, ys0, self.size, shadow);
+        }
+    }
+
+    fn draw_front(&self) {
+        let mut yaw_color = LIGHTGRAY;
+        let mut left: Vec2 = Vec2::from_angle(self.rot-PI/10.0);
+        let mut right: Vec2 = Vec2::from_angle(self.rot+PI/10.0);
+        let l0 = self.pos + left * self.size;
+        let r0 = self.pos + right * self.size;
+        let mut l1 = self.pos + left * self.size*1.5;
+        let mut r1 = self.pos + right * self.size*1.5;
+        if self.attacking {
+            yaw_color = RED;
+            if self.attack_visual {
+                l1 = self.pos + left * self.size*2.0;
+                r1 = self.pos + right * self.size*2.0;
+            }
+            draw_line(l0.x, l0.y, l1.x, l1.y, self.size/3.0, yaw_color);
+            draw_line(r0.x, r0.y, r1.x, r1.y, self.size/3.0, yaw_color);
+        } else if self.eating {
+            yaw_color = BLUE;
+            if self.eat_visual {
+                left = Vec2::from_angle(self.rot-PI/18.0);
+                right = Vec2::from_angle(self.rot+PI/18.0);
+            }
+            l1 = self.pos + left * self.size*1.1;
+            r1 = self.pos + right * self.size*1.1;
+            draw_circle(l1.x, l1.y, self.size*0.33, yaw_color);
+            draw_circle(r1.x, r1.y, self.size*0.33, yaw_color);
+        } else {
+            draw_line(l0.x, l0.y, l1.x, l1.y, self.size/3.0, yaw_color);
+            draw_line(r0.x, r0.y, r1.x, r1.y, self.size/3.0, yaw_color);
+        }
+    }
+
+    fn draw_eyes(&self, selected: bool, settings: &Settings) {
+        let ang = self.vision_angle/2.0;
+        let mut small_vision = settings.agent_vision_range*settings.peripheral_vision;
+        let range = self.vision_range;
+        small_vision = clamp(small_vision, 0.0, range);
+        let left_vision_border = Vec2::from_angle(self.rot - ang);
+        let right_vision_border = Vec2::from_angle(self.rot + ang);
+        let mut color = LIGHTGRAY;
+        if self.eating { color = BLUE; }
+        if self.attacking { color = RED; }
+        let eye_l = Vec2::from_angle(self.rot - PI / 3.0) * self.size*0.66;
+        let eye_r = Vec2::from_angle(self.rot + PI / 3.0) * self.size*0.66;
+        let xl = self.pos.x + eye_l.x;
+        let yl = self.pos.y + eye_l.y;
+        let xr = self.pos.x + eye_r.x;
+        let yr = self.pos.y + eye_r.y;
+        let s = self.size*0.33;
+        let vl0 = self.pos + left_vision_border*small_vision;
+        let vr0 = self.pos + right_vision_border*small_vision;
+        let vl1 = self.pos + left_vision_border*range;
+        let vr1 = self.pos + right_vision_border*range;
+        draw_circle(xl, yl, s, color);
+        draw_circle(xr, yr, s, color);
+        if selected {
+            draw_line(vl0.x, vl0.y, vl1.x, vl1.y, 0.5, SKYBLUE);
+            draw_line(vr0.x, vr0.y, vr1.x, vr1.y, 0.5, SKYBLUE);
+            draw_smooth_arc(range, self.pos, self.rot, self.vision_angle/2.0, 10.0, 0.5, SKYBLUE);
+            draw_smooth_arc(small_vision, self.pos, self.rot+PI, PI-ang, 10.0, 0.5, SKYBLUE);
+        }
+    }
+
+    fn draw_target(&self, _selected: bool) {
+        self.enemy.inspect(|_| {
+            self.enemy_position.inspect(|enemy_position| {
+                let v0l = Vec2::from_angle(self.rot - PI / 2.0) * self.size;
+                let v0r = Vec2::from_angle(self.rot + PI / 2.0) * self.size;
+                let x0l = self.pos.x + v0l.x;
+                let y0l = self.pos.y + v0l.y;
+                let x0r = self.pos.x + v0r.x;
+                let y0r = self.pos.y + v0r.y;
+                let x1 = enemy_position.x;
+                let y1 = enemy_position.y;
+                draw_line(x0l, y0l, x1, y1, 2.0, core_color(self.color));
+                draw_line(x0r, y0r, x1, y1, 2.0, core_color(self.color));
+            });
+        });
+        self.plant.inspect(|_| {
+            self.plant_position.inspect(|plant_position| {
+                let v0l = Vec2::from_angle(self.rot - PI / 2.0) * self.size;
+                let v0r = Vec2::from_angle(self.rot + PI / 2.0) * self.size;
+                let x0l = self.pos.x + v0l.x;
+                let y0l = self.pos.y + v0l.y;
+                let x0r = self.pos.x + v0r.x;
+                let y0r = self.pos.y + v0r.y;
+                let x1 = plant_position.x;
+                let y1 = plant_position.y;
+                draw_line(x0l, y0l, x1, y1, 1.0, core_color(self.color));
+                draw_line(x0r, y0r, x1, y1, 1.0, core_color(self.color));
+            });
+        });
+    }
+
+    fn draw_info(&self, font: &Font, settings: &Settings) {
+        let x0 = self.pos.x;
+        let y0 = self.pos.y;
+        let text_cfg = TextParams {
+            font: *font,
+            font_size: 10,
+            color: WHITE,
+            ..Default::default()
+        };
+        //let mut info: String;
+        let mut info = "".to_string();
+        let mut info_gen = "".to_string();
+        if settings.show_specie {
+            info = format!("{}", self.specie.to_uppercase());
+        } 
+        if settings.show_generation {
+            info_gen = format!("{}", self.generation);
+        }
+        let mut row = 1;
+        if settings.show_specie {
+            let txt_center = get_text_center(&info, Some(*font), 10, 1.0, 0.0);
+            draw_text_ex(&info, x0 - txt_center.x, y0 - txt_center.y + self.size * 2.0 + 10.0, text_cfg.clone());
+            row += 1;
+        }
+        if settings.show_generation {
+            let txt_center = get_text_center(&info_gen, Some(*font), 10, 1.0, 0.0);
+            draw_text_ex(&info_gen, x0 - txt_center.x, y0 - txt_center.y + self.size * 2.0 + (10.0*row as f32), text_cfg.clone());
+        }
+    }
+
+    fn draw_status_bar(&self, percent: f32, color1: Color, color2: Color, offset: Vec2) {
+        let xc = self.pos.x + offset.x; let yc = self.pos.y + offset.y;
+        let x0 = xc-10.0; let y0 = yc -0.75;
+        let w = 20.0*percent;
+        draw_rectangle(x0, y0, 20.0, 1.5, color2);
+        draw_rectangle(x0, y0, w, 1.5, color1);
+    }
+
+}
+
+
+pub trait PlantDraw {
+    fn draw(&self, _show_range: bool);
+}
+
+impl PlantDraw for Box<dyn PlantType> {
+
+    fn draw(&self, _show_range: bool) {
+        let plant = self.get_base();
+        let x0 = plant.pos.x;
+        let y0 = plant.pos.y;
+        let size = plant.size;
+        let age = plant.time/plant.life_length;
+        let r = clamp(-0.25+(1.5-age), 0., 0.75,);
+        match self.get_kind() {
+            PlantKind::Grass => {
+                let color = Color::new(r, 1.0, 0.0, 1.0);
+                draw_circle(x0, y0, size, color);
+                draw_circle_lines(x0, y0, size, size/5.0, DARKGREEN);
+            },
+            PlantKind::Bush => {
+                let fill = clamp(plant.eng/plant.max_eng.max(1.0), 0., 1.);
+                let color = Color::new(r*0.5, 0.35+0.25*fill, 0.1, 1.0);
+                draw_circle(x0, y0, size, color);
+                draw_circle_lines(x0, y0, size, size/4.0, Color::new(0.3, 0.2, 0.05, 1.0));
+                if fill > 0.5 {
+                    for i in 0..3 {
+                        let v = Vec2::from_angle(plant.rot + i as f32 * 2.0 * PI / 3.0) * size * 0.5;
+                        draw_circle(x0 + v.x, y0 + v.y, (size/5.0).max(1.0), RED);
+                    }
+                }
+            },
+            PlantKind::Toxic => {
+                let color = Color::new(0.5+0.5*r, 0.2, 0.8, 1.0);
+                draw_circle(x0, y0, size, color);
+                draw_circle_lines(x0, y0, size, size/5.0, Color::new(0.3, 0.0, 0.4, 1.0));
+            },
+        }
+    }
+
+}
+
+
+pub trait CarcassDraw {
+    fn draw(&self);
+}
+
+impl CarcassDraw for Carcass {
+
+    fn draw(&self) {
+        let fresh = clamp(self.eng/self.max_eng, 0.0, 1.0);
+        let color = Color::new(0.4 + 0.4*fresh, 0.1, 0.1, 0.4 + 0.6*fresh);
+        draw_circle(self.pos.x, self.pos.y, self.size, color);
+        draw_circle_lines(self.pos.x, self.pos.y, self.size, self.size/5.0, MAROON);
+    }
+
+}
+
+
+pub trait ObstacleDraw {
+    fn draw(&self);
+}
+
+impl ObstacleDraw for Obstacle {
+
+    fn draw(&self) {
+        let outline = self.shape.outline();
+        let n = outline.len();
+        let edges = match self.shape {
+            ObstacleShape::Segment { .. } => 1,
+            ObstacleShape::Polygon { .. } => n,
+        };
+        for i in 0..edges {
+            let a = outline[i];
+            let b = outline[(i+1)%n];
+            draw_line(a.x, a.y, b.x, b.y, WALL_RADIUS*2.0, LIGHTGRAY);
+            draw_circle(a.x, a.y, WALL_RADIUS, LIGHTGRAY);
+            draw_circle(b.x, b.y, WALL_RADIUS, LIGHTGRAY);
+        }
+    }
+
+}
+
+
+pub trait TerrainDraw {
+    fn draw(&self, show_occupied: bool, edit: bool);
+    fn draw_scent(&self);
+}
+
+impl TerrainDraw for Terrain {
+
+    fn draw(&self, show_occupied: bool, edit: bool) {
+        for c in 0..self.cells.len() {
+            for r in 0..self.cells[c].len() {
+                let (terrain, water) = self.get_color(c, r);
+                let x0 = c as f32 * self.cell_size;
+                let y0 = r as f32 * self.cell_size;
+                draw_rectangle(x0, y0, self.cell_size, self.cell_size, core_color(terrain));
+                match water {
+                    None => {},
+                    Some(water) => {
+                        draw_rectangle(x0, y0, self.cell_size, self.cell_size, core_color(water));
+                    },
+                }
+            }
+        }
+        for spring in self.springs.iter() {
+            let color = match spring.flow >= 0.0 {
+                true => color_u8!(0, 200, 255, 220),
+                false => color_u8!(120, 80, 40, 220),
+            };
+            draw_circle_lines(spring.x, spring.y, self.cell_size/3.0, 2.0, color);
+        }
+        for cloud in self.clouds.iter() {
+            if cloud.radius > 0.0 {
+                draw_circle(cloud.pos.x, cloud.pos.y, cloud.radius, color_u8!(200, 200, 220, 40));
+            }
+        }
+        if show_occupied {
+            for occupied in self.occupied.iter() {
+                let x0 = occupied[0] as f32 * self.cell_size;
+                let y0 = occupied[1] as f32 * self.cell_size;
+                draw_rectangle_lines(x0, y0, self.cell_size, self.cell_size, 2.0, color_u8!(255, 0, 0, 255));
+            }
+        }
+        if edit {
+            match self.cursor {
+                None => {},
+                Some(_coord) => {
+                    for (cell_loc, i) in self.brushed.iter() {
+                        let x = cell_loc[0] as f32 * self.cell_size;
+                        let y = cell_loc[1] as f32 * self.cell_size;
+                        let a = clamp((100.0+155.0*i) as u8, 0, 255) as u8;
+                        draw_rectangle_lines(x, y, self.cell_size, self.cell_size, 2.0, color_u8!(0, 0, 255, a));
+                    }
+                },
+            }
+        }
+    }
+
+    fn draw_scent(&self) {
+        for c in 0..self.cells.len() {
+            for r in 0..self.cells[c].len() {
+                let cell = &self.cells[c][r];
+                let mut s = [0.0; SCENT_CHANNELS];
+                for ch in 0..SCENT_CHANNELS {
+                    let scent = cell.get_scent(ch);
+                    s[ch] = scent / (1.0 + scent);
+                }
+                let a = s.iter().cloned().fold(0.0, f32::max);
+                if a < 0.05 { continue; }
+                let color = Color::new(s[0], s[1], s[2], 0.6*a);
+                draw_rectangle(c as f32 * self.cell_size, r as f32 * self.cell_size, self.cell_size, self.cell_size, color);
+            }
+        }
+    }
+
+}
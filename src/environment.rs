@@ -0,0 +1,54 @@
+//#![allow(unused)]
+
+use std::f32::consts::PI;
+use crate::settings::*;
+
+
+#[doc = r"Environment clock driven by simulation time. Seasons and day/night cycles modulate plant growth and agent vision."]
+#[derive(Clone, Copy, Debug)]
+pub struct Environment {
+    #[doc = r"Phase of year: 0.0 is middle of winter, 1.0 is middle of summer."]
+    pub season: f32,
+    #[doc = r"Phase of day: 0.0 is midnight, 1.0 is noon."]
+    pub daylight: f32,
+    #[doc = r"Multiplier of plant growth and cloning."]
+    pub growth: f32,
+    #[doc = r"Multiplier of agent vision range."]
+    pub vision: f32,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+
+    pub fn new() -> Self {
+        Self {
+            season: 0.5,
+            daylight: 0.5,
+            growth: 1.0,
+            vision: 1.0,
+        }
+    }
+
+    pub fn update(&mut self, sim_time: f64, settings: &Settings) {
+        self.season = Self::phase(sim_time, settings.season_length);
+        self.daylight = Self::phase(sim_time, settings.day_length);
+        let season_amp = settings.season_amplitude.clamp(0.0, 1.0);
+        let day_amp = settings.day_amplitude.clamp(0.0, 1.0);
+        self.growth = 1.0 + season_amp * (2.0 * self.season - 1.0);
+        self.vision = 1.0 + day_amp * (2.0 * self.daylight - 1.0);
+    }
+
+    #[doc = r"Position in cycle mapped to 0.0-1.0 by cosine, so cycle starts at its middle (0.5) and rises. Zero length disables cycle."]
+    fn phase(sim_time: f64, length: f32) -> f32 {
+        if length <= 0.0 {
+            return 0.5;
+        }
+        let t = (sim_time % length as f64) as f32 / length;
+        return 0.5 - 0.5 * (2.0 * PI * t + PI / 2.0).cos();
+    }
+}
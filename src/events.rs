@@ -0,0 +1,172 @@
+//#![allow(unused)]
+
+use std::collections::VecDeque;
+use crate::math::*;
+use rapier2d::prelude::RigidBodyHandle;
+
+#[doc = r"Most events kept in queue. When nobody takes them, oldest are dropped."]
+pub const MAX_QUEUED_EVENTS: usize = 10_000;
+
+
+#[doc = r"How new agent appeared in simulation."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BirthCause {
+    Reproduction,
+    Mating,
+    Spontaneous,
+    FromRanking,
+    #[doc = r"Placed by scenario or user from saved agent or imported genome."]
+    Placed,
+}
+
+#[doc = r"Why agent was removed from simulation."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Killed,
+    Exhausted,
+    Lost,
+}
+
+#[doc = r"Something that happened in simulation during tick."]
+#[derive(Clone, Debug)]
+pub enum SimEvent {
+    Birth { agent: RigidBodyHandle, parent: Option<RigidBodyHandle>, partner: Option<RigidBodyHandle>, specie: String, pos: Vec2, cause: BirthCause },
+    Death { agent: RigidBodyHandle, specie: String, pos: Vec2, cause: DeathCause },
+    Kill { killer: RigidBodyHandle, killer_specie: String, victim: RigidBodyHandle, victim_specie: String, pos: Vec2 },
+    Speciation { agent: RigidBodyHandle, specie: String, ancestor_specie: String, pos: Vec2 },
+    PlantCloned { plant: RigidBodyHandle, parent: RigidBodyHandle, pos: Vec2 },
+    Autosave { sim_name: String, sim_time: f64 },
+}
+
+#[doc = r"Events collected by simulation with time of tick they happened in.
+Consumers take them with drain after advancing simulation. Events nobody took are cleared at start of next frame and every tick queue is capped to MAX_QUEUED_EVENTS.
+Queue also counts every pushed event, so statistics don't depend on consumers."]
+#[derive(Clone, Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<(f64, SimEvent)>,
+    totals: EventCounters,
+}
+
+impl EventQueue {
+
+    pub fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            totals: EventCounters::default(),
+        }
+    }
+
+    pub fn push(&mut self, sim_time: f64, event: SimEvent) {
+        self.totals.record(&event);
+        self.events.push_back((sim_time, event));
+    }
+
+    pub fn get_iter(&self) -> impl Iterator<Item = &(f64, SimEvent)> {
+        return self.events.iter();
+    }
+
+    #[doc = r"Takes all collected events out of queue, oldest first."]
+    pub fn drain(&mut self) -> impl Iterator<Item = (f64, SimEvent)> + '_ {
+        return self.events.drain(..);
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn count(&self) -> usize {
+        return self.events.len();
+    }
+
+    #[doc = r"Drops oldest events above limit."]
+    pub fn cap(&mut self, limit: usize) {
+        while self.events.len() > limit {
+            self.events.pop_front();
+        }
+    }
+
+    #[doc = r"Counts of events pushed since last reset, including ones already drained or dropped."]
+    pub fn totals(&self) -> &EventCounters {
+        return &self.totals;
+    }
+
+    pub fn reset_totals(&mut self) {
+        self.totals = EventCounters::default();
+    }
+}
+
+#[doc = r"Running totals of simulation events, e.g. for reports of headless run."]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventCounters {
+    pub births: usize,
+    pub matings: usize,
+    pub deaths: usize,
+    pub kills: usize,
+    pub speciations: usize,
+    pub plant_clones: usize,
+    pub autosaves: usize,
+}
+
+impl EventCounters {
+
+    pub fn record(&mut self, event: &SimEvent) {
+        match event {
+            SimEvent::Birth { cause, .. } => {
+                self.births += 1;
+                if *cause == BirthCause::Mating {
+                    self.matings += 1;
+                }
+            },
+            SimEvent::Death { .. } => self.deaths += 1,
+            SimEvent::Kill { .. } => self.kills += 1,
+            SimEvent::Speciation { .. } => self.speciations += 1,
+            SimEvent::PlantCloned { .. } => self.plant_clones += 1,
+            SimEvent::Autosave { .. } => self.autosaves += 1,
+        }
+    }
+
+    #[doc = r"Counts all events taken from queue."]
+    pub fn consume(&mut self, queue: &mut EventQueue) {
+        for (_, event) in queue.drain() {
+            self.record(&event);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(i: u32) -> RigidBodyHandle {
+        return RigidBodyHandle::from_raw_parts(i, 0);
+    }
+
+    #[test]
+    fn counters_consume_queue() {
+        let mut queue = EventQueue::new();
+        queue.push(1.0, SimEvent::Birth { agent: handle(1), parent: Some(handle(0)), partner: Some(handle(2)), specie: "Abo".to_string(), pos: Vec2::ZERO, cause: BirthCause::Mating });
+        queue.push(1.0, SimEvent::Birth { agent: handle(3), parent: None, partner: None, specie: "Abo".to_string(), pos: Vec2::ZERO, cause: BirthCause::Spontaneous });
+        queue.push(2.0, SimEvent::Kill { killer: handle(1), killer_specie: "Abo".to_string(), victim: handle(3), victim_specie: "Abo".to_string(), pos: Vec2::ZERO });
+        queue.push(2.0, SimEvent::Death { agent: handle(3), specie: "Abo".to_string(), pos: Vec2::ZERO, cause: DeathCause::Killed });
+        let mut counters = EventCounters::default();
+        counters.consume(&mut queue);
+        assert_eq!(queue.count(), 0);
+        assert_eq!(counters, EventCounters { births: 2, matings: 1, deaths: 1, kills: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn cap_drops_oldest_but_totals_count_all() {
+        let mut queue = EventQueue::new();
+        for i in 0..5 {
+            queue.push(i as f64, SimEvent::Death { agent: handle(i), specie: "Abo".to_string(), pos: Vec2::ZERO, cause: DeathCause::Exhausted });
+        }
+        queue.cap(2);
+        assert_eq!(queue.get_iter().map(|(time, _)| *time).collect::<Vec<f64>>(), vec![3.0, 4.0]);
+        assert_eq!(queue.totals().deaths, 5);
+        queue.drain().count();
+        assert_eq!(queue.totals().deaths, 5);
+        queue.reset_totals();
+        assert_eq!(queue.totals().deaths, 0);
+    }
+}
@@ -0,0 +1,490 @@
+//#![allow(unused)]
+
+use base64::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::neuro::*;
+use crate::sketch::*;
+use crate::misc::*;
+use crate::statistics::*;
+use crate::util::{StableMap, fnv_hash};
+
+const GENOME_VERSION: u8 = 1;
+const PRECISION: f32 = 1000.0;
+const MAX_TRAIT: i32 = 10;
+const MAX_SIZE: i32 = 40;
+const MAX_MEMORY: u64 = 50;
+
+fn quantize(v: f32) -> i32 {
+    return (v * PRECISION).round() as i32;
+}
+
+fn dequantize(v: i32) -> f32 {
+    return v as f32 / PRECISION;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeGene {
+    pub id: u64,
+    pub kind: u8,
+    pub label: String,
+    pub x: i32,
+    pub y: i32,
+    pub bias: i32,
+    pub memory: Option<(u32, i32)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LinkGene {
+    pub id: u64,
+    pub node_from: u64,
+    pub node_to: u64,
+    pub w: i32,
+}
+
+#[doc = r"Canonical genome of agent: heritable traits, colors, shape and network topology with weights.
+Genes are sorted by id and real values are stored with 0.001 precision, so equal agents have equal genomes which can be hashed and compared."]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AgentGenome {
+    pub specie: String,
+    pub shape: u8,
+    pub color: [u8; 4],
+    pub color_second: [u8; 4],
+    pub size: i32,
+    pub power: i32,
+    pub speed: i32,
+    pub shell: i32,
+    pub mutations: i32,
+    pub eyes: i32,
+    pub ears: i32,
+    pub nodes: Vec<NodeGene>,
+    pub links: Vec<LinkGene>,
+}
+
+fn neuron_type_code(node_type: &NeuronTypes) -> u8 {
+    return match node_type {
+        NeuronTypes::INPUT => 0,
+        NeuronTypes::DEEP => 1,
+        NeuronTypes::OUTPUT => 2,
+        NeuronTypes::ANY => 3,
+    };
+}
+
+fn neuron_type_from_code(code: u8) -> Option<NeuronTypes> {
+    return match code {
+        0 => Some(NeuronTypes::INPUT),
+        1 => Some(NeuronTypes::DEEP),
+        2 => Some(NeuronTypes::OUTPUT),
+        3 => Some(NeuronTypes::ANY),
+        _ => None,
+    };
+}
+
+fn shape_code(shape: &MyShapeType) -> u8 {
+    return match shape {
+        MyShapeType::Ball => 0,
+        MyShapeType::Cuboid => 1,
+        MyShapeType::Segment => 2,
+    };
+}
+
+fn shape_from_code(code: u8) -> MyShapeType {
+    return match code {
+        1 => MyShapeType::Cuboid,
+        2 => MyShapeType::Segment,
+        _ => MyShapeType::Ball,
+    };
+}
+
+fn color_to_bytes(color: &[f32; 4]) -> [u8; 4] {
+    return color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+}
+
+fn color_from_bytes(color: &[u8; 4]) -> [f32; 4] {
+    return color.map(|c| c as f32 / 255.0);
+}
+
+impl AgentGenome {
+
+    pub fn from_sketch(sketch: &AgentSketch) -> Self {
+        let mut nodes: Vec<NodeGene> = sketch.network.nodes.values().map(|node| {
+            NodeGene {
+                id: node.id,
+                kind: neuron_type_code(&node.node_type),
+                label: node.label.to_owned(),
+                x: node.pos.x.round() as i32,
+                y: node.pos.y.round() as i32,
+                bias: quantize(node.bias),
+                memory: node.memory.as_ref().map(|memory| {
+                    let (size, mem_rate) = memory.get_params();
+                    (size as u32, quantize(mem_rate))
+                }),
+            }
+        }).collect();
+        nodes.sort();
+        let mut links: Vec<LinkGene> = sketch.network.links.values().map(|link| {
+            LinkGene { id: link.id, node_from: link.node_from, node_to: link.node_to, w: quantize(link.w) }
+        }).collect();
+        links.sort();
+        return Self {
+            specie: sketch.specie.to_owned(),
+            shape: shape_code(&sketch.shape),
+            color: color_to_bytes(&sketch.color),
+            color_second: color_to_bytes(&sketch.color_second),
+            size: sketch.size.round() as i32,
+            power: sketch.power,
+            speed: sketch.speed,
+            shell: sketch.shell,
+            mutations: sketch.mutations,
+            eyes: sketch.eyes,
+            ears: sketch.ears,
+            nodes,
+            links,
+        };
+    }
+
+    #[doc = r"Sketch of new agent with this genome. Agent gets first generation, no points, unknown position and specie as its only ancestor."]
+    pub fn to_sketch(&self) -> AgentSketch {
+        let mut nodes: StableMap<u64, NodeSketch> = StableMap::default();
+        let mut links: StableMap<u64, LinkSketch> = StableMap::default();
+        let mut neuro_map = NeuroMap::new();
+        for gene in self.nodes.iter() {
+            let node_type = neuron_type_from_code(gene.kind).unwrap_or(NeuronTypes::ANY);
+            match node_type {
+                NeuronTypes::INPUT => neuro_map.add_sensor(&gene.label, gene.id),
+                NeuronTypes::OUTPUT => neuro_map.add_effector(&gene.label, gene.id),
+                _ => {},
+            }
+            nodes.insert(gene.id, NodeSketch {
+                id: gene.id,
+                pos: MyPos2 { x: gene.x as f32, y: gene.y as f32 },
+                bias: dequantize(gene.bias),
+                node_type,
+                label: gene.label.to_owned(),
+                memory: gene.memory.map(|(size, mem_rate)| MemStore::new(size as usize, dequantize(mem_rate))),
+                memory_type: gene.memory.is_some(),
+                lazy_num: 0,
+            });
+        }
+        for gene in self.links.iter() {
+            links.insert(gene.id, LinkSketch { id: gene.id, w: dequantize(gene.w), node_from: gene.node_from, node_to: gene.node_to });
+        }
+        let mut ancestors = Ancestors::new();
+        ancestors.add_ancestor(Ancestor::new(&self.specie, 0, 0));
+        return AgentSketch {
+            specie: self.specie.to_owned(),
+            generation: 0,
+            size: self.size as f32,
+            shape: shape_from_code(self.shape),
+            color: color_from_bytes(&self.color),
+            color_second: color_from_bytes(&self.color_second),
+            network: NetworkSketch { nodes, links },
+            pos: [-1.0, -1.0],
+            points: 0.0,
+            neuro_map,
+            power: self.power,
+            speed: self.speed,
+            shell: self.shell,
+            mutations: self.mutations,
+            eyes: self.eyes,
+            ears: self.ears,
+            ancestors,
+            state: None,
+        };
+    }
+
+    #[doc = r"Short hash of heritable genes. Specie name is skipped, so clones living under different names have equal fingerprints.
+It is FNV hash of encoded genes, so it stays the same between program versions."]
+    pub fn fingerprint(&self) -> u64 {
+        let mut bytes: Vec<u8> = vec![];
+        self.put_genes(&mut bytes);
+        return fnv_hash(&bytes);
+    }
+
+    #[doc = r"Encodes genome as short url-safe string. Numbers are stored as varints and links point to nodes by their position in genome."]
+    pub fn encode(&self) -> String {
+        let mut bytes: Vec<u8> = vec![GENOME_VERSION];
+        put_str(&mut bytes, &self.specie);
+        self.put_genes(&mut bytes);
+        return BASE64_URL_SAFE_NO_PAD.encode(bytes);
+    }
+
+    #[doc = r"Canonical bytes of all genes except specie name. Links to nodes missing in genome are skipped."]
+    fn put_genes(&self, bytes: &mut Vec<u8>) {
+        bytes.push(self.shape);
+        bytes.extend_from_slice(&self.color);
+        bytes.extend_from_slice(&self.color_second);
+        for v in [self.size, self.power, self.speed, self.shell, self.mutations, self.eyes, self.ears] {
+            put_int(bytes, v);
+        }
+        put_uint(bytes, self.nodes.len() as u64);
+        for node in self.nodes.iter() {
+            put_uint(bytes, node.id);
+            bytes.push(node.kind);
+            put_str(bytes, &node.label);
+            put_int(bytes, node.x);
+            put_int(bytes, node.y);
+            put_int(bytes, node.bias);
+            match node.memory {
+                Some((size, mem_rate)) => {
+                    bytes.push(1);
+                    put_uint(bytes, size as u64);
+                    put_int(bytes, mem_rate);
+                },
+                None => bytes.push(0),
+            }
+        }
+        let index: StableMap<u64, usize> = self.nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        let links: Vec<(&LinkGene, usize, usize)> = self.links.iter()
+            .filter_map(|link| Some((link, *index.get(&link.node_from)?, *index.get(&link.node_to)?)))
+            .collect();
+        put_uint(bytes, links.len() as u64);
+        for (link, from, to) in links {
+            put_uint(bytes, link.id);
+            put_uint(bytes, from as u64);
+            put_uint(bytes, to as u64);
+            put_int(bytes, link.w);
+        }
+    }
+
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let bytes = BASE64_URL_SAFE_NO_PAD.decode(code.trim().as_bytes())
+            .map_err(|e| format!("can't decode genome: {}", e))?;
+        return GenomeReader { bytes: &bytes, pos: 0 }.read_genome()
+            .ok_or_else(|| "can't decode genome: invalid or unsupported data".to_string());
+    }
+
+}
+
+fn put_uint(bytes: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let b = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes.push(b);
+            return;
+        }
+        bytes.push(b | 0x80);
+    }
+}
+
+fn put_int(bytes: &mut Vec<u8>, v: i32) {
+    put_uint(bytes, ((v << 1) ^ (v >> 31)) as u32 as u64);
+}
+
+fn put_str(bytes: &mut Vec<u8>, s: &str) {
+    put_uint(bytes, s.len() as u64);
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+struct GenomeReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> GenomeReader<'a> {
+
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        return Some(b);
+    }
+
+    fn uint(&mut self) -> Option<u64> {
+        let mut v: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+        return None;
+    }
+
+    fn int(&mut self) -> Option<i32> {
+        let v = u32::try_from(self.uint()?).ok()?;
+        return Some(((v >> 1) as i32) ^ -((v & 1) as i32));
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.uint()? as usize;
+        let s = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        return String::from_utf8(s.to_vec()).ok();
+    }
+
+    fn color(&mut self) -> Option<[u8; 4]> {
+        return Some([self.byte()?, self.byte()?, self.byte()?, self.byte()?]);
+    }
+
+    #[doc = r"Reads genome and checks that every value is in range agents can have, so hostile code can't build oversized or broken agent."]
+    fn read_genome(&mut self) -> Option<AgentGenome> {
+        if self.byte()? != GENOME_VERSION {
+            return None;
+        }
+        let specie = self.string()?;
+        let shape = self.byte()?;
+        if shape > 2 {
+            return None;
+        }
+        let color = self.color()?;
+        let color_second = self.color()?;
+        let mut traits = [0; 7];
+        for t in traits.iter_mut() {
+            *t = self.int()?;
+        }
+        if !(1..=MAX_SIZE).contains(&traits[0]) || traits[1..].iter().any(|t| !(0..=MAX_TRAIT).contains(t)) {
+            return None;
+        }
+        let nodes_num = self.uint()? as usize;
+        let mut nodes: Vec<NodeGene> = vec![];
+        for _ in 0..nodes_num {
+            let id = self.uint()?;
+            if nodes.iter().any(|n| n.id == id) {
+                return None;
+            }
+            let kind = self.byte()?;
+            neuron_type_from_code(kind)?;
+            let label = self.string()?;
+            let (x, y, bias) = (self.int()?, self.int()?, self.int()?);
+            let memory = match self.byte()? {
+                0 => None,
+                1 => {
+                    let size = self.uint()?;
+                    if !(1..=MAX_MEMORY).contains(&size) {
+                        return None;
+                    }
+                    Some((size as u32, self.int()?))
+                },
+                _ => return None,
+            };
+            nodes.push(NodeGene { id, kind, label, x, y, bias, memory });
+        }
+        let links_num = self.uint()? as usize;
+        let mut links: Vec<LinkGene> = vec![];
+        for _ in 0..links_num {
+            let id = self.uint()?;
+            let node_from = nodes.get(self.uint()? as usize)?.id;
+            let node_to = nodes.get(self.uint()? as usize)?.id;
+            let w = self.int()?;
+            links.push(LinkGene { id, node_from, node_to, w });
+        }
+        if self.pos != self.bytes.len() {
+            return None;
+        }
+        nodes.sort();
+        links.sort();
+        let [size, power, speed, shell, mutations, eyes, ears] = traits;
+        return Some(AgentGenome { specie, shape, color, color_second, size, power, speed, shell, mutations, eyes, ears, nodes, links });
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::phyx::physics::Physics;
+    use crate::rng::SimRng;
+    use crate::settings::Settings;
+
+    fn agent_genome(seed: u64) -> AgentGenome {
+        let mut rng = SimRng::new(seed);
+        let mut physics = Physics::new();
+        let agent = Agent::new(&mut physics, &Settings::default(), &mut rng);
+        return AgentGenome::from_sketch(&agent.get_sketch());
+    }
+
+    fn small_genome() -> AgentGenome {
+        return AgentGenome {
+            specie: "abcd".to_string(),
+            shape: 0,
+            color: [255, 0, 0, 255],
+            color_second: [0, 0, 255, 255],
+            size: 10,
+            power: 5,
+            speed: 4,
+            shell: 3,
+            mutations: 2,
+            eyes: 6,
+            ears: 7,
+            nodes: vec![
+                NodeGene { id: 1, kind: 0, label: "CON".to_string(), x: 0, y: 50, bias: 250, memory: None },
+                NodeGene { id: 2, kind: 1, label: String::new(), x: 50, y: 50, bias: -120, memory: Some((8, 500)) },
+                NodeGene { id: 3, kind: 2, label: "MOV".to_string(), x: 100, y: 50, bias: 0, memory: None },
+            ],
+            links: vec![
+                LinkGene { id: 10, node_from: 1, node_to: 2, w: 750 },
+                LinkGene { id: 11, node_from: 2, node_to: 3, w: -300 },
+            ],
+        };
+    }
+
+    #[test]
+    fn genome_round_trips_through_code_and_sketch() {
+        for seed in 0..5 {
+            let genome = agent_genome(seed);
+            assert!(!genome.links.is_empty());
+            let decoded = AgentGenome::decode(&genome.encode()).unwrap();
+            assert_eq!(decoded, genome);
+            assert_eq!(AgentGenome::from_sketch(&decoded.to_sketch()), genome);
+            assert_eq!(decoded.fingerprint(), genome.fingerprint());
+        }
+        let genome = small_genome();
+        assert_eq!(AgentGenome::from_sketch(&genome.to_sketch()), genome);
+    }
+
+    #[test]
+    fn encode_skips_links_to_missing_nodes() {
+        let mut genome = small_genome();
+        genome.links.push(LinkGene { id: 12, node_from: 1, node_to: 99, w: 100 });
+        let decoded = AgentGenome::decode(&genome.encode()).unwrap();
+        assert_eq!(decoded.links, small_genome().links);
+        assert_eq!(genome.fingerprint(), small_genome().fingerprint());
+    }
+
+    #[test]
+    fn decode_reports_invalid_code() {
+        assert!(AgentGenome::decode("not a genome!").is_err());
+        let code = small_genome().encode();
+        assert!(AgentGenome::decode(&code[..code.len()-4]).is_err());
+        assert!(AgentGenome::decode("").is_err());
+    }
+
+    #[test]
+    fn decode_rejects_hostile_values() {
+        let hostile: Vec<fn(&mut AgentGenome)> = vec![
+            |g| g.nodes[1].memory = Some((u32::MAX, 500)),
+            |g| g.nodes[1].memory = Some((0, 500)),
+            |g| g.size = 0,
+            |g| g.size = i32::MAX,
+            |g| g.power = -1,
+            |g| g.speed = 1000,
+            |g| g.shell = i32::MIN,
+            |g| g.eyes = 11,
+            |g| g.ears = -5,
+            |g| g.nodes[0].kind = 7,
+            |g| g.shape = 9,
+            |g| g.nodes[1].id = g.nodes[0].id,
+        ];
+        for spoil in hostile {
+            let mut genome = small_genome();
+            spoil(&mut genome);
+            assert!(AgentGenome::decode(&genome.encode()).is_err());
+        }
+        assert!(AgentGenome::decode(&small_genome().encode()).is_ok());
+    }
+
+    #[test]
+    fn fingerprint_is_fixed_and_ignores_specie() {
+        let genome = small_genome();
+        let mut renamed = genome.clone();
+        renamed.specie = "wxyz".to_string();
+        assert_eq!(renamed.fingerprint(), genome.fingerprint());
+        // saved fingerprints must not change with compiler or std version
+        assert_eq!(genome.fingerprint(), 0xBEDE84B4AD321E54);
+        renamed.links[0].w += 1;
+        assert_ne!(renamed.fingerprint(), genome.fingerprint());
+    }
+}
@@ -0,0 +1,126 @@
+#![allow(unused)]
+
+use std::f32::consts::PI;
+use egui_macroquad::egui::{Pos2, Color32};
+use macroquad::prelude::*;
+use rapier2d::prelude::*;
+use rapier2d::parry::query::contact;
+use evolve_core::util::make_isometry;
+
+
+pub fn contact_mouse(mouse_pos: Vec2, target_pos: Vec2, target_rad: f32) -> bool {
+    let v1 = Vec2::new(mouse_pos.x, mouse_pos.y);
+    let v2 = Vec2::new(target_pos.x, target_pos.y);
+    let pos1 = make_isometry(v1.x, v1.y, 0.0);
+    let pos2 = make_isometry(v2.x, v2.y, 0.0);
+    let ball1 = Ball::new(2.0);
+    let ball2 = Ball::new(target_rad);
+    match contact(&pos1, &ball1, &pos2, &ball2, 0.0).unwrap() {
+        Some(_) => true,
+        None => false,
+    }
+}
+
+pub fn vec2_to_uivec2(vec2: &Vec2) -> egui_macroquad::egui::Vec2 {
+    return egui_macroquad::egui::Vec2::new(vec2.x, vec2.y);
+}
+
+pub fn vec2_to_pos2(vec2: &egui_macroquad::egui::Vec2) -> Pos2 {
+    return Pos2 { x: vec2.x, y: vec2.y };
+}
+
+pub fn vec2_to_ivec2(vec2: &egui_macroquad::egui::Vec2) -> Vec2 {
+    return Vec2 { x: vec2.x, y: vec2.y };
+}
+
+pub fn ivec2_to_pos2(vec2: IVec2) -> Pos2 {
+    return Pos2 { x: vec2.x as f32, y: vec2.y as f32};
+}
+
+#[doc = r"Converts color of simulation object to drawable color."]
+pub fn core_color(color: evolve_core::math::Color) -> Color {
+    return Color::new(color.r, color.g, color.b, color.a);
+}
+
+pub fn color_to_color32(color: evolve_core::math::Color) -> Color32 {
+    let r = (color.r*255.0) as u8;
+    let g = (color.g*255.0) as u8;
+    let b = (color.b*255.0) as u8;
+    let af32 = clamp((color.a*255.0).round(), 0.0, 255.0);
+    let a = af32 as u8;
+    return Color32::from_rgba_unmultiplied(r, g, b, a);
+
+}
+
+pub struct MouseState {
+    pub pos: Vec2,
+}
+
+pub struct MyIcon {
+    pub small: [u8; 16*16*4],
+    pub medium: [u8; 32*32*4],
+    pub big: [u8; 64*64*4],
+}
+
+impl MyIcon {
+    pub fn color_filled(color: Color) -> Self {
+        let r = (color.r * 255.) as u8;
+        let g = (color.g * 255.) as u8;
+        let b = (color.b * 255.) as u8;
+        let a = (color.a * 255.) as u8;
+        let small = [r, g, b, a].repeat(16*16);
+        let medium = [r, g, b, a].repeat(32*32);
+        let big = [r, g, b, a].repeat(64*64);
+        let mut s: [u8; 16*16*4] = [0; 16*16*4]; 
+        let mut m: [u8; 32*32*4] = [0; 32*32*4]; 
+        let mut l: [u8; 64*64*4] = [0; 64*64*4]; 
+        for i in 0..s.len() {
+            s[i] = small[i];
+        }
+        for i in 0..m.len() {
+            m[i] = medium[i];
+        }
+        for i in 0..l.len() {
+            l[i] = big[i];
+        }
+        Self {
+            small: s,
+            medium: m,
+            big: l,
+        }
+    }
+}
+
+pub fn draw_smooth_circle(r: f32, center: Vec2, detail: f32, width: f32, color: Color) {
+    let o = PI * r * 2.0;
+    let s = o / detail;
+    let a = 2.0 * PI / s;
+    let mut angle = 0.0;
+    while angle <= 2.0*PI {
+        let p0 = center + Vec2::from_angle(angle) * r;
+        angle += a;
+        let p1 = center + Vec2::from_angle(angle) * r;
+        draw_line(p0.x, p0.y, p1.x, p1.y, width, color);
+    }
+    let p0 = center + Vec2::from_angle(angle) * r;
+    let p1 = center + Vec2::from_angle(0.0) * r;
+    draw_line(p0.x, p0.y, p1.x, p1.y, width, color);
+}
+
+pub fn draw_smooth_arc(r: f32, center: Vec2, rotation: f32, half_angle: f32, detail: f32, width: f32, color: Color) {
+    let rel_peri = (2.0*half_angle) / (2.0*PI);
+    let o = PI * r * 2.0 * rel_peri;
+    let s = o / (detail*rel_peri);
+    let a = 2.0 * half_angle / s;
+    let mut angle = rotation - half_angle;
+    while angle + a <= rotation + half_angle {
+        let p0 = center + Vec2::from_angle(angle) * r;
+        angle += a;
+        let p1 = center + Vec2::from_angle(angle) * r;
+        draw_line(p0.x, p0.y, p1.x, p1.y, width, color);
+    }
+    angle = clamp(angle, rotation - half_angle, rotation + half_angle);
+    let p0 = center + Vec2::from_angle(angle) * r;
+    let p1 = center + Vec2::from_angle(rotation + half_angle) * r;
+    draw_line(p0.x, p0.y, p1.x, p1.y, width, color);
+}
@@ -0,0 +1,180 @@
+//#![allow(unused)]
+
+use std::path::Path;
+use std::str::FromStr;
+use crate::sim::*;
+use crate::settings::*;
+use crate::rng::SimRng;
+use crate::scenario::Scenario;
+use crate::events::EventCounters;
+
+
+#[doc = r"Options of simulation run without window, parsed from command line arguments:
+--headless [--settings <file>] [--scenario <file>] [--load <sim name>] [--name <name>] [--seed <num>] [--time <sim seconds> | --ticks <num>]"]
+pub struct HeadlessConfig {
+    pub settings_path: Option<String>,
+    pub scenario_path: Option<String>,
+    pub load_name: Option<String>,
+    pub sim_name: Option<String>,
+    pub seed: Option<u64>,
+    pub sim_time: Option<f64>,
+    pub ticks: Option<u64>,
+}
+
+impl HeadlessConfig {
+
+    #[doc = r"Reads options from arguments. Returns message for user when any argument is unknown, incomplete or has wrong value."]
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Self {
+            settings_path: None,
+            scenario_path: None,
+            load_name: None,
+            sim_name: None,
+            seed: None,
+            sim_time: None,
+            ticks: None,
+        };
+        let mut i = 1;
+        while i < args.len() {
+            let value = args.get(i+1);
+            match (args[i].as_str(), value) {
+                ("--headless", _) => {
+                    i += 1;
+                    continue;
+                },
+                ("--settings", Some(v)) => config.settings_path = Some(v.to_owned()),
+                ("--scenario", Some(v)) => config.scenario_path = Some(v.to_owned()),
+                ("--load", Some(v)) => config.load_name = Some(v.to_owned()),
+                ("--name", Some(v)) => config.sim_name = Some(v.to_owned()),
+                ("--seed", Some(v)) => config.seed = Some(parse_value(&args[i], v)?),
+                ("--time", Some(v)) => config.sim_time = Some(parse_value(&args[i], v)?),
+                ("--ticks", Some(v)) => config.ticks = Some(parse_value(&args[i], v)?),
+                (arg, _) => {
+                    return Err(format!("unknown or incomplete argument: {}", arg));
+                },
+            }
+            i += 2;
+        }
+        if config.sim_time.is_none() && config.ticks.is_none() {
+            return Err("headless run needs --time <sim seconds> or --ticks <num>".to_string());
+        }
+        if config.load_name.is_some() && (config.settings_path.is_some() || config.scenario_path.is_some() || config.seed.is_some()) {
+            return Err("--load continues saved simulation with its own settings and seed, it can't be used with --settings, --scenario or --seed".to_string());
+        }
+        return Ok(config);
+    }
+
+    fn is_finished(&self, ticks: u64, sim_time: f64) -> bool {
+        if let Some(max_ticks) = self.ticks {
+            if ticks >= max_ticks { return true; }
+        }
+        if let Some(max_time) = self.sim_time {
+            if sim_time >= max_time { return true; }
+        }
+        return false;
+    }
+
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    return value.parse::<T>().map_err(|_| format!("wrong value of {}: {}", arg, value));
+}
+
+#[doc = r"Builds simulation without window, font and input, runs it for given time and saves results."]
+pub fn run_headless(config: HeadlessConfig) -> Result<(), String> {
+    let mut settings = Settings::default();
+    if let Some(path) = &config.settings_path {
+        match read_settings(Path::new(path)) {
+            Some(loaded) => settings = loaded,
+            None => return Err(format!("can't read settings from {}", path)),
+        }
+    }
+    let mut sim = Simulation::new(settings);
+    match (&config.load_name, &config.scenario_path) {
+        (Some(name), _) => {
+            if !sim.load_sim(name, false) {
+                return Err(format!("can't load simulation {}", name));
+            }
+        },
+        (None, Some(path)) => {
+            let mut scenario = Scenario::from_file(Path::new(path))?;
+            if config.seed.is_some() {
+                scenario.seed = config.seed;
+            }
+            sim.load_scenario(scenario);
+        },
+        (None, None) => {
+            if let Some(seed) = config.seed {
+                sim.rng = SimRng::new(seed);
+            }
+            sim.init();
+        },
+    }
+    sim.running = true;
+    if let Some(name) = &config.sim_name {
+        sim.simulation_name = name.to_owned();
+    }
+    sim.settings.pause = false;
+    let start_time = sim.sim_state.sim_time;
+    let mut ticks: u64 = 0;
+    let mut next_report = 0.0;
+    let mut counters = EventCounters::default();
+    println!("[HEADLESS] {} started at {:.0} (seed: {})", sim.simulation_name, start_time, sim.rng.get_seed());
+    while !config.is_finished(ticks, sim.sim_state.sim_time - start_time) {
+        sim.update();
+        counters.consume(&mut sim.events);
+        ticks += 1;
+        let elapsed = sim.sim_state.sim_time - start_time;
+        if elapsed >= next_report {
+            next_report += 100.0;
+            println!(
+                "[HEADLESS] time: {:.0} | ticks: {} | agents: {} | plants: {} | births: {} | deaths: {} | kills: {} | species: {}",
+                sim.sim_state.sim_time, ticks, sim.agents.count(), sim.plants.count(),
+                counters.births, counters.deaths, counters.kills, counters.speciations
+            );
+        }
+    }
+    sim.save_sim();
+    sim.save_statistics();
+    println!("[HEADLESS] {} finished at {:.0} after {} ticks", sim.simulation_name, sim.sim_state.sim_time, ticks);
+    println!(
+        "[HEADLESS] births: {} (mating: {}) | deaths: {} | kills: {} | new species: {} | plant clones: {} | autosaves: {}",
+        counters.births, counters.matings, counters.deaths, counters.kills, counters.speciations, counters.plant_clones, counters.autosaves
+    );
+    return Ok(());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        let mut args = vec!["evolve-headless".to_string()];
+        args.extend(list.iter().map(|arg| arg.to_string()));
+        return args;
+    }
+
+    #[test]
+    fn parses_run_options() {
+        let config = HeadlessConfig::from_args(&args(&["--headless", "--load", "trial", "--ticks", "50"])).unwrap();
+        assert_eq!(config.load_name.as_deref(), Some("trial"));
+        assert_eq!(config.ticks, Some(50));
+        assert!(config.sim_time.is_none());
+    }
+
+    #[test]
+    fn rejects_wrong_values() {
+        assert!(HeadlessConfig::from_args(&args(&["--seed", "x", "--ticks", "5"])).is_err());
+        assert!(HeadlessConfig::from_args(&args(&["--time", "-", "--ticks", "5"])).is_err());
+        assert!(HeadlessConfig::from_args(&args(&["--ticks", "5.5"])).is_err());
+        assert!(HeadlessConfig::from_args(&args(&["--seed", "3"])).is_err());
+        assert!(HeadlessConfig::from_args(&args(&["--ticks"])).is_err());
+    }
+
+    #[test]
+    fn load_keeps_saved_seed_and_settings() {
+        assert!(HeadlessConfig::from_args(&args(&["--load", "trial", "--seed", "3", "--ticks", "5"])).is_err());
+        assert!(HeadlessConfig::from_args(&args(&["--load", "trial", "--settings", "s.json", "--ticks", "5"])).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+//! Core of evolve simulation: agents, neural networks, plants, terrain, physics,
+//! ranking, statistics and serialization. Contains no rendering, window input or user interface.
+
+pub mod math;
+pub mod neuro;
+pub mod sim;
+pub mod timer;
+pub mod util;
+pub mod phyx;
+pub mod ranking;
+pub mod agent;
+pub mod collector;
+pub mod misc;
+pub mod plant;
+pub mod terrain;
+pub mod settings;
+pub mod statistics;
+pub mod signals;
+pub mod sketch;
+pub mod headless;
+pub mod rng;
+pub mod spatial;
+pub mod events;
+pub mod scenario;
+pub mod environment;
+pub mod obstacle;
+pub mod carcass;
+pub mod species;
+pub mod genome;
@@ -2,34 +2,24 @@
 //#![windows_subsystem = "windows"]
 
 mod camera;
-mod neuro;
-mod sim;
-mod timer;
 mod ui;
-mod util;
-mod phyx;
-mod ranking;
-mod agent;
-mod collector;
-mod misc;
-mod plant;
-mod terrain;
-mod settings;
 mod monit;
-mod statistics;
-mod signals;
-mod sketch;
 mod net_draw;
+mod draw;
+mod dbg;
+mod gui_util;
+mod app;
 
 use std::env;
-use crate::sim::*;
-use crate::statistics::*;
 use macroquad::miniquad::conf::Icon;
 use macroquad::prelude::*;
-use crate::signals::*;
-use util::MyIcon;
-use util::generate_seed;
-use crate::settings::*;
+use evolve_core::statistics::*;
+use evolve_core::signals::*;
+use evolve_core::util::generate_seed;
+use evolve_core::settings::*;
+use evolve_core::headless::*;
+use crate::gui_util::MyIcon;
+use crate::app::App;
 
 
 fn app_configuration() -> Conf {
@@ -53,42 +43,56 @@ fn app_configuration() -> Conf {
     }
 }
 
-fn setup() {
-    set_settings(Settings::default());
-    set_signals(Signals::new());
-    set_mutations(MutationStats::new(0.0, 0.0));
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        if let Err(msg) = HeadlessConfig::from_args(&args).and_then(run_headless) {
+            eprintln!("{}", msg);
+            std::process::exit(1);
+        }
+        return;
+    }
+    macroquad::Window::from_config(app_configuration(), run_window());
 }
 
-#[macroquad::main(app_configuration)]
-async fn main() {
-    setup();
+async fn run_window() {
     let seed = generate_seed();
     rand::srand(seed);
     //let font = Font::
     let font = load_ttf_font("assets/fonts/jetbrain_medium.ttf").await.unwrap();
-    let mut sim = Simulation::new(font.clone());
-    sim.init();
-    sim.ui.load_textures();
-    let mut args = env::args();
-    match args.nth(1) {
-        Some(save_path) => {
-            sim.running = true;
-            sim.load_sim(&save_path, true);
+    let mut app = App::new(font.clone());
+    app.sim.init();
+    app.ui.load_textures();
+    let args: Vec<String> = env::args().collect();
+    match (args.get(1).map(|arg| arg.as_str()), args.get(2)) {
+        (Some("--scenario"), Some(scenario_path)) => {
+            app.sim.signals.load_scenario_name = Some(scenario_path.to_owned());
+        },
+        (Some(arg), _) if arg.starts_with("--") => {
+            eprintln!("unknown or incomplete argument: {}", arg);
+            std::process::exit(1);
+        },
+        (Some(save_path), _) => {
+            if !app.sim.load_sim(save_path, true) {
+                eprintln!("can't load simulation from {}", save_path);
+                std::process::exit(1);
+            }
+            app.sim.running = true;
         },
-        None => {},
+        (None, _) => {},
     }
 
     loop {
-        sim.input();
-        sim.process_ui();
-        if sim.is_running() {
-            sim.update();
-            sim.draw();
-            sim.debug_physic();
+        app.input();
+        app.process_ui();
+        if app.sim.is_running() {
+            app.update();
+            app.draw();
+            app.debug_physic();
         } else {
-            sim.check_signals();
+            app.sim.check_signals();
         }
-        sim.draw_ui();
+        app.draw_ui();
         next_frame().await;
     }
 }
@@ -0,0 +1,101 @@
+//#![allow(unused)]
+
+pub use glam::{Vec2, vec2, IVec2, ivec2, Vec4};
+
+
+#[doc = r"Clamp value to range min..max."]
+pub fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
+    if value < min {
+        return min;
+    } else if value > max {
+        return max;
+    }
+    return value;
+}
+
+#[doc = r"RGBA color with components in 0.0..1.0 range. It has the same layout as frontend colors
+and is converted to them only when drawn, so core does not depend on rendering crate."]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    pub fn to_vec(&self) -> Vec4 {
+        return Vec4::new(self.r, self.g, self.b, self.a);
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        return [self.r, self.g, self.b, self.a];
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from(c: [f32; 4]) -> Self {
+        Self::new(c[0], c[1], c[2], c[3])
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(c: Color) -> Self {
+        c.to_array()
+    }
+}
+
+pub const LIGHTGRAY: Color = Color::new(0.78, 0.78, 0.78, 1.00);
+pub const GRAY: Color = Color::new(0.51, 0.51, 0.51, 1.00);
+pub const DARKGRAY: Color = Color::new(0.31, 0.31, 0.31, 1.00);
+pub const YELLOW: Color = Color::new(0.99, 0.98, 0.00, 1.00);
+pub const GOLD: Color = Color::new(1.00, 0.80, 0.00, 1.00);
+pub const ORANGE: Color = Color::new(1.00, 0.63, 0.00, 1.00);
+pub const PINK: Color = Color::new(1.00, 0.43, 0.76, 1.00);
+pub const RED: Color = Color::new(0.90, 0.16, 0.22, 1.00);
+pub const MAROON: Color = Color::new(0.75, 0.13, 0.22, 1.00);
+pub const GREEN: Color = Color::new(0.00, 0.89, 0.19, 1.00);
+pub const LIME: Color = Color::new(0.00, 0.62, 0.18, 1.00);
+pub const DARKGREEN: Color = Color::new(0.00, 0.46, 0.17, 1.00);
+pub const SKYBLUE: Color = Color::new(0.40, 0.75, 1.00, 1.00);
+pub const BLUE: Color = Color::new(0.00, 0.47, 0.95, 1.00);
+pub const DARKBLUE: Color = Color::new(0.00, 0.32, 0.67, 1.00);
+pub const PURPLE: Color = Color::new(0.78, 0.48, 1.00, 1.00);
+pub const VIOLET: Color = Color::new(0.53, 0.24, 0.75, 1.00);
+pub const DARKPURPLE: Color = Color::new(0.44, 0.12, 0.49, 1.00);
+pub const BEIGE: Color = Color::new(0.83, 0.69, 0.51, 1.00);
+pub const BROWN: Color = Color::new(0.50, 0.42, 0.31, 1.00);
+pub const DARKBROWN: Color = Color::new(0.30, 0.25, 0.18, 1.00);
+pub const WHITE: Color = Color::new(1.00, 1.00, 1.00, 1.00);
+pub const BLACK: Color = Color::new(0.00, 0.00, 0.00, 1.00);
+pub const MAGENTA: Color = Color::new(1.00, 0.00, 1.00, 1.00);
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_limits_value_to_range() {
+        assert_eq!(clamp(5, 0, 3), 3);
+        assert_eq!(clamp(-1.0, 0.0, 1.0), 0.0);
+        assert_eq!(clamp(0.5, 0.0, 1.0), 0.5);
+    }
+
+    #[test]
+    fn color_round_trips_through_array() {
+        let color = Color::from_rgba(255, 0, 51, 255);
+        let array: [f32; 4] = color.into();
+        assert_eq!(array, [1.0, 0.0, 0.2, 1.0]);
+        assert_eq!(Color::from(array), color);
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(unused)]
 
-use std::collections::HashMap;
+use crate::util::StableMap;
 use serde::{Deserialize, Serialize};
 use crate::neuro::Network;
 
@@ -15,20 +15,20 @@ pub enum MyShapeType {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NeuroMap {
-    pub sensors: HashMap<String, u64>,
-    pub effectors: HashMap<String, u64>,
-    signals: HashMap<u64, f32>,
-    actions: HashMap<String, f32>,
+    pub sensors: StableMap<String, u64>,
+    pub effectors: StableMap<String, u64>,
+    signals: StableMap<u64, f32>,
+    actions: StableMap<String, f32>,
 }
 
 impl NeuroMap {
 
     pub fn new() -> Self {
         Self { 
-            sensors: HashMap::new(), 
-            effectors: HashMap::new(),
-            signals: HashMap::new(),
-            actions: HashMap::new(), 
+            sensors: StableMap::default(), 
+            effectors: StableMap::default(),
+            signals: StableMap::default(),
+            actions: StableMap::default(), 
         }
     }
 
@@ -61,7 +61,7 @@ impl NeuroMap {
     }
 
     pub fn recv_actions(&mut self, network: &Network) {
-        self.actions = HashMap::new();
+        self.actions = StableMap::default();
         for (k, v) in self.effectors.iter() {
             self.actions.insert(k.to_owned(), network.get_node_value(v).unwrap());
         }
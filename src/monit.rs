@@ -1,9 +1,8 @@
 #![allow(unused)]
 
 use macroquad::prelude::*;
-use crate::timer::*;
-use crate::settings::*;
-use crate::util::dt;
+use evolve_core::timer::*;
+use evolve_core::settings::*;
 
 pub struct PerformanceMonitor {
     fps_list: Vec<i32>,
@@ -19,13 +18,13 @@ impl PerformanceMonitor {
             fps_list: vec![], 
             fps: 0,
             dt: 0.0,
-            timer: Timer::new(dur, true, true, false),
+            timer: Timer::new(dur, true, true),
         }
     }
 
     pub fn monitor(&mut self) {
-        let fps = (get_fps() as f32/sim_speed()) as i32;
-        let dt = dt()*sim_speed();
+        let fps = get_fps();
+        let dt = get_frame_time();
         self.fps_list.push(fps);
         if self.timer.update(dt) {
             let sum: i32 = self.fps_list.iter().sum();
@@ -1,6 +1,7 @@
 use macroquad::prelude::*;
 
-use crate::{
+use crate::gui_util::core_color;
+use evolve_core::{
     agent::Agent, 
     neuro::NeuronTypes, 
     util::*,
@@ -24,12 +25,12 @@ pub fn draw_network(agent: &Agent, timer: f32, cam_offset: Vec2, _cam_zoom: Vec2
         let p1 = coord0*resize+zero;
         let p2 = coord1*resize+zero;
         let pt = coord_t*resize+zero;
-        let (_, color1) = link.get_colors();
+        let color1 = core_color(link.get_colors().1);
         draw_line(p1.x, p1.y, p2.x, p2.y, w, color1);
         draw_circle(pt.x, pt.y, w, color1);
     }
     for (key, node) in network.nodes.iter() {
-        let (_, color1) = node.get_colors();
+        let color1 = core_color(node.get_colors().1);
         let (r0, _) = node.get_size();
         let mut mem = node.get_mem_size();
         let mut pos: Vec2 = node.pos.as_vec2();
@@ -1,10 +1,8 @@
 //#![allow(unused)]
 
-use macroquad::prelude::*;
-use macroquad::rand::*;
-use ::rand::thread_rng;
+use crate::math::*;
 use ::rand::Rng;
-use std::collections::HashMap;
+use ::rand::seq::SliceRandom;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use serde::{Serialize, Deserialize};
@@ -14,10 +12,75 @@ use crate::sketch::NodeSketch;
 use crate::statistics::*;
 use crate::util::*;
 use crate::settings::*;
+use crate::rng::SimRng;
 
 
-pub fn generate_id() -> u64 {
-    return rand::gen_range(u64::MIN, u64::MAX);
+#[doc = r"Innovation numbers are ids of nodes and links derived from their place in network topology, not drawn from rng or counter.
+Same gene (input or output with the same label, link between the same nodes or node splitting the same link) has the same id in every network,
+in every simulation and after loading, so genes of different networks can always be aligned."]
+fn innovation(kind: u8, a: u64, b: u64) -> u64 {
+    let mut bytes = vec![kind];
+    bytes.extend_from_slice(&a.to_le_bytes());
+    bytes.extend_from_slice(&b.to_le_bytes());
+    return fnv_hash(&bytes);
+}
+
+#[doc = r"Innovation of link between two nodes."]
+pub fn link_innovation(node_from: u64, node_to: u64) -> u64 {
+    return innovation(b'L', node_from, node_to);
+}
+
+#[doc = r"Innovation of node inserted into link, identified by link and node it pointed to before split."]
+pub fn node_innovation(link: u64, node_to: u64) -> u64 {
+    return innovation(b'N', link, node_to);
+}
+
+#[doc = r"Innovation of input or output node, identified by its label and number of earlier nodes of the same kind with this label."]
+fn io_innovation(output: bool, label: &str, occurrence: usize) -> u64 {
+    let kind = match output {
+        true => b'O',
+        false => b'I',
+    };
+    return innovation(kind, fnv_hash(label.as_bytes()), occurrence as u64);
+}
+
+#[doc = r"Innovation of hidden node of founder network, identified by its layer and place in layer."]
+fn founder_innovation(layer: usize, index: usize) -> u64 {
+    return innovation(b'D', layer as u64, index as u64);
+}
+
+#[doc = r"Genes of network aligned by ids: node ids and link weights. Used to measure compatibility distance between networks."]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Genome {
+    pub nodes: StableSet<u64>,
+    pub links: StableMap<u64, f32>,
+}
+
+impl Genome {
+
+    #[doc = r"NEAT compatibility distance: share of non-matching genes plus mean weight difference of matching links, scaled by coefficients from settings."]
+    pub fn distance(&self, other: &Genome, settings: &Settings) -> f32 {
+        let matching_nodes = self.nodes.iter().filter(|id| other.nodes.contains(*id)).count();
+        let mut matching_links = 0;
+        let mut weight_diff = 0.0;
+        for (id, w) in self.links.iter() {
+            if let Some(w2) = other.links.get(id) {
+                matching_links += 1;
+                weight_diff += (w - w2).abs();
+            }
+        }
+        let genes1 = self.nodes.len() + self.links.len();
+        let genes2 = other.nodes.len() + other.links.len();
+        let matching = matching_nodes + matching_links;
+        let non_matching = (genes1 - matching) + (genes2 - matching);
+        let n = genes1.max(genes2).max(1) as f32;
+        let weight_mean = match matching_links {
+            0 => 0.0,
+            _ => weight_diff / matching_links as f32,
+        };
+        return settings.compat_disjoint * non_matching as f32 / n + settings.compat_weight * weight_mean;
+    }
+
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -61,14 +124,13 @@ pub struct Link {
     pub node_from: u64,
     pub node_to: u64,
     signal: f32,
-    new_mut: bool,
     w_mut: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct Network {
-    pub nodes: HashMap<u64, Node>,
-    pub links: HashMap<u64, Link>,
+    pub nodes: StableMap<u64, Node>,
+    pub links: StableMap<u64, Link>,
     //pub margins: NeuroMargins,
     pub input_keys: Vec<u64>,
     pub output_keys: Vec<u64>,
@@ -85,11 +147,10 @@ impl MemStore {
         }
     }
 
-    pub fn new_random() -> Self {
-        let mut rng = thread_rng();
-        let size: usize = rng.gen_range(1..=50);
+    pub fn new_random(rng: &mut SimRng) -> Self {
+        let size: usize = rng.gen_range(1, 51);
         let memory: VecDeque<f32> = VecDeque::from(vec![0.0; size]);
-        let mem_rate: f32 = rng.gen_range(0.0..=1.0);
+        let mem_rate: f32 = rng.gen_range(0.0, 1.0);
         Self {
             size,
             mem_rate,
@@ -98,16 +159,14 @@ impl MemStore {
         }
     }
 
-    pub fn mutate_size(&mut self) {
-        let mut rng = thread_rng();
-        let size: isize = rng.gen_range(-5..=5);
+    pub fn mutate_size(&mut self, rng: &mut SimRng) {
+        let size: isize = rng.gen_range(-5, 6);
         self.size = clamp(self.size as isize + size, 1, 50) as usize;
         self.memory = VecDeque::from(vec![0.0; self.size]);
     }
 
-    pub fn mutate_mem_rate(&mut self) {
-        let mut rng = thread_rng();
-        let rate: f32 = rng.gen_range(0.0..=1.0);
+    pub fn mutate_mem_rate(&mut self, rng: &mut SimRng) {
+        let rate: f32 = rng.gen_range(0.0, 1.0);
         self.mem_rate = clamp((self.mem_rate + rate)/2.0, 0.0, 1.0);
     }
 
@@ -132,16 +191,20 @@ impl MemStore {
         self.mean
     }
 
+    pub fn get_params(&self) -> (usize, f32) {
+        return (self.size, self.mem_rate);
+    }
+
 }
 
 impl Node {
 
-    pub fn new(position: IVec2, neuron_type: NeuronTypes, label: &str, memory_node: bool) -> Self {
+    pub fn new(id: u64, position: IVec2, neuron_type: NeuronTypes, label: &str, memory_node: bool, rng: &mut SimRng) -> Self {
         Self {
-            id: generate_id(),
+            id,
             pos: position,
-            bias: rand::gen_range(-1.0, 1.0),
-            val: rand::gen_range(0.0, 0.0),
+            bias: rng.gen_range(-1.0, 1.0),
+            val: 0.0,
             sum: 0.0,
             selected: false,
             node_type: neuron_type,
@@ -149,7 +212,7 @@ impl Node {
             label: label.to_string(),
             new_mut: false,
             memory: match memory_node {
-                true => Some(MemStore::new_random()),
+                true => Some(MemStore::new_random(rng)),
                 false => None,
             },
             lazy_num:0,
@@ -218,15 +281,15 @@ impl Node {
             n if n>0.0 => { 
                 let v0 = clamp(255.0*n, 0.0, 255.0);
                 let v = v0 as u8;
-                let c1 = color_u8!(255, g, 0, v);
-                let c0 = color_u8!(255, g, 0, 255);
+                let c1 = Color::from_rgba(255, g, 0, v);
+                let c0 = Color::from_rgba(255, g, 0, 255);
                 (c0, c1) 
             },
             n if n<0.0 => { 
                 let v0 = clamp(255.0*n.abs(), 0.0, 255.0);
                 let v = v0 as u8;
-                let c1 = color_u8!(0, 150, 255, v);
-                let c0 = color_u8!(0, 150, 255, 255);
+                let c1 = Color::from_rgba(0, 150, 255, v);
+                let c0 = Color::from_rgba(0, 150, 255, 255);
                 (c0, c1) 
             },
             _ => {
@@ -294,19 +357,18 @@ impl Node {
                 sum
             },
         };
-        let mut v = 0.0;
-        match self.node_type {
+        let v = match self.node_type {
             NeuronTypes::INPUT => {
-                v = sum;
+                sum
             },
             NeuronTypes::OUTPUT => {
-                v = sum.tanh();
+                sum.tanh()
             }
             _ => {
                 sum += self.bias;
-                v = sum.tanh();
+                sum.tanh()
             },
-        }
+        };
         self.val = clamp(v, -1.0, 1.0);
         self.sum = 0.0;
         self.lazy = false;
@@ -315,19 +377,18 @@ impl Node {
 
 impl Link {
 
-    pub fn new(node_from: u64, node_to: u64, new_mut: bool) -> Self {
+    pub fn new(node_from: u64, node_to: u64, rng: &mut SimRng) -> Self {
         Self {
-            id: generate_id(),
+            id: link_innovation(node_from, node_to),
             node_from,
             node_to,
-            w: rand::gen_range(-1.0, 1.0),
+            w: rng.gen_range(-1.0, 1.0),
             signal: 0.0,
-            new_mut,
             w_mut: false,
         }
     }
 
-    pub fn get_coords(&self, nodes: &HashMap<u64, Node>, timer: f32) -> (Vec2, Vec2, Vec2) {
+    pub fn get_coords(&self, nodes: &StableMap<u64, Node>, timer: f32) -> (Vec2, Vec2, Vec2) {
         let n0 = self.node_from;
         let n1 = self.node_to;
         let node0 = nodes.get(&n0).unwrap();
@@ -351,12 +412,12 @@ impl Link {
         if s > 0.0 {
             let mut r = 100 + (155.0 * s) as u8;
             r = clamp(r, 0, 255);
-            color1 = color_u8!(r, 0, 0, (100.0+155.0*s) as u8);
+            color1 = Color::from_rgba(r, 0, 0, (100.0+155.0*s) as u8);
         }
         if s < 0.0 {
             let mut b = 100 + (155.0 * s.abs()) as u8;
             b = clamp(b, 0, 255);
-            color1 = color_u8!(0, b, b, (100.0+155.0*s.abs()) as u8);
+            color1 = Color::from_rgba(0, b, b, (100.0+155.0*s.abs()) as u8);
         }
         return (color0, color1);
     }
@@ -366,7 +427,7 @@ impl Link {
         return 1.0 + s * 4.0;
     }
 
-    pub fn calc(&mut self, nodes: &mut HashMap<u64, Node>) {
+    pub fn calc(&mut self, nodes: &mut StableMap<u64, Node>) {
         let n0 = self.node_from;
         let n1 = self.node_to;
         let w = self.w;
@@ -389,7 +450,7 @@ impl Link {
     }
 
     pub fn from_sketch(sketch: LinkSketch) -> Link {
-        Link { id: sketch.id, w: sketch.w, node_from: sketch.node_from, node_to: sketch.node_to, signal: 0.0, new_mut: false, w_mut: false }
+        Link { id: sketch.id, w: sketch.w, node_from: sketch.node_from, node_to: sketch.node_to, signal: 0.0, w_mut: false }
     }
 }
 
@@ -398,26 +459,74 @@ impl Network {
 
     pub fn new(_duration: f32) -> Self {
         Self {
-            nodes: HashMap::new(),
-            links: HashMap::new(),
+            nodes: StableMap::default(),
+            links: StableMap::default(),
             //margins: NeuroMargins { x_min: 0.01, x_max: 0.99, y_min: 0.01, y_max: 0.99 },
             input_keys: vec![],
             output_keys: vec![],
         }
     }
 
-    pub fn build(&mut self,input_num: usize, input_labels: Vec<&str>, hidden_num: Vec<usize>, output_num: usize, output_labels: Vec<&str>, link_rate: f32) {
-        self.create_nodes2(input_num, input_labels, hidden_num, output_num, output_labels);
-        self.create_links(link_rate);
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(&mut self,input_num: usize, input_labels: Vec<&str>, hidden_num: Vec<usize>, output_num: usize, output_labels: Vec<&str>, link_rate: f32, rng: &mut SimRng) {
+        self.create_nodes2(input_num, input_labels, hidden_num, output_num, output_labels, rng);
+        self.create_links(link_rate, rng);
         let (i, _, o) = self.get_node_keys_by_type();
         self.input_keys = i;
         self.output_keys = o;
     }
 
+    #[doc = r"Innovation of new input or output node with given label."]
+    fn io_innovation(&self, output: bool, label: &str) -> u64 {
+        let occurrence = self.nodes.values()
+            .filter(|node| node.label == label)
+            .filter(|node| matches!((output, node.node_type), (false, NeuronTypes::INPUT) | (true, NeuronTypes::OUTPUT)))
+            .count();
+        return io_innovation(output, label, occurrence);
+    }
+
+    #[doc = r"Add unconnected input node below existing ones and spread all input nodes evenly. Returns key of new node."]
+    pub fn add_input_node(&mut self, label: &str, rng: &mut SimRng) -> u64 {
+        let mut keys: Vec<(i32, u64)> = self.nodes.iter()
+            .filter(|(_, node)| matches!(node.node_type, NeuronTypes::INPUT))
+            .map(|(key, node)| (node.pos.y, *key))
+            .collect();
+        keys.sort();
+        let node = Node::new(self.io_innovation(false, label), IVec2::new(0, 0), NeuronTypes::INPUT, label, false, rng);
+        let id = node.id;
+        self.nodes.insert(id, node);
+        keys.push((0, id));
+        let hi = 100.0 / (keys.len()+1) as f32;
+        for (i, (_, key)) in keys.iter().enumerate() {
+            self.nodes.get_mut(key).unwrap().pos.y = (hi+hi*i as f32) as i32;
+        }
+        self.input_keys.push(id);
+        return id;
+    }
+
+    #[doc = r"Add unconnected output node below existing ones and spread all output nodes evenly. Returns key of new node."]
+    pub fn add_output_node(&mut self, label: &str, rng: &mut SimRng) -> u64 {
+        let mut keys: Vec<(i32, u64)> = self.nodes.iter()
+            .filter(|(_, node)| matches!(node.node_type, NeuronTypes::OUTPUT))
+            .map(|(key, node)| (node.pos.y, *key))
+            .collect();
+        keys.sort();
+        let node = Node::new(self.io_innovation(true, label), IVec2::new(100, 0), NeuronTypes::OUTPUT, label, false, rng);
+        let id = node.id;
+        self.nodes.insert(id, node);
+        keys.push((0, id));
+        let ho = 100.0 / (keys.len()+1) as f32;
+        for (i, (_, key)) in keys.iter().enumerate() {
+            self.nodes.get_mut(key).unwrap().pos.y = (ho+ho*i as f32) as i32;
+        }
+        self.output_keys.push(id);
+        return id;
+    }
+
     pub fn input(&mut self, input_values: Vec<(u64, f32)>) {
         for (key, value) in input_values.iter() {
             match self.nodes.get_mut(key) {
-                None => warn!("input node {} not found", key),
+                None => eprintln!("input node {} not found", key),
                 Some(node) => {
                     node.recv_input(*value);
                 },
@@ -476,17 +585,19 @@ impl Network {
         return (hidden_num, self.links.len() as i32);
     }
 
-    fn create_nodes2(&mut self, input: usize, input_labels: Vec<&str>, hidden: Vec<usize>, output: usize, output_labels: Vec<&str>) {
+    fn create_nodes2(&mut self, input: usize, input_labels: Vec<&str>, hidden: Vec<usize>, output: usize, output_labels: Vec<&str>, rng: &mut SimRng) {
         let deep_n = hidden.len()+1;
         let hi = 100.0 / (input+1) as f32;
         let ho = 100.0 / (output+1) as f32;
         let wd = (100/deep_n) as i32;
         for i in 0..input {
             let node = Node::new(
+                self.io_innovation(false, input_labels[i]),
                 IVec2::new(0, (hi+hi*i as f32) as i32), 
                 NeuronTypes::INPUT, 
                 input_labels[i],
                 false,
+                rng,
             );
             let id = node.id;
             self.nodes.insert(id, node);
@@ -495,13 +606,15 @@ impl Network {
         for deep in 0..hidden.len() {
             let hd = 100.0 / (hidden[deep]+1) as f32;
             for d in 0..hidden[deep] {
-                let m = thread_rng().gen_bool(1.0/3.0);
+                let m = rng.gen_bool(1.0/3.0);
                 let node = Node::new(
+                    founder_innovation(deep, d),
                     IVec2::new(wd*(deep as i32+1), 
                     (hd+hd*d as f32) as i32), 
                     NeuronTypes::DEEP, 
                     "",
                     m,
+                    rng,
                 );
                 let id = node.id;
                 self.nodes.insert(id, node);
@@ -510,17 +623,19 @@ impl Network {
 
         for o in 0..output {
             let node = Node::new(
+                self.io_innovation(true, output_labels[o]),
                 IVec2::new(100, (ho+ho*o as f32) as i32), 
                 NeuronTypes::OUTPUT, 
                 output_labels[o], 
-                false
+                false,
+                rng,
             );
             let id = node.id;
             self.nodes.insert(id, node);
         }
     }
     
-    fn create_links(&mut self, links: f32) {
+    fn create_links(&mut self, links: f32, rng: &mut SimRng) {
         let nodes_id2: Vec<u64> = self.nodes.keys().copied().collect();
         let nodes_id1: Vec<u64> = self.nodes.keys().copied().collect();
         for id in nodes_id1.iter().copied() {
@@ -534,14 +649,14 @@ impl Network {
                                 let x1 = self.nodes.get(&id).unwrap().pos.x;
                                 let x2 = self.nodes.get(&id2).unwrap().pos.x;
                                 if x2 > x1 {
-                                    if rand::gen_range(0.0, 1.0) <= links {
-                                        self.add_link(id, id2, false)
+                                    if rng.gen_range(0.0, 1.0) <= links {
+                                        self.add_link(id, id2, rng)
                                     }
                                 }
                             },
                             _ => {
-                                if rand::gen_range(0.0, 1.0) <= links {
-                                    self.add_link(id, id2, false)
+                                if rng.gen_range(0.0, 1.0) <= links {
+                                    self.add_link(id, id2, rng)
                                 }
                             },
                         }
@@ -550,8 +665,8 @@ impl Network {
                         match self.nodes.get(&id).unwrap().node_type {
                             NeuronTypes::OUTPUT => { continue; },
                             _ => {
-                                if rand::gen_range(0.0, 1.0) <= links {
-                                    self.add_link(id, id2, false)
+                                if rng.gen_range(0.0, 1.0) <= links {
+                                    self.add_link(id, id2, rng)
                                 }
                             },
                         }
@@ -563,11 +678,20 @@ impl Network {
         }
     }
         
-    pub fn add_link(&mut self, node_from: u64, node_to: u64, new_mut: bool) {
-        let link = Link::new(node_from, node_to, new_mut);
+    pub fn add_link(&mut self, node_from: u64, node_to: u64, rng: &mut SimRng) {
+        let link = Link::new(node_from, node_to, rng);
         self.links.insert(link.id, link);
     }
 
+    #[doc = r"Adds mutated link. Returns false if network already has link with the same innovation number."]
+    fn add_innovation_link(&mut self, node_from: u64, node_to: u64, rng: &mut SimRng) -> bool {
+        if self.links.contains_key(&link_innovation(node_from, node_to)) {
+            return false;
+        }
+        self.add_link(node_from, node_to, rng);
+        return true;
+    }
+
     pub fn calc(&mut self) {
         for (_id, link) in self.links.iter_mut() {
             link.calc(&mut self.nodes);
@@ -609,7 +733,6 @@ impl Network {
         links_map.clone_from(&self.links);
         links_map.iter_mut().map(|(_, l)| {
             l.w_mut = false;
-            l.new_mut = false;
         });
         Self {
             nodes: nodes_map,
@@ -620,15 +743,28 @@ impl Network {
         }
     } */
 
-    pub fn replicate(&self) -> Network {
+    pub fn replicate(&self, rng: &mut SimRng) -> Network {
         let sketch: NetworkSketch = self.get_sketch();
-        let network = sketch.from_sketch();
+        let network = sketch.from_sketch(rng);
         return network;
     }
 
+    #[doc = r"Current values of nodes, which are carried between evaluations."]
+    pub fn get_node_values(&self) -> StableMap<u64, f32> {
+        return self.nodes.iter().map(|(id, node)| (*id, node.val)).collect();
+    }
+
+    pub fn set_node_values(&mut self, values: &StableMap<u64, f32>) {
+        for (id, val) in values.iter() {
+            if let Some(node) = self.nodes.get_mut(id) {
+                node.val = *val;
+            }
+        }
+    }
+
     pub fn get_sketch(&self) -> NetworkSketch {
-        let mut nodes_sketch: HashMap<u64, NodeSketch> = HashMap::new();
-        let mut links_sketch: HashMap<u64, LinkSketch> = HashMap::new();
+        let mut nodes_sketch: StableMap<u64, NodeSketch> = StableMap::default();
+        let mut links_sketch: StableMap<u64, LinkSketch> = StableMap::default();
 
         for (_, node) in self.nodes.iter() {
             let n = node.get_sketch();
@@ -647,39 +783,47 @@ impl Network {
         }
     }
 
-    pub fn mutate(&mut self, m: f32) {
-        let settings = get_settings();
+    pub fn get_genome(&self) -> Genome {
+        return Genome {
+            nodes: self.nodes.keys().copied().collect(),
+            links: self.links.iter().map(|(id, link)| (*id, link.w)).collect(),
+        };
+    }
+
+    pub fn compatibility(&self, other: &Network, settings: &Settings) -> f32 {
+        return self.get_genome().distance(&other.get_genome(), settings);
+    }
+
+    pub fn mutate(&mut self, m: f32, settings: &Settings, mutations: &mut MutationStats, rng: &mut SimRng) {
         let mut_node_add = settings.mut_add_node + settings.mut_add_node*m;
         let mut_node_del = settings.mut_del_node + settings.mut_del_node*-m;
         let mut_link_add = settings.mut_add_link + settings.mut_add_link*m;
         let mut_link_del = settings.mut_del_link + settings.mut_del_link*-m;
         let mut_change_val = settings.mut_change_val + settings.mut_change_val*m;
-        let (dl2, dn2) = self.delete_random_link(mut_link_del);
-        let al = self.add_random_link(mut_link_add);
-        let w = self.mutate_link_weight(mut_change_val);
-        let (an, dn, al2, dl, b) = self.mutate_nodes(mut_node_add, mut_node_del, mut_change_val);
-        self.mutate_nodes_mem(mut_node_add);
-        let mut stats = get_mutations();
-        stats.add_values(an as i32, (dn+dn2) as i32, (al+al2) as i32, (dl+dl2) as i32, b as i32, w as i32);
-        set_mutations(stats);
-    }
-
-    fn mutate_nodes(&mut self, mut_add: f32, mut_del: f32, mut_mod: f32) -> (usize, usize, usize, usize, usize) {
-        let (dn, dl) = self.del_random_node(mut_del);
+        let (dl2, dn2) = self.delete_random_link(mut_link_del, rng);
+        let al = self.add_random_link(mut_link_add, rng);
+        let w = self.mutate_link_weight(mut_change_val, rng);
+        let (an, dn, al2, dl, b) = self.mutate_nodes(mut_node_add, mut_node_del, mut_change_val, rng);
+        self.mutate_nodes_mem(mut_node_add, rng);
+        mutations.add_values(an as i32, (dn+dn2) as i32, (al+al2) as i32, (dl+dl2) as i32, b as i32, w as i32);
+    }
+
+    fn mutate_nodes(&mut self, mut_add: f32, mut_del: f32, mut_mod: f32, rng: &mut SimRng) -> (usize, usize, usize, usize, usize) {
+        let (dn, dl) = self.del_random_node(mut_del, rng);
         //let dn = 0; let dl = 0;
-        let (an, al) = self.add_random_node(mut_add);
-        let b = self.mutate_nodes_bias(mut_mod);
+        let (an, al) = self.add_random_node(mut_add, rng);
+        let b = self.mutate_nodes_bias(mut_mod, rng);
         return (an, dn, al, dl, b);
     }
 
-    fn mutate_nodes_bias(&mut self, m: f32) -> usize{
+    fn mutate_nodes_bias(&mut self, m: f32, rng: &mut SimRng) -> usize{
         let mut counter = 0;
         let node_keys: Vec<u64> = self.nodes.keys().copied().collect();
         for k in node_keys {    
-            if self.mutate_this(m) {
+            if self.mutate_this(m, rng) {
                 //let k = *node_keys.choose().unwrap();
                 let node = self.nodes.get_mut(&k).unwrap();
-                let bias = node.bias + rand::gen_range(-0.2, 0.2);
+                let bias = node.bias + rng.gen_range(-0.2, 0.2);
                 node.bias = clamp(bias, -1.0, 1.0);
                 counter += 1;
             }
@@ -687,32 +831,32 @@ impl Network {
         return counter;
     }
 
-    fn mutate_nodes_mem(&mut self, m: f32) -> usize{
+    fn mutate_nodes_mem(&mut self, m: f32, rng: &mut SimRng) -> usize{
         let mut counter = 0;
         let node_keys: Vec<u64> = self.nodes.keys().copied().collect();
         for k in node_keys {    
-            if self.mutate_this(m) {
+            if self.mutate_this(m, rng) {
                 let node = self.nodes.get_mut(&k).unwrap();
                 match node.memory {
                     Some(ref mut memory) => {
-                        match thread_rng().gen_bool(2.0/10.0) {
+                        match rng.gen_bool(2.0/10.0) {
                             true => {
                                 node.memory = None;
                             },
                             false => {
-                                match vec![true, false].choose().unwrap() {
+                                match rng.gen_bool(0.5) {
                                     true => {
-                                        memory.mutate_mem_rate();
+                                        memory.mutate_mem_rate(rng);
                                     },
                                     false => {
-                                        memory.mutate_size();
+                                        memory.mutate_size(rng);
                                     },
                                 }
                             },
                         }
                     },
                     None => {
-                        node.memory = Some(MemStore::new_random());
+                        node.memory = Some(MemStore::new_random(rng));
                         counter += 1;
                     },
                 }
@@ -721,21 +865,21 @@ impl Network {
         return counter;
     }
 
-    fn mutate_this(&self, m: f32) -> bool {
-        if random_unit_unsigned() < m {
+    fn mutate_this(&self, m: f32, rng: &mut SimRng) -> bool {
+        if random_unit_unsigned(rng) < m {
             return true;
         } else {
             return false;
         }
     }
 
-    fn mutate_link_weight(&mut self, m: f32) -> usize {
+    fn mutate_link_weight(&mut self, m: f32, rng: &mut SimRng) -> usize {
         let mut counter = 0;
         let link_keys: Vec<u64> = self.links.keys().copied().collect();
         for k in link_keys {
-            if self.mutate_this(m) {
+            if self.mutate_this(m, rng) {
                 let link = self.links.get_mut(&k).unwrap();
-                let weight = link.w + rand::gen_range(-0.2, 0.2);
+                let weight = link.w + rng.gen_range(-0.2, 0.2);
                 link.w = clamp(weight, -1.0, 1.0);
                 link.w_mut = true;
                 counter += 1;
@@ -744,34 +888,43 @@ impl Network {
         return counter;
     }
 
-    fn add_random_node(&mut self, m: f32) -> (usize, usize) {
+    #[doc = r"Splits random links by inserting new node. Split link is removed and replaced by link into new node with weight 1.0 and link out of it with old weight, so every link keeps innovation number of its own ends."]
+    fn add_random_node(&mut self, m: f32, rng: &mut SimRng) -> (usize, usize) {
         let link_keys: Vec<u64> = self.links.keys().copied().collect();
         let mut counter_n = 0;
         let mut counter_l = 0;
         for k in link_keys {
-            if self.mutate_this(m) {
-                let link = self.links.get_mut(&k).unwrap();
+            if self.mutate_this(m, rng) {
+                let link = self.links.get(&k).unwrap();
                 let n0 = link.node_from;
                 let n1 = link.node_to;
-                let node0 = self.nodes.get(&n0).unwrap();
-                let pos0 = node0.pos;
-                let node1 = self.nodes.get(&n1).unwrap();
-                let pos1 = node1.pos;
+                let w = link.w;
+                let nx = node_innovation(k, n1);
+                if self.nodes.contains_key(&nx) {
+                    continue;
+                }
+                let pos0 = self.nodes.get(&n0).unwrap().pos;
+                let pos1 = self.nodes.get(&n1).unwrap().pos;
                 let posx = pos0 + (pos1-pos0)/2;
-                let nx = generate_id();
                 let mut new_node = Node::new(
+                    nx,
                     posx, 
                     NeuronTypes::DEEP, 
                     "", 
-                    false
+                    false,
+                    rng,
                 );
-                new_node.id = nx;
                 new_node.new_mut = true;
                 self.nodes.insert(nx, new_node);
                 counter_n += 1;
-                link.node_to = nx;
-                self.add_link(nx, n1, true);
-                counter_l += 1;
+                self.links.remove(&k);
+                for (from, to, weight) in [(n0, nx, 1.0), (nx, n1, w)] {
+                    let mut new_link = Link::new(from, to, rng);
+                    new_link.w = weight;
+                    new_link.w_mut = true;
+                    self.links.insert(new_link.id, new_link);
+                    counter_l += 1;
+                }
             }
         }
         return (counter_n, counter_l);
@@ -806,7 +959,7 @@ impl Network {
         };
     }
 
-    fn del_random_node(&mut self, _m: f32) -> (usize, usize) {
+    fn del_random_node(&mut self, _m: f32, rng: &mut SimRng) -> (usize, usize) {
         let mut counter_n = 0;
         let mut counter_l = 0;
         let mut nodes_to_del: Vec<u64> = vec![]; 
@@ -827,7 +980,7 @@ impl Network {
                                     //if !links_from.is_empty() && !links_to.is_empty() { continue; }
                                     //let a = self.nodes.get(&k).unwrap().active_rate;
                                     let lazy = (self.nodes.get(&k).unwrap().lazy_num as f32 / 10.0).powi(2);
-                                    let r: f32 = thread_rng().gen();
+                                    let r: f32 = rng.gen();
                                     if r <= lazy {
                                         println!("REMOVE LAZY NODE: r:{:.3} -> lazy{:.3}", r, lazy);
                                         nodes_to_del.push(node_key);
@@ -859,13 +1012,13 @@ impl Network {
         return (counter_n, counter_l);
     }
 
-    fn add_random_link(&mut self, m: f32) -> usize {
+    fn add_random_link(&mut self, m: f32, rng: &mut SimRng) -> usize {
         let mut counter = 0;
         let node_keys: Vec<u64> = self.nodes.keys().copied().collect();
         let node_keys1: Vec<u64> = self.nodes.keys().copied().collect();
         for k0 in node_keys {
-            if self.mutate_this(m) {
-                let k1 = *node_keys1.choose().unwrap();
+            if self.mutate_this(m, rng) {
+                let k1 = *node_keys1.choose(rng).unwrap();
                 if k0 == k1 { return counter; }
                 let node0 = self.nodes.get(&k0).unwrap();
                 let node1 = self.nodes.get(&k1).unwrap();
@@ -874,23 +1027,26 @@ impl Network {
                         match node1.node_type {
                             NeuronTypes::INPUT => { continue; },
                             _ => {
-                                self.add_link(k0, k1, true);
-                                counter += 1;
+                                if self.add_innovation_link(k0, k1, rng) {
+                                    counter += 1;
+                                }
                             },
                         }
                     },
                     NeuronTypes::DEEP => {
                         match node1.node_type {
                             NeuronTypes::OUTPUT => {
-                                self.add_link(k0, k1, true);
-                                counter += 1;
+                                if self.add_innovation_link(k0, k1, rng) {
+                                    counter += 1;
+                                }
                             },
                             NeuronTypes::DEEP => {
                                 let x0 = node0.pos.x;
                                 let x1 = node1.pos.x;
                                 if x1 > x0 {
-                                    self.add_link(k0, k1, true);
-                                    counter += 1;
+                                    if self.add_innovation_link(k0, k1, rng) {
+                                        counter += 1;
+                                    }
                                 }
                             },
                             _ => { continue; }
@@ -904,13 +1060,13 @@ impl Network {
         return counter;
     }
 
-    fn delete_random_link(&mut self, m: f32) -> (usize, usize) {
+    fn delete_random_link(&mut self, m: f32, rng: &mut SimRng) -> (usize, usize) {
         let mut counter: usize = 0;
         let mut counter_n: usize = 0;
         let mut nodes_to_check: Vec<u64> = vec![];
         let link_keys: Vec<u64> = self.links.keys().copied().collect();
         for k in link_keys {
-            if self.mutate_this(m) {
+            if self.mutate_this(m, rng) {
                 let link = self.links.get(&k).unwrap();
                 let n0 = link.node_from;
                 let n1 = link.node_to;
@@ -958,3 +1114,99 @@ impl MyPos2 {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: [&str; 4] = ["CON", "RED", "RED", "ENG"];
+    const OUTPUTS: [&str; 3] = ["MOV", "RED", "ATK"];
+
+    fn network(seed: u64) -> Network {
+        let mut rng = SimRng::new(seed);
+        let mut network = Network::new(1.0);
+        network.build(INPUTS.len(), INPUTS.to_vec(), vec![3, 3], OUTPUTS.len(), OUTPUTS.to_vec(), 0.5, &mut rng);
+        return network;
+    }
+
+    fn genome(nodes: &[u64], links: &[(u64, f32)]) -> Genome {
+        return Genome {
+            nodes: nodes.iter().copied().collect(),
+            links: links.iter().copied().collect(),
+        };
+    }
+
+    #[test]
+    fn distance_counts_non_matching_genes_and_weight_difference() {
+        let mut settings = Settings::default();
+        settings.compat_disjoint = 1.0;
+        settings.compat_weight = 0.5;
+        let a = genome(&[1, 2, 3], &[(10, 0.5), (11, -0.5)]);
+        assert_eq!(a.distance(&a, &settings), 0.0);
+        let b = genome(&[1, 2, 3], &[(10, 0.5), (11, 0.5)]);
+        assert!((a.distance(&b, &settings) - 0.5*0.5).abs() < 1e-6);
+        let c = genome(&[1, 2, 4], &[(10, 0.5), (12, -0.5)]);
+        assert!((a.distance(&c, &settings) - 4.0/5.0).abs() < 1e-6);
+        assert_eq!(a.distance(&c, &settings), c.distance(&a, &settings));
+        let d = genome(&[5, 6], &[]);
+        assert!((a.distance(&d, &settings) - 7.0/5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn founders_share_innovation_numbers() {
+        let (a, b) = (network(1), network(2));
+        assert_eq!(a.nodes.len(), INPUTS.len() + 6 + OUTPUTS.len());
+        assert_eq!(a.get_genome().nodes, b.get_genome().nodes);
+        let mut common = 0;
+        for (id, link) in a.links.iter() {
+            if let Some(other) = b.links.values().find(|l| l.node_from == link.node_from && l.node_to == link.node_to) {
+                assert_eq!(other.id, *id);
+                common += 1;
+            }
+        }
+        assert!(common > 0);
+    }
+
+    #[test]
+    fn same_mutation_gets_same_innovation_in_separate_networks() {
+        let parent = network(3);
+        let mut a = parent.replicate(&mut SimRng::new(4));
+        let mut b = parent.replicate(&mut SimRng::new(5));
+        a.add_random_node(1.0, &mut SimRng::new(6));
+        b.add_random_node(1.0, &mut SimRng::new(7));
+        assert!(a.nodes.len() > parent.nodes.len());
+        assert_eq!(a.get_genome().nodes, b.get_genome().nodes);
+        let mut links_a: Vec<u64> = a.links.keys().copied().collect();
+        let mut links_b: Vec<u64> = b.links.keys().copied().collect();
+        links_a.sort();
+        links_b.sort();
+        assert_eq!(links_a, links_b);
+        let mut added = parent.replicate(&mut SimRng::new(8));
+        let (from, to) = (a.input_keys[0], a.output_keys[0]);
+        added.add_innovation_link(from, to, &mut SimRng::new(9));
+        assert!(added.links.contains_key(&link_innovation(from, to)));
+    }
+
+    #[test]
+    fn split_link_is_replaced_by_links_with_own_innovations() {
+        let mut net = network(10);
+        let before: Vec<Link> = net.links.values().copied().collect();
+        let (n, l) = net.add_random_node(1.0, &mut SimRng::new(11));
+        assert!(n > 0);
+        assert_eq!(l, 2 * n);
+        for (key, link) in net.links.iter() {
+            assert_eq!(*key, link.id);
+            assert_eq!(*key, link_innovation(link.node_from, link.node_to));
+            assert!(net.nodes.contains_key(&link.node_from) && net.nodes.contains_key(&link.node_to));
+        }
+        for old in before.iter() {
+            let nx = node_innovation(old.id, old.node_to);
+            if net.nodes.contains_key(&nx) {
+                assert!(!net.links.contains_key(&old.id));
+                assert_eq!(net.links.get(&link_innovation(old.node_from, nx)).unwrap().w, 1.0);
+                assert_eq!(net.links.get(&link_innovation(nx, old.node_to)).unwrap().w, old.w);
+            }
+        }
+    }
+}
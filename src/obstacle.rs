@@ -0,0 +1,140 @@
+//#![allow(unused)]
+
+use crate::math::*;
+use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::util::StableMap;
+use crate::phyx::physics::Physics;
+use crate::phyx::physics_misc::PhysicsMaterial;
+
+
+#[doc = r"Half of thickness of wall made from segment."]
+pub const WALL_RADIUS: f32 = 2.0;
+
+#[doc = r"Shape of static obstacle in world coordinates. Polygon may be concave, it is split into convex parts."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ObstacleShape {
+    Segment { a: [f32; 2], b: [f32; 2] },
+    Polygon { points: Vec<[f32; 2]> },
+}
+
+impl ObstacleShape {
+
+    pub fn collider_shape(&self) -> Option<SharedShape> {
+        match self {
+            ObstacleShape::Segment { a, b } => {
+                return Some(SharedShape::capsule(point![a[0], a[1]], point![b[0], b[1]], WALL_RADIUS));
+            },
+            ObstacleShape::Polygon { points } => {
+                if points.len() < 3 { return None; }
+                let vertices: Vec<Point<f32>> = points.iter().map(|p| point![p[0], p[1]]).collect();
+                let n = vertices.len() as u32;
+                let indices: Vec<[u32; 2]> = (0..n).map(|i| [i, (i+1)%n]).collect();
+                return Some(SharedShape::convex_decomposition(&vertices, &indices));
+            },
+        }
+    }
+
+    #[doc = r"Outline of obstacle as closed (polygon) or open (segment) line."]
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self {
+            ObstacleShape::Segment { a, b } => vec![vec2(a[0], a[1]), vec2(b[0], b[1])],
+            ObstacleShape::Polygon { points } => points.iter().map(|p| vec2(p[0], p[1])).collect(),
+        }
+    }
+
+    pub fn distance_to(&self, pos: Vec2) -> f32 {
+        let outline = self.outline();
+        let mut dist = f32::INFINITY;
+        let n = outline.len();
+        let edges = match self {
+            ObstacleShape::Segment { .. } => 1,
+            ObstacleShape::Polygon { .. } => n,
+        };
+        for i in 0..edges {
+            let a = outline[i];
+            let b = outline[(i+1)%n];
+            let ab = b - a;
+            let t = if ab.length_squared() > 0.0 { ((pos - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0) } else { 0.0 };
+            dist = dist.min(pos.distance(a + ab * t));
+        }
+        return dist;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Obstacle {
+    pub shape: ObstacleShape,
+    pub rbh: RigidBodyHandle,
+}
+
+#[doc = r"Static walls and obstacles added as fixed bodies to physics world."]
+pub struct ObstacleBox {
+    pub obstacles: StableMap<RigidBodyHandle, Obstacle>,
+}
+
+impl Default for ObstacleBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ObstacleBox {
+
+    pub fn new() -> Self {
+        Self {
+            obstacles: StableMap::default(),
+        }
+    }
+
+    pub fn add_obstacle(&mut self, shape: ObstacleShape, physics: &mut Physics) -> Option<RigidBodyHandle> {
+        let collider_shape = match shape.collider_shape() {
+            Some(collider_shape) => collider_shape,
+            None => {
+                println!("obstacle {:?} has not enough points", shape);
+                return None;
+            },
+        };
+        let rbh = physics.add_fixed_object(
+            collider_shape,
+            PhysicsMaterial::obstacle(),
+            InteractionGroups::new(Group::GROUP_3, Group::GROUP_1 | Group::GROUP_2),
+        );
+        self.obstacles.insert(rbh, Obstacle { shape, rbh });
+        return Some(rbh);
+    }
+
+    #[doc = r"Remove obstacle closest to position if it is not further than range."]
+    pub fn remove_obstacle_at(&mut self, pos: Vec2, range: f32, physics: &mut Physics) -> bool {
+        let mut closest: Option<(RigidBodyHandle, f32)> = None;
+        for (rbh, obstacle) in self.obstacles.iter() {
+            let dist = obstacle.shape.distance_to(pos);
+            if dist <= range && closest.map_or(true, |(_, d)| dist < d) {
+                closest = Some((*rbh, dist));
+            }
+        }
+        match closest {
+            Some((rbh, _)) => {
+                self.obstacles.remove(&rbh);
+                physics.remove_object(rbh);
+                return true;
+            },
+            None => {
+                return false;
+            },
+        }
+    }
+
+    pub fn get_iter(&self) -> impl Iterator<Item = (&RigidBodyHandle, &Obstacle)> {
+        return self.obstacles.iter();
+    }
+
+    pub fn get_shapes(&self) -> Vec<ObstacleShape> {
+        return self.obstacles.values().map(|obstacle| obstacle.shape.to_owned()).collect();
+    }
+
+    pub fn count(&self) -> usize {
+        return self.obstacles.len();
+    }
+}
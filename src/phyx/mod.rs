@@ -1,3 +1,2 @@
 pub mod physics;
-pub mod physics_misc;
-pub mod dbg;
\ No newline at end of file
+pub mod physics_misc;
\ No newline at end of file
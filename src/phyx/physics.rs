@@ -1,14 +1,11 @@
 //#![allow(unused)]
 
 use crate::util::*;
-use macroquad::prelude::*;
-use macroquad::math::clamp;
+use crate::math::*;
 use rapier2d::na::*;
 use rapier2d::prelude::*;
-use std::collections::HashSet;
 use crate::settings::*;
 use super::physics_misc::*;
-use crate::phyx::dbg::MacroRapierDebugger;
 
 pub struct Physics {
     pub core: PhysicsCore,
@@ -24,6 +21,11 @@ impl Physics {
         self.core.step_physics();
     }
 
+    #[doc = r"Makes queries see objects added since last step, e.g. after world was loaded."]
+    pub fn update_queries(&mut self) {
+        self.core.update_query_pipeline();
+    }
+
     pub fn remove_object(&mut self, rbh: RigidBodyHandle) {
         self.core.remove_physics_object(rbh);
     } 
@@ -44,8 +46,21 @@ impl Physics {
         return self.core.add_collider(rbh, rel_position, rotation, shape, material, groups);
     }
 
-    pub fn get_object_state(&self, rbh: RigidBodyHandle) -> PhysicState {
-        return self.core.get_physics_data(rbh);
+    pub fn add_fixed_object(&mut self, shape: SharedShape, material: PhysicsMaterial, groups: InteractionGroups) -> RigidBodyHandle {
+        return self.core.add_fixed(shape, material, groups);
+    }
+
+    #[doc = r"Distance along ray to closest fixed obstacle."]
+    pub fn cast_ray_to_fixed(&self, origin: &Vec2, direction: &Vec2, max_dist: f32) -> Option<f32> {
+        return self.core.cast_ray_to_fixed(origin, direction, max_dist);
+    }
+
+    pub fn touches_fixed(&self, position: &Vec2, radius: f32) -> bool {
+        return self.core.touches_fixed(position, radius);
+    }
+
+    pub fn get_object_state(&self, rbh: RigidBodyHandle, settings: &Settings) -> PhysicState {
+        return self.core.get_physics_data(rbh, settings);
     }
 
     pub fn get_object_position(&self, rbh: RigidBodyHandle) -> Option<Vec2> {
@@ -56,23 +71,20 @@ impl Physics {
         return self.core.get_object_size(rbh);
     }
 
-    pub fn get_closest_agent(&self, agent_body_handle: RigidBodyHandle, detection_range: f32, detection_angle: f32, direction: Vec2) -> Option<RigidBodyHandle> {
-        return self.core.get_closest_agent(agent_body_handle, detection_range, detection_angle, direction);
-    }
-
-    pub fn get_closest_plant(&self, agent_body_handle: RigidBodyHandle, detection_range: f32, detection_angle: f32, direction: Vec2) -> Option<RigidBodyHandle> {
-        return self.core.get_closest_plant(agent_body_handle, detection_range, detection_angle, direction);
+    pub fn get_contacts_set(&self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
+        return self.core.get_contacts_set(agent_body_handle, radius);
     }
 
-    pub fn get_contacts_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
-        return self.core.get_contacts_set(agent_body_handle, radius);
+    #[doc = r"Agents with any collider within radius from position, except excluded one."]
+    pub fn get_agents_in_range(&self, position: &Vec2, radius: f32, exclude: RigidBodyHandle) -> StableSet<RigidBodyHandle> {
+        return self.core.get_agents_in_range(position, radius, exclude);
     }
 
-    pub fn get_contacted_agent_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
+    pub fn get_contacted_agent_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
         return self.core.get_contacted_agent_set(agent_body_handle, radius);
     }
 
-    pub fn get_contacted_plant_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
+    pub fn get_contacted_plant_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
         return self.core.get_contacted_plant_set(agent_body_handle, radius);
     }
 
@@ -92,10 +104,6 @@ impl Physics {
         return self.core.rigid_bodies.iter_mut();
     }
 
-    pub fn count_near_plants(&self, rbh: RigidBodyHandle, detection_range: f32) -> usize {
-        return self.core.count_near_plants(rbh, detection_range);
-    }    
-
     pub fn get_first_collider_mut(&mut self, rbh: RigidBodyHandle) -> &mut Collider {
         let rb = self.core.rigid_bodies.get(rbh).unwrap();
         let ch = rb.colliders().first().unwrap();
@@ -103,8 +111,8 @@ impl Physics {
         return c;
     }
 
-    pub fn debug_draw(&mut self) {
-        self.core.debug_draw();
+    pub fn debug_render(&mut self, backend: &mut impl DebugRenderBackend) {
+        self.core.debug_render(backend);
     }
 
 }
@@ -125,7 +133,6 @@ pub struct PhysicsCore {
     multibody_joint_set: MultibodyJointSet,
     ccd_solver: CCDSolver,
     debug_render_pipeline: DebugRenderPipeline,
-    debug_renderer: MacroRapierDebugger,
     query_pipeline: QueryPipeline,
     physics_hooks: (),
     event_handler: (),
@@ -136,7 +143,7 @@ impl PhysicsCore {
     pub fn new() -> Self {
 
         let params = IntegrationParameters {
-            dt: 1./60.,
+            dt: SIM_TICK,
             ..Default::default()
         };
 
@@ -151,7 +158,6 @@ impl PhysicsCore {
             DebugRenderMode::COLLIDER_SHAPES | 
             DebugRenderMode::IMPULSE_JOINTS | 
             DebugRenderMode::JOINTS;
-            DebugRenderMode::SOLVER_CONTACTS;
 
         Self {
             attract_num: 0,
@@ -167,7 +173,6 @@ impl PhysicsCore {
             multibody_joint_set: MultibodyJointSet::new(),
             ccd_solver: CCDSolver::new(),
             debug_render_pipeline: DebugRenderPipeline::new(dbg_cfg, dbg_mode),
-            debug_renderer: MacroRapierDebugger,
             query_pipeline: QueryPipeline::new(),
             physics_hooks: (),
             event_handler: (),
@@ -194,6 +199,10 @@ impl PhysicsCore {
         );
     }
 
+    pub fn update_query_pipeline(&mut self) {
+        self.query_pipeline.update(&self.colliders);
+    }
+
     pub fn remove_physics_object(&mut self, body_handle: RigidBodyHandle) {
         _ = self.rigid_bodies.remove(body_handle, &mut self.island_manager, &mut self.colliders, &mut self.impulse_joint_set, &mut self.multibody_joint_set, true);
         //self.remove_body_key_relation(&body_handle);
@@ -261,8 +270,31 @@ impl PhysicsCore {
         return rbh;
     }
 
-    pub fn get_physics_data(&self, handle: RigidBodyHandle) -> PhysicState {
-        let settings = get_settings();
+    #[doc = r"Fixed body with collider given in world coordinates."]
+    pub fn add_fixed(&mut self, shape: SharedShape, physics_props: PhysicsMaterial, groups: InteractionGroups) -> RigidBodyHandle {
+        let fixed_body = RigidBodyBuilder::fixed().build();
+        let rbh = self.rigid_bodies.insert(fixed_body);
+        let collider = ColliderBuilder::new(shape).friction(physics_props.friction).restitution(physics_props.restitution)
+            .active_collision_types(ActiveCollisionTypes::default()).collision_groups(groups)
+            .build();
+        self.colliders.insert_with_parent(collider, rbh, &mut self.rigid_bodies);
+        return rbh;
+    }
+
+    pub fn cast_ray_to_fixed(&self, origin: &Vec2, direction: &Vec2, max_dist: f32) -> Option<f32> {
+        let ray = Ray::new(point![origin.x, origin.y], vector![direction.x, direction.y]);
+        let filter = QueryFilter::only_fixed().exclude_sensors();
+        return self.query_pipeline.cast_ray(&self.rigid_bodies, &self.colliders, &ray, max_dist, true, filter)
+            .map(|(_, toi)| toi);
+    }
+
+    pub fn touches_fixed(&self, position: &Vec2, radius: f32) -> bool {
+        let filter = QueryFilter::only_fixed().exclude_sensors();
+        let iso = make_isometry(position.x, position.y, 0.0);
+        return self.query_pipeline.intersection_with_shape(&self.rigid_bodies, &self.colliders, &iso, &Ball::new(radius), filter).is_some();
+    }
+
+    pub fn get_physics_data(&self, handle: RigidBodyHandle, settings: &Settings) -> PhysicState {
         if let Some(rb) = self.rigid_bodies.get(handle) {
             let iso = rb.position();
             let (pos, rot) = iso_to_vec2_rot(iso);
@@ -331,8 +363,8 @@ impl PhysicsCore {
         }
     }
 
-    pub fn get_contacts_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
-        let mut contacts: HashSet<RigidBodyHandle> = HashSet::new();
+    pub fn get_contacts_set(&self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
+        let mut contacts: StableSet<RigidBodyHandle> = StableSet::default();
         let rb = self.rigid_bodies.get(agent_body_handle).unwrap();
         let filter = QueryFilter {
             flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
@@ -356,8 +388,28 @@ impl PhysicsCore {
         return contacts;
     }
 
-    pub fn get_contacted_agent_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
-        let mut contacts: HashSet<RigidBodyHandle> = HashSet::new();
+    pub fn get_agents_in_range(&self, position: &Vec2, radius: f32, exclude: RigidBodyHandle) -> StableSet<RigidBodyHandle> {
+        let mut agents: StableSet<RigidBodyHandle> = StableSet::default();
+        let filter = QueryFilter {
+            flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
+            groups: Some(InteractionGroups::new(Group::GROUP_1, Group::GROUP_1)),
+            exclude_rigid_body: Some(exclude),
+            ..Default::default()
+        };
+        let iso = Isometry2::new(Vector2::new(position.x, position.y), 0.0);
+        self.query_pipeline.intersections_with_shape(&self.rigid_bodies, &self.colliders, &iso, &Ball::new(radius), filter,
+            |collided| {
+                if let Some(rbh) = self.get_body_handle_from_collider(collided) {
+                    agents.insert(rbh);
+                }
+                return true;
+            },
+        );
+        return agents;
+    }
+
+    pub fn get_contacted_agent_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
+        let mut contacts: StableSet<RigidBodyHandle> = StableSet::default();
         let rb = self.rigid_bodies.get(agent_body_handle).unwrap();
         let filter = QueryFilter {
             flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
@@ -381,8 +433,8 @@ impl PhysicsCore {
         return contacts;
     }
 
-    pub fn get_contacted_plant_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> HashSet<RigidBodyHandle> {
-        let mut contacts: HashSet<RigidBodyHandle> = HashSet::new();
+    pub fn get_contacted_plant_set(&mut self, agent_body_handle: RigidBodyHandle, radius: f32) -> StableSet<RigidBodyHandle> {
+        let mut contacts: StableSet<RigidBodyHandle> = StableSet::default();
         let rb = self.rigid_bodies.get(agent_body_handle).unwrap();
         let filter = QueryFilter {
             flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
@@ -406,109 +458,9 @@ impl PhysicsCore {
         return contacts;
     }
 
-    pub fn get_closest_agent(&self, agent_body_handle: RigidBodyHandle, detection_range: f32, detection_angle: f32, direction: Vec2) -> Option<RigidBodyHandle> {
-        let mut small_vision = get_settings().peripheral_vision*get_settings().agent_vision_range;
-        small_vision = clamp(small_vision, 0.0, detection_range);
-        let rb = self.rigid_bodies.get(agent_body_handle).unwrap();
-        let pos1 = matrix_to_vec2(rb.position().translation);
-        let mut dist = f32::INFINITY;
-        let mut target: RigidBodyHandle = RigidBodyHandle::invalid();
-        let detector = ColliderBuilder::ball(detection_range).sensor(true).density(0.0).build();
-        let filter = QueryFilter {
-            flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
-            groups: Some(InteractionGroups::new(Group::GROUP_1, Group::GROUP_1)),
-            exclude_collider: None,
-            exclude_rigid_body: Some(agent_body_handle),
-            ..Default::default()
-        };
-        self.query_pipeline.intersections_with_shape(&self.rigid_bodies, &self.colliders, rb.position(), detector.shape(), filter,
-            |collided| {
-                let rb2_handle = self.get_body_handle_from_collider(collided).unwrap();
-                let rb2 = self.rigid_bodies.get(rb2_handle).unwrap();
-                let pos2 = matrix_to_vec2(rb2.position().translation);
-                let new_dist = pos1.distance(pos2);
-                let local_pos = pos2 - pos1;
-                let ang = direction.angle_between((local_pos).normalize_or_zero());
-                if new_dist <= small_vision && new_dist < dist {
-                    dist = new_dist;
-                    target = rb2_handle;
-                } else if new_dist < dist && ang.abs() <= detection_angle/2.0 {
-                    dist = new_dist;
-                    target = rb2_handle;
-                }
-                return true;
-            },
-        );
-        if dist < f32::INFINITY {
-            return Some(target);
-        } else {
-            return None;
-        }
-    }
-
-    pub fn get_closest_plant(&self, agent_body_handle: RigidBodyHandle, detection_range: f32, detection_angle: f32, direction: Vec2) -> Option<RigidBodyHandle> {
-        let rb = self.rigid_bodies.get(agent_body_handle).unwrap();
-        let pos1 = matrix_to_vec2(rb.position().translation);
-        let mut dist = f32::INFINITY;
-        let mut target: RigidBodyHandle = RigidBodyHandle::invalid();
-        let detector = ColliderBuilder::ball(detection_range).sensor(true).density(0.0).build();
-        let filter = QueryFilter {
-            flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
-            groups: Some(InteractionGroups::new(Group::GROUP_2, Group::GROUP_2)),
-            exclude_collider: None,
-            exclude_rigid_body: Some(agent_body_handle),
-            ..Default::default()
-        };
-        self.query_pipeline.intersections_with_shape(&self.rigid_bodies, &self.colliders, rb.position(), detector.shape(), filter,
-            |collided| {
-                let rb2_handle = self.get_body_handle_from_collider(collided).unwrap();
-                let rb2 = self.rigid_bodies.get(rb2_handle).unwrap();
-                let pos2 = matrix_to_vec2(rb2.position().translation);
-                let new_dist = pos1.distance(pos2);
-                let local_pos = pos2 - pos1;
-                let ang = direction.angle_between((local_pos).normalize_or_zero());
-                if new_dist <= detection_range*0.1 && new_dist < dist {
-                    dist = new_dist;
-                    target = rb2_handle;
-                } else if new_dist < dist && ang.abs() <= detection_angle/2.0 {
-                    dist = new_dist;
-                    target = rb2_handle;
-                }
-                return true;
-            },
-        );
-        if dist < f32::INFINITY {
-            return Some(target);
-        } else {
-            return None;
-        }
-    }
-
-    pub fn count_near_plants(&self, rbh: RigidBodyHandle, detection_range: f32) -> usize {
-        let rb = self.rigid_bodies.get(rbh).unwrap();
-        //let pos1 = matrix_to_vec2(rb.position().translation);
-        //let mut dist = f32::INFINITY;
-        //let mut target: RigidBodyHandle = RigidBodyHandle::invalid();
-        let detector = ColliderBuilder::ball(detection_range).sensor(true).density(0.0).build();
-        let filter = QueryFilter {
-            flags: QueryFilterFlags::ONLY_DYNAMIC | QueryFilterFlags::EXCLUDE_SENSORS,
-            groups: Some(InteractionGroups::new(Group::GROUP_2, Group::GROUP_2)),
-            exclude_collider: None,
-            exclude_rigid_body: Some(rbh),
-            ..Default::default()
-        };
-        let mut n: usize = 0;
-        self.query_pipeline.intersections_with_shape(&self.rigid_bodies, &self.colliders, rb.position(), detector.shape(), filter,
-         |_| {
-            n += 1;
-            return true;
-        });
-        return n;
-    }
-
-    pub fn debug_draw(&mut self) {
+    pub fn debug_render(&mut self, backend: &mut impl DebugRenderBackend) {
         self.debug_render_pipeline.render(
-            &mut self.debug_renderer, 
+            backend, 
             &self.rigid_bodies, 
             &self.colliders, 
             &self.impulse_joint_set, 
@@ -1,7 +1,7 @@
 #![allow(unused)]
 
 use crate::util::*;
-use macroquad::prelude::*;
+use crate::math::*;
 use rapier2d::na::*;
 use rapier2d::prelude::*;
 use std::collections::hash_set::{Iter};
@@ -51,5 +51,13 @@ impl PhysicsMaterial {
     pub fn agent() -> Self {
         Self { friction: 0.8, restitution: 0.0, density: 1.0, linear_damping: 0.1, angular_damping: 0.7 }
     }
+
+    pub fn carcass() -> Self {
+        Self { friction: 0.9, restitution: 0.0, density: 3.0, linear_damping: 8.0, angular_damping: 1.0 }
+    }
+
+    pub fn obstacle() -> Self {
+        Self { friction: 0.3, restitution: 0.2, density: 1.0, linear_damping: 0.0, angular_damping: 0.0 }
+    }
 }
 
@@ -1,27 +1,159 @@
 //#![allow(unused)]
 
 use rapier2d::geometry::*;
-use macroquad::{prelude::*, color};
+use crate::math::*;
 use rapier2d::prelude::*;
 use crate::timer::Timer;
 use crate::util::*;
 use crate::phyx::physics::Physics;
 use crate::phyx::physics_misc::PhysicsMaterial;
 use crate::settings::*;
+use crate::rng::SimRng;
+use crate::environment::Environment;
+use crate::terrain::{Biome, Terrain};
+use serde::{Serialize, Deserialize};
+use crate::sketch::{PlantSketch, PlantKindSketch};
 
 
+#[doc = r"Species of plant. Every kind has its own behaviour, look and save representation."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlantKind {
+    Grass,
+    Bush,
+    Toxic,
+}
+
+impl PlantKind {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlantKind::Grass => "GRASS",
+            PlantKind::Bush => "BUSH",
+            PlantKind::Toxic => "TOXIC",
+        }
+    }
+
+    #[doc = r"Kind drawn with probability proportional to its share in settings."]
+    pub fn random(settings: &Settings, rng: &mut SimRng) -> Self {
+        let grass = settings.grass_share.max(0.0);
+        let bush = settings.bush_share.max(0.0);
+        let toxic = settings.toxic_share.max(0.0);
+        let total = grass + bush + toxic;
+        if total <= 0.0 {
+            return PlantKind::Grass;
+        }
+        let r = random_unit_unsigned(rng) * total;
+        if r < grass {
+            return PlantKind::Grass;
+        } else if r < grass + bush {
+            return PlantKind::Bush;
+        } else {
+            return PlantKind::Toxic;
+        }
+    }
+}
+
+
+#[doc = r"Heritable traits of plant. Clone gets genome of its parent with small mutations."]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PlantGenome {
+    #[doc = r"Size at which plant stops growing."]
+    pub max_size: f32,
+    #[doc = r"Multiplier of plant growth."]
+    pub growth: f32,
+    #[doc = r"Length of life in seconds."]
+    pub lifetime: f32,
+    #[doc = r"Maximal distance of clone from its parent."]
+    pub clone_distance: f32,
+    #[doc = r"Energy stored in plant per squared unit of size."]
+    pub energy_density: f32,
+    #[doc = r"Part (0.0-1.0) of eaten energy which poisons eater instead of feeding it. Toxic plant grows slower."]
+    pub toxicity: f32,
+}
+
+impl PlantGenome {
+
+    #[doc = r"Initial genome typical for plant kind."]
+    pub fn new(kind: PlantKind, settings: &Settings, rng: &mut SimRng) -> Self {
+        let lifetime = settings.plant_lifetime + settings.plant_lifetime * random_unit(rng) / 4.0;
+        match kind {
+            PlantKind::Grass => Self {
+                max_size: 6.0,
+                growth: 2.0,
+                lifetime: lifetime * 0.5,
+                clone_distance: 30.0,
+                energy_density: 6.0,
+                toxicity: 0.0,
+            },
+            PlantKind::Bush => Self {
+                max_size: 14.0,
+                growth: 0.5,
+                lifetime: lifetime * 3.0,
+                clone_distance: 20.0,
+                energy_density: 20.0,
+                toxicity: 0.0,
+            },
+            PlantKind::Toxic => Self {
+                max_size: 10.0,
+                growth: 1.0,
+                lifetime,
+                clone_distance: 25.0,
+                energy_density: 10.0,
+                toxicity: 0.6,
+            },
+        }
+    }
+
+    #[doc = r"Copy of genome with every trait changed by up to 20% with probability of plant_mutations."]
+    pub fn mutate(&self, settings: &Settings, rng: &mut SimRng) -> Self {
+        let mut genome = self.to_owned();
+        let mutate = |value: f32, min: f32, max: f32, rng: &mut SimRng| -> f32 {
+            if random_unit_unsigned(rng) < settings.plant_mutations {
+                return clamp(value + value.max(0.05) * random_unit(rng) * 0.2, min, max);
+            }
+            return value;
+        };
+        genome.max_size = mutate(genome.max_size, 3.0, 20.0, rng);
+        genome.growth = mutate(genome.growth, 0.2, 3.0, rng);
+        genome.lifetime = mutate(genome.lifetime, 10.0, 3000.0, rng);
+        genome.clone_distance = mutate(genome.clone_distance, 5.0, 100.0, rng);
+        genome.energy_density = mutate(genome.energy_density, 2.0, 30.0, rng);
+        genome.toxicity = mutate(genome.toxicity, 0.0, 1.0, rng);
+        return genome;
+    }
+}
+
+
+#[doc = r"Behaviour of plant species. Common state lives in base `Plant` which is shared by all kinds."]
 pub trait PlantType {
-    fn new(physics: &mut Physics) -> Self;
-    fn draw(&self, _show_range: bool);
-    fn update(&mut self, physics: &mut Physics);
-    fn resize(&mut self, physics: &mut Physics);
-    fn drain_eng(&mut self, eng_loss: f32);
-    fn update_physics(&mut self, physics: &mut Physics, _resize: bool);
-    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics) -> Option<Plant>;
-    fn check_edges(&mut self, body: &mut RigidBody);
-    fn is_alive(&self) -> bool;
-    fn get_body_handle(&self) -> RigidBodyHandle;
-    fn get_lifetime(&self) -> f32;
+    fn new(pos: Vec2, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Self where Self: Sized;
+    fn update(&mut self, physics: &mut Physics, terrain: &mut Terrain, settings: &Settings, env: &Environment);
+    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics, terrain: &Terrain, settings: &Settings, env: &Environment, rng: &mut SimRng) -> Option<Box<dyn PlantType>>;
+    fn get_kind(&self) -> PlantKind;
+    fn get_base(&self) -> &Plant;
+    fn get_base_mut(&mut self) -> &mut Plant;
+    fn get_sketch(&self) -> PlantKindSketch;
+
+    fn drain_eng(&mut self, eng_loss: f32) {
+        self.get_base_mut().eng -= eng_loss;
+    }
+
+    #[doc = r"Part of eaten energy which poisons eater."]
+    fn get_toxicity(&self) -> f32 {
+        return self.get_base().genome.toxicity;
+    }
+
+    fn is_alive(&self) -> bool {
+        return self.get_base().alife;
+    }
+
+    fn get_body_handle(&self) -> RigidBodyHandle {
+        return self.get_base().physics_handle;
+    }
+
+    fn get_lifetime(&self) -> f32 {
+        return self.get_base().time;
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -31,128 +163,99 @@ pub struct Plant {
     pub size: f32,
     pub max_eng: f32,
     pub eng: f32,
-    color: color::Color,
     //shape: Ball,
     physics_handle: RigidBodyHandle,
     alife: bool,
     pub time: f32,
     clone_timer: Timer,
     growth_timer: Timer,
-    life_length: f32,
+    pub life_length: f32,
     clone_ready: bool,
+    biome: Biome,
+    water: i32,
+    pub genome: PlantGenome,
+    pub generation: u32,
 }
 
 
-impl PlantType for Plant {
-    
-    fn new(physics: &mut Physics) -> Self {
-        let settings = get_settings();
-        let pos = random_position(settings.world_w as f32, settings.world_h as f32);
-        let size = 2.0;
-        let shape = SharedShape::ball(size);
-        let rbh = physics.add_dynamic_object(
-            &pos, 
-            0.0, 
-            shape.clone(), 
-            PhysicsMaterial::plant(), 
-            InteractionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_2), 
-            true
-        );
-        let max_life = settings.plant_lifetime + settings.plant_lifetime * random_unit() / 4.0;
-        Self {
-            pos,
-            rot: 0.0,
-            size,
-            max_eng: size.powi(2)*10.0,
-            eng: size.powi(2)*10.0,
-            color: YELLOW,
-            //shape: Ball { radius: size },
-            physics_handle: rbh,
-            life_length: max_life,
-            time: max_life,
-            alife: true,
-            clone_timer: Timer::new(10.0, true, true, true),
-            growth_timer: Timer::new(10.0, true, true, true),
-            clone_ready: false,
-        }
-    }
-    
-    fn draw(&self, _show_range: bool) {
-        let x0 = self.pos.x;
-        let y0 = self.pos.y;
-        let age = self.time/self.life_length;
-        let g = clamp(1.0, 0., 1.,);
-        let r = clamp(-0.25+(1.5-age), 0., 0.75,);
-        let b = clamp(0., 0., 1.,);
-        let color = Color::new(r, g, b, 1.0);
-        draw_circle(x0, y0, self.size, color);
-        draw_circle_lines(x0, y0, self.size, self.size/5.0, DARKGREEN)
-    }
-    
-    fn update(&mut self, physics: &mut Physics){
-        let dt = dt()*sim_speed();
-        let settings = get_settings();
+impl Plant {
+
+    #[doc = r"Growth, aging and death of plant. Plant which can't starve stays alive with no energy and regrows.
+Energy never exceeds max_eng and plant takes from soil only nutrients it can store."]
+    pub fn grow(&mut self, physics: &mut Physics, terrain: &mut Terrain, settings: &Settings, env: &Environment, can_starve: bool) {
+        let dt = dt();
         let mut resize = false;
         self.time -= dt;
-        self.eng += settings.growth * dt;
+        let genome = &self.genome;
+        self.eng += settings.growth * env.growth * self.biome.plant_growth() * genome.growth * (1.0 - 0.5 * genome.toxicity) * dt;
+        self.eng = self.eng.min(self.max_eng);
+        let room = (self.max_eng - self.eng).max(0.0);
+        self.eng += terrain.take_nutrients(&self.pos, (settings.nutrient_uptake * dt).min(room), settings.topology == WorldTopology::TORUS);
         if self.growth_timer.update(dt) {
-            if self.eng >= self.size.powi(2)*10.0 {
+            let density = self.genome.energy_density;
+            if self.eng >= self.size.powi(2)*density && self.size + 1.0 <= self.genome.max_size {
                 self.size += 1.0;
                 resize = true;
-                if self.size >= settings.plant_clone_size as f32 {
+                if self.size >= (settings.plant_clone_size as f32).min(self.genome.max_size) {
                     self.clone_ready = true;
                 }
-            } else if self.eng < (self.size-1.0).powi(2)*10.0 && self.size >= 1.0 {
+            } else if self.eng < (self.size-1.0).powi(2)*density && self.size >= 1.0 {
                 self.size -= 1.0;
                 resize = true;
             }
         }
-        self.update_physics(physics, resize);
-        self.pos = wrap_around(&self.pos);
-        if self.eng <= 0.0 || self.time <= 0.0 {
+        self.update_physics(physics, resize, settings);
+        self.pos = wrap_around(&self.pos, settings);
+        if self.pos.x.is_nan() || self.pos.y.is_nan() {
+            self.alife = false;
+            return;
+        }
+        if !can_starve && self.eng < 0.0 {
+            self.eng = 0.0;
+        }
+        if (can_starve && self.eng <= 0.0) || self.time <= 0.0 || self.water >= settings.plant_drown_depth {
             self.eng = 0.0;
             self.alife = false;
             return;
         }
         if resize {
             self.resize(physics);
-            self.max_eng = self.size.powi(2)*10.0;
+            self.max_eng = self.size.powi(2)*self.genome.energy_density;
             if self.eng > self.max_eng {
                 self.eng = self.max_eng;
             }
         }
     }
 
-    fn resize(&mut self, physics: &mut Physics) {
+    pub fn resize(&mut self, physics: &mut Physics) {
         let c = physics.get_first_collider_mut(self.physics_handle);
         c.set_shape(SharedShape::ball(self.size));
     }
 
-    fn drain_eng(&mut self, eng_loss: f32) {
-        self.eng -= eng_loss;
-    }
-
-    fn update_physics(&mut self, physics: &mut Physics, _resize: bool) {
-        let physics_data = physics.get_object_state(self.physics_handle);
+    pub fn update_physics(&mut self, physics: &mut Physics, _resize: bool, settings: &Settings) {
+        let physics_data = physics.get_object_state(self.physics_handle, settings);
         self.pos = physics_data.position;
         self.rot = physics_data.rotation;
         match physics.get_object_mut(self.physics_handle) {
             Some(body) => {
-                self.check_edges(body);
+                self.check_edges(body, settings);
             }
             None => {}
         }
     }
 
-    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics) -> Option<Plant> {
-        if self.clone_timer.update(dt()*sim_speed()) {
+    #[doc = r"Young plant with mutated genome if parent is ready to clone and place is suitable."]
+    pub fn offspring(&mut self, plant_num: i32, physics: &mut Physics, terrain: &Terrain, settings: &Settings, env: &Environment, rng: &mut SimRng) -> Option<Plant> {
+        if self.clone_timer.update(dt()) {
             if self.clone_ready {
-                let plant_balance = get_settings().plant_balance as f32;
-                let r = plant_balance/((plant_num as f32));
-                if random_unit_unsigned() > r { return None; }
+                let plant_balance = settings.plant_balance as f32;
+                let r = env.growth * self.biome.plant_density() * plant_balance/((plant_num as f32));
+                if random_unit_unsigned(rng) > r { return None; }
                 self.clone_ready = false;
-                let mut plant = Plant::new(physics);
-                plant.pos = self.pos + random_unit_vec2() * 25.0;
+                let pos = wrap_around(&(self.pos + random_unit_vec2(rng) * self.genome.clone_distance), settings);
+                if random_unit_unsigned(rng) > terrain.plant_suitability(&pos, settings) { return None; }
+                let genome = self.genome.mutate(settings, rng);
+                let plant = Plant::with_genome(pos, genome, self.generation + 1, physics, settings, rng);
                 return Some(plant);
             } else {
                 return None;
@@ -162,9 +265,15 @@ impl PlantType for Plant {
         }
     }
 
-    fn check_edges(&mut self, body: &mut RigidBody) {
-        let settings = get_settings();
+    pub fn check_edges(&mut self, body: &mut RigidBody, settings: &Settings) {
         let mut raw_pos = matrix_to_vec2(body.position().translation);
+        if settings.topology == WorldTopology::TORUS {
+            let wrapped = wrap_around(&raw_pos, settings);
+            if wrapped != raw_pos {
+                body.set_position(make_isometry(wrapped.x, wrapped.y, self.rot), true);
+            }
+            return;
+        }
         let mut out_of_edge = false;
         if raw_pos.x < -5.0 {
             raw_pos.x = 0.0;
@@ -185,16 +294,248 @@ impl PlantType for Plant {
         }
     }    
 
-    fn is_alive(&self) -> bool {
-        return self.alife;
+    #[doc = r"Young plant with given genome at position."]
+    pub fn with_genome(pos: Vec2, genome: PlantGenome, generation: u32, physics: &mut Physics, _settings: &Settings, rng: &mut SimRng) -> Self {
+        let clone_timer = Timer::new_random(10.0, true, true, rng);
+        let growth_timer = Timer::new_random(10.0, true, true, rng);
+        return Self::with_timers(pos, genome, generation, clone_timer, growth_timer, physics);
     }
 
-    fn get_lifetime(&self) -> f32 {
-        return self.time;
+    fn with_timers(pos: Vec2, genome: PlantGenome, generation: u32, clone_timer: Timer, growth_timer: Timer, physics: &mut Physics) -> Self {
+        let size = 2.0;
+        let shape = SharedShape::ball(size);
+        let rbh = physics.add_dynamic_object(
+            &pos, 
+            0.0, 
+            shape.clone(), 
+            PhysicsMaterial::plant(), 
+            InteractionGroups::new(Group::GROUP_2, Group::GROUP_1 | Group::GROUP_2 | Group::GROUP_3), 
+            true
+        );
+        Self {
+            pos,
+            rot: 0.0,
+            size,
+            max_eng: size.powi(2)*genome.energy_density,
+            eng: size.powi(2)*genome.energy_density,
+            //shape: Ball { radius: size },
+            physics_handle: rbh,
+            life_length: genome.lifetime,
+            time: genome.lifetime,
+            alife: true,
+            clone_timer,
+            growth_timer,
+            clone_ready: false,
+            biome: Biome::default(),
+            water: 0,
+            genome,
+            generation,
+        }
     }
 
-    fn get_body_handle(&self) -> RigidBodyHandle {
-        return self.physics_handle;
+    pub fn set_biome_tile(&mut self, biome: Biome) {
+        self.biome = biome;
+    }
+
+    pub fn set_water_tile(&mut self, water: i32) {
+        self.water = water;
+    }
+
+    pub fn get_biome(&self) -> Biome {
+        return self.biome;
+    }
+
+    pub fn get_sketch(&self) -> PlantSketch {
+        return PlantSketch {
+            pos: [self.pos.x, self.pos.y],
+            size: self.size,
+            eng: self.eng,
+            time: self.time,
+            generation: self.generation,
+            genome: self.genome.to_owned(),
+            clone_timer: Some(self.clone_timer),
+            growth_timer: Some(self.growth_timer),
+        };
+    }
+
+    #[doc = r"Plant loaded from save. Plants saved without timers get them started from zero, so loading never draws from rng."]
+    pub fn from_sketch(sketch: &PlantSketch, physics: &mut Physics, settings: &Settings) -> Self {
+        let pos = vec2(sketch.pos[0], sketch.pos[1]);
+        let clone_timer = sketch.clone_timer.unwrap_or(Timer::new(10.0, true, true));
+        let growth_timer = sketch.growth_timer.unwrap_or(Timer::new(10.0, true, true));
+        let mut plant = Plant::with_timers(pos, sketch.genome.to_owned(), sketch.generation, clone_timer, growth_timer, physics);
+        plant.size = sketch.size.max(1.0);
+        plant.max_eng = plant.size.powi(2)*plant.genome.energy_density;
+        plant.eng = sketch.eng;
+        plant.time = sketch.time;
+        plant.clone_ready = plant.size >= (settings.plant_clone_size as f32).min(plant.genome.max_size);
+        plant.resize(physics);
+        return plant;
+    }
+}
+
+
+#[doc = r"New plant of given kind as trait object."]
+pub fn new_plant(kind: PlantKind, pos: Vec2, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Box<dyn PlantType> {
+    match kind {
+        PlantKind::Grass => Box::new(Grass::new(pos, physics, settings, rng)),
+        PlantKind::Bush => Box::new(Bush::new(pos, physics, settings, rng)),
+        PlantKind::Toxic => Box::new(ToxicPlant::new(pos, physics, settings, rng)),
+    }
+}
+
+#[doc = r"Restore plant of kind stored in save."]
+pub fn plant_from_sketch(sketch: &PlantKindSketch, physics: &mut Physics, settings: &Settings) -> Box<dyn PlantType> {
+    match sketch {
+        PlantKindSketch::Grass { plant } => {
+            return Box::new(Grass { base: Plant::from_sketch(plant, physics, settings) });
+        },
+        PlantKindSketch::Bush { plant, regrowths } => {
+            let base = Plant::from_sketch(plant, physics, settings);
+            return Box::new(Bush { base, bare: base.eng <= 0.0, regrowths: *regrowths });
+        },
+        PlantKindSketch::Toxic { plant } => {
+            return Box::new(ToxicPlant { base: Plant::from_sketch(plant, physics, settings) });
+        },
+    }
+}
+
+
+#[doc = r"Small, fast growing and short living plant."]
+pub struct Grass {
+    pub base: Plant,
+}
+
+impl PlantType for Grass {
+
+    fn new(pos: Vec2, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Self {
+        let genome = PlantGenome::new(PlantKind::Grass, settings, rng);
+        Self {
+            base: Plant::with_genome(pos, genome, 0, physics, settings, rng),
+        }
+    }
+
+    fn update(&mut self, physics: &mut Physics, terrain: &mut Terrain, settings: &Settings, env: &Environment) {
+        self.base.grow(physics, terrain, settings, env, true);
+    }
+
+    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics, terrain: &Terrain, settings: &Settings, env: &Environment, rng: &mut SimRng) -> Option<Box<dyn PlantType>> {
+        return self.base.offspring(plant_num, physics, terrain, settings, env, rng)
+            .map(|base| Box::new(Grass { base }) as Box<dyn PlantType>);
+    }
+
+    fn get_kind(&self) -> PlantKind {
+        return PlantKind::Grass;
+    }
+
+    fn get_base(&self) -> &Plant {
+        return &self.base;
+    }
+
+    fn get_base_mut(&mut self) -> &mut Plant {
+        return &mut self.base;
+    }
+
+    fn get_sketch(&self) -> PlantKindSketch {
+        return PlantKindSketch::Grass { plant: self.base.get_sketch() };
+    }
+}
+
+
+#[doc = r"Slow growing, long living and nutritious plant. Eaten to the ground it doesn't die but regrows."]
+pub struct Bush {
+    pub base: Plant,
+    pub bare: bool,
+    pub regrowths: u32,
+}
+
+impl PlantType for Bush {
+
+    fn new(pos: Vec2, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Self {
+        let genome = PlantGenome::new(PlantKind::Bush, settings, rng);
+        Self {
+            base: Plant::with_genome(pos, genome, 0, physics, settings, rng),
+            bare: false,
+            regrowths: 0,
+        }
+    }
+
+    fn update(&mut self, physics: &mut Physics, terrain: &mut Terrain, settings: &Settings, env: &Environment) {
+        if self.base.eng <= 0.0 && !self.bare {
+            self.bare = true;
+            self.regrowths += 1;
+        }
+        self.base.grow(physics, terrain, settings, env, false);
+        if self.bare && self.base.eng > 0.0 {
+            self.bare = false;
+        }
+    }
+
+    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics, terrain: &Terrain, settings: &Settings, env: &Environment, rng: &mut SimRng) -> Option<Box<dyn PlantType>> {
+        return self.base.offspring(plant_num, physics, terrain, settings, env, rng)
+            .map(|base| Box::new(Bush { base, bare: false, regrowths: 0 }) as Box<dyn PlantType>);
+    }
+
+    fn get_kind(&self) -> PlantKind {
+        return PlantKind::Bush;
     }
 
+    fn get_base(&self) -> &Plant {
+        return &self.base;
+    }
+
+    fn get_base_mut(&mut self) -> &mut Plant {
+        return &mut self.base;
+    }
+
+    fn get_sketch(&self) -> PlantKindSketch {
+        return PlantKindSketch::Bush { plant: self.base.get_sketch(), regrowths: self.regrowths };
+    }
+}
+
+
+pub const TOXIC_PLANT_MIN_TOXICITY: f32 = 0.4;
+
+#[doc = r"Poisonous plant. Its toxicity never falls below TOXIC_PLANT_MIN_TOXICITY, however genome evolves."]
+pub struct ToxicPlant {
+    pub base: Plant,
+}
+
+impl PlantType for ToxicPlant {
+
+    fn new(pos: Vec2, physics: &mut Physics, settings: &Settings, rng: &mut SimRng) -> Self {
+        let genome = PlantGenome::new(PlantKind::Toxic, settings, rng);
+        Self {
+            base: Plant::with_genome(pos, genome, 0, physics, settings, rng),
+        }
+    }
+
+    fn update(&mut self, physics: &mut Physics, terrain: &mut Terrain, settings: &Settings, env: &Environment) {
+        self.base.grow(physics, terrain, settings, env, true);
+    }
+
+    fn update_cloning(&mut self, plant_num: i32, physics: &mut Physics, terrain: &Terrain, settings: &Settings, env: &Environment, rng: &mut SimRng) -> Option<Box<dyn PlantType>> {
+        return self.base.offspring(plant_num, physics, terrain, settings, env, rng)
+            .map(|base| Box::new(ToxicPlant { base }) as Box<dyn PlantType>);
+    }
+
+    fn get_toxicity(&self) -> f32 {
+        return self.base.genome.toxicity.max(TOXIC_PLANT_MIN_TOXICITY);
+    }
+
+    fn get_kind(&self) -> PlantKind {
+        return PlantKind::Toxic;
+    }
+
+    fn get_base(&self) -> &Plant {
+        return &self.base;
+    }
+
+    fn get_base_mut(&mut self) -> &mut Plant {
+        return &mut self.base;
+    }
+
+    fn get_sketch(&self) -> PlantKindSketch {
+        return PlantKindSketch::Toxic { plant: self.base.get_sketch() };
+    }
 }
@@ -3,10 +3,12 @@
 
 use std::path::Iter;
 use std::vec::IntoIter;
-use macroquad::rand::{self, ChooseRandom};
 use crate::agent::Agent;
 use crate::sketch::AgentSketch;
 use crate::settings::*;
+use crate::rng::SimRng;
+use crate::genome::AgentGenome;
+use crate::util::StableSet;
 
 pub struct Ranking {
     pub general: Vec<AgentSketch>,
@@ -35,8 +37,8 @@ impl Ranking {
     }
 
     fn update_general(&mut self) {
-        let settings = get_settings();
         self.general.sort_by(|a, b| b.points.total_cmp(&a.points));
+        Self::remove_clones(&mut self.general);
         let general_copy = self.general.to_vec();
         for elem1 in general_copy.iter() {
             self.general.retain(|elem2| {
@@ -59,8 +61,8 @@ impl Ranking {
     }
 
     fn update_school(&mut self) {
-        let settings = get_settings();
         self.school.sort_by(|a, b| b.points.total_cmp(&a.points));
+        Self::remove_clones(&mut self.school);
         let school_copy = self.school.to_vec();
         for elem1 in school_copy.iter() {
             self.school.retain(|elem2| {
@@ -82,6 +84,12 @@ impl Ranking {
         }
     }
 
+    #[doc = r"Keeps only best scored agent among agents with identical genomes. Expects list sorted by points."]
+    fn remove_clones(list: &mut Vec<AgentSketch>) {
+        let mut genomes: StableSet<u64> = StableSet::default();
+        list.retain(|elem| genomes.insert(AgentGenome::from_sketch(elem).fingerprint()));
+    }
+
     pub fn add_agent(&mut self, agent: AgentSketch) {
         let gen = agent.generation;
         if gen <= self.school_max_gen {
@@ -111,14 +119,14 @@ impl Ranking {
         self.school.is_empty()
     }
 
-    pub fn get_random_agent(&mut self) -> Option<AgentSketch> {
+    pub fn get_random_agent(&mut self, rng: &mut SimRng) -> Option<AgentSketch> {
         let mut s: AgentSketch;
-        if rand::gen_range(0, 2)  == 0 {
+        if rng.gen_range(0, 2)  == 0 {
             if self.is_general_empty() {
                 return None;
             }
             let i = self.general.len()-1;
-            let idx = rand::gen_range(0, i);
+            let idx = rng.gen_range(0, i);
             let agent = self.general.get_mut(idx).unwrap();
             s = agent.to_owned();
             agent.points -= agent.points*0.5;
@@ -128,7 +136,7 @@ impl Ranking {
                 return None;
             }
             let i = self.school.len()-1;
-            let idx = rand::gen_range(0, i);
+            let idx = rng.gen_range(0, i);
             let agent = self.school.get_mut(idx).unwrap();
             s = agent.to_owned();
             agent.points -= agent.points*0.5;
@@ -137,9 +145,9 @@ impl Ranking {
         return Some(s);
     }
 
-    fn get_random_from_ranking(&mut self) -> AgentSketch {
+    fn get_random_from_ranking(&mut self, rng: &mut SimRng) -> AgentSketch {
         let i = self.general.len()-1;
-        let idx = rand::gen_range(0, i);
+        let idx = rng.gen_range(0, i);
         let agent = self.general.get_mut(idx).unwrap();
         let s = agent.to_owned();
         agent.points -= agent.points*0.5;
@@ -147,9 +155,9 @@ impl Ranking {
         return s;
     }
 
-    fn get_random_from_school(&mut self) -> AgentSketch {
+    fn get_random_from_school(&mut self, rng: &mut SimRng) -> AgentSketch {
         let i = self.general.len()-1;
-        let idx = rand::gen_range(0, i);
+        let idx = rng.gen_range(0, i);
         let agent = self.general.get_mut(idx).unwrap();
         let s = agent.to_owned();
         agent.points -= agent.points*0.5;
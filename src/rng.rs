@@ -0,0 +1,58 @@
+//#![allow(unused)]
+
+use rand::{Error, Rng, RngCore, SeedableRng};
+use rand::distributions::uniform::SampleUniform;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+
+#[doc = r"Seedable random number generator owned by simulation. Every random decision is drawn from it,
+so the same seed, settings and number of ticks give the same results. Its state is saved with simulation."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl SimRng {
+
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    #[doc = r"Random value in range low..high (returns low for empty range)."]
+    pub fn gen_range<T: SampleUniform + PartialOrd>(&mut self, low: T, high: T) -> T {
+        if low < high {
+            return self.rng.gen_range(low..high);
+        }
+        return low;
+    }
+
+}
+
+impl RngCore for SimRng {
+
+    fn next_u32(&mut self) -> u32 {
+        return self.rng.next_u32();
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        return self.rng.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        return self.rng.try_fill_bytes(dest);
+    }
+
+}
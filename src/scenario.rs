@@ -0,0 +1,249 @@
+//#![allow(unused)]
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::settings::*;
+use crate::terrain::*;
+use crate::rng::SimRng;
+use crate::obstacle::ObstacleShape;
+
+
+#[doc = r"Terrain of scenario: noise generator params, optional heightmap file (rows of altitudes 0-100 separated by spaces or commas),
+springs and sinks of water and whether water cycle runs from start."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioTerrain {
+    #[serde(default)]
+    pub noise: TerrainNoise,
+    #[serde(default)]
+    pub heightmap: Option<String>,
+    #[serde(default)]
+    pub springs: Vec<Spring>,
+    #[doc = r"Runs water cycle and flow. On by default, so rivers and lakes change over time."]
+    #[serde(default = "default_update")]
+    pub update: bool,
+}
+
+fn default_update() -> bool {
+    return true;
+}
+
+impl Default for ScenarioTerrain {
+    fn default() -> Self {
+        Self { noise: TerrainNoise::default(), heightmap: None, springs: vec![], update: default_update() }
+    }
+}
+
+#[doc = r"Agents loaded from saved .agent file (from saves/agents/) placed at start of scenario."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeedAgents {
+    pub file: String,
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+fn default_count() -> usize {
+    return 1;
+}
+
+#[doc = r"Change of simulation made by scenario at given time."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScenarioAction {
+    Settings { values: Map<String, Value> },
+    AddWater { x: f32, y: f32, radius: f32, amount: i32 },
+    AddTerrain { x: f32, y: f32, radius: f32, amount: i32 },
+    Rain { x: f32, y: f32, radius: f32, duration: f32 },
+    SpawnAgents { file: String, #[serde(default = "default_count")] count: usize },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimedAction {
+    pub time: f64,
+    #[serde(flatten)]
+    pub action: ScenarioAction,
+}
+
+#[doc = r"Declarative description of experiment loaded from json file:
+world size, terrain, obstacles, initial population, settings overrides and timed actions."]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub world: Option<[i32; 2]>,
+    #[serde(default)]
+    pub terrain: ScenarioTerrain,
+    #[serde(default)]
+    pub agents: Option<usize>,
+    #[serde(default)]
+    pub plants: Option<usize>,
+    #[serde(default)]
+    pub seed_agents: Vec<SeedAgents>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleShape>,
+    #[serde(default)]
+    pub settings: Map<String, Value>,
+    #[serde(default)]
+    pub actions: Vec<TimedAction>,
+}
+
+impl Scenario {
+
+    #[doc = r"Reads and validates scenario. Returns message for user when file can't be read or describes impossible world."]
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("can't read scenario from {}: {}", path.display(), e))?;
+        let mut scenario = serde_json::from_str::<Scenario>(&content)
+            .map_err(|e| format!("can't deserialize scenario from {}: {}", path.display(), e))?;
+        scenario.validate().map_err(|e| format!("invalid scenario {}: {}", path.display(), e))?;
+        scenario.actions.sort_by(|a, b| a.time.total_cmp(&b.time));
+        return Ok(scenario);
+    }
+
+    #[doc = r"Checks that world has positive size and every settings override names existing setting with valid value.
+Negative counts of agents and plants are already rejected when scenario is deserialized."]
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some([w, h]) = self.world {
+            if w <= 0 || h <= 0 {
+                return Err(format!("world size must be positive, got {}x{}", w, h));
+            }
+        }
+        check_overrides(&self.settings)?;
+        for timed in self.actions.iter() {
+            if let ScenarioAction::Settings { values } = &timed.action {
+                check_overrides(values).map_err(|e| format!("action at {}: {}", timed.time, e))?;
+            }
+        }
+        let settings = self.apply_settings(&Settings::default());
+        if settings.world_w <= 0 || settings.world_h <= 0 {
+            return Err(format!("world size must be positive, got {}x{}", settings.world_w, settings.world_h));
+        }
+        return Ok(());
+    }
+
+    #[doc = r"Settings with world size, initial population and overrides of scenario applied."]
+    pub fn apply_settings(&self, settings: &Settings) -> Settings {
+        let mut settings = override_settings(settings, &self.settings);
+        if let Some([w, h]) = self.world {
+            settings.world_w = w;
+            settings.world_h = h;
+        }
+        if let Some(n) = self.agents {
+            settings.agent_init_num = n;
+        }
+        if let Some(n) = self.plants {
+            settings.plant_init_num = n;
+        }
+        return settings;
+    }
+
+    pub fn build_terrain(&self, settings: &Settings, rng: &mut SimRng) -> Terrain {
+        let mut terrain = Terrain::with_noise(settings.world_w as f32, settings.world_h as f32, settings.grid_size as f32, &self.terrain.noise, rng);
+        if let Some(path) = &self.terrain.heightmap {
+            match read_heightmap(Path::new(path)) {
+                Some(heightmap) => terrain.apply_heightmap(&heightmap),
+                None => {},
+            }
+        }
+        terrain.springs = self.terrain.springs.to_vec();
+        return terrain;
+    }
+}
+
+fn check_overrides(values: &Map<String, Value>) -> Result<(), String> {
+    let serialized = serde_json::to_value(Settings::default()).unwrap();
+    for (key, value) in values.iter() {
+        if serialized.get(key).is_none() {
+            return Err(format!("unknown setting {}", key));
+        }
+        let mut changed = serialized.clone();
+        changed[key] = value.to_owned();
+        if let Err(e) = serde_json::from_value::<Settings>(changed) {
+            return Err(format!("invalid value of setting {}: {}", key, e));
+        }
+    }
+    return Ok(());
+}
+
+#[doc = r"Copy of settings with values from json object (keys are names of settings fields). Unknown or invalid values are skipped."]
+pub fn override_settings(settings: &Settings, values: &Map<String, Value>) -> Settings {
+    let mut serialized = serde_json::to_value(settings).unwrap();
+    for (key, value) in values.iter() {
+        if serialized.get(key).is_none() {
+            println!("scenario: unknown setting {}", key);
+            continue;
+        }
+        let mut changed = serialized.clone();
+        changed[key] = value.to_owned();
+        match serde_json::from_value::<Settings>(changed.clone()) {
+            Ok(_) => serialized = changed,
+            Err(e) => println!("scenario: invalid value of setting {}: {}", key, e),
+        }
+    }
+    return serde_json::from_value::<Settings>(serialized).unwrap_or(settings.to_owned());
+}
+
+fn read_heightmap(path: &Path) -> Option<Vec<Vec<i32>>> {
+    match fs::read_to_string(path) {
+        Err(e) => {
+            println!("can't read heightmap from {}: {}", path.to_str().unwrap(), e);
+            return None;
+        },
+        Ok(content) => {
+            let heightmap: Vec<Vec<i32>> = content.lines()
+                .map(|line| line.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter_map(|v| v.trim().parse::<i32>().ok())
+                    .collect::<Vec<i32>>())
+                .filter(|row| !row.is_empty())
+                .collect();
+            return Some(heightmap);
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<Scenario, String> {
+        let scenario = serde_json::from_str::<Scenario>(json).map_err(|e| e.to_string())?;
+        scenario.validate()?;
+        return Ok(scenario);
+    }
+
+    #[test]
+    fn accepts_valid_scenario() {
+        let scenario = parse(r#"{"world": [800, 600], "agents": 10, "settings": {"agent_min_num": 5},
+            "actions": [{"time": 10.0, "type": "settings", "values": {"plant_min_num": 3}}]}"#).unwrap();
+        let settings = scenario.apply_settings(&Settings::default());
+        assert_eq!((settings.world_w, settings.world_h), (800, 600));
+        assert_eq!(settings.agent_init_num, 10);
+        assert_eq!(settings.agent_min_num, 5);
+    }
+
+    #[test]
+    fn example_scenario_is_valid() {
+        assert!(parse(include_str!("../docs/scenario_example.json")).is_ok());
+    }
+
+    #[test]
+    fn rejects_impossible_world() {
+        assert!(parse(r#"{"world": [0, 0]}"#).is_err());
+        assert!(parse(r#"{"world": [500, -1]}"#).is_err());
+        assert!(parse(r#"{"settings": {"world_w": 0}}"#).is_err());
+        assert!(parse(r#"{"agents": -5}"#).is_err());
+        assert!(parse(r#"{"plants": -1}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_or_invalid_overrides() {
+        assert!(parse(r#"{"settings": {"no_such_setting": 1}}"#).is_err());
+        assert!(parse(r#"{"settings": {"agent_min_num": "many"}}"#).is_err());
+        assert!(parse(r#"{"actions": [{"time": 5.0, "type": "settings", "values": {"typo": 1}}]}"#).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 #![allow(unused)]
 
-use macroquad::{experimental::collections::storage, prelude::Vec2};
+use crate::math::Vec2;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use base64::prelude::*;
 
 //use crate::globals::*;
 
@@ -22,17 +25,44 @@ pub enum SelectMode {
     CHILDS,
 }
 
-
-pub fn set_settings(settings: Settings) {
-    storage::store(settings);
+#[doc = r"Shape of world: bounded by walls or torus where opposite edges are connected."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorldTopology {
+    BOUNDED,
+    TORUS,
 }
 
-pub fn get_settings() -> Settings {
-    return storage::get::<Settings>().clone();
+#[doc = r"How agents reproduce: by cloning single parent or by mating with compatible partner in contact."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReproductionMode {
+    ASEXUAL,
+    SEXUAL,
 }
 
-pub fn sim_speed() -> f32 {
-    return get_settings().sim_speed;
+
+#[doc = r"Reads settings from file exported by simulation (base64 encoded) or from plain json file."]
+pub fn read_settings(path: &Path) -> Option<Settings> {
+    match fs::read_to_string(path) {
+        Err(e) => {
+            println!("can't read settings from {}: {}", path.to_str().unwrap(), e);
+            return None;
+        },
+        Ok(content) => {
+            let json = match BASE64_STANDARD.decode(content.trim().as_bytes()) {
+                Ok(decoded) => String::from_utf8(decoded).unwrap_or(content),
+                Err(_) => content,
+            };
+            match serde_json::from_str::<Settings>(&json) {
+                Ok(settings) => {
+                    return Some(settings);
+                },
+                Err(e) => {
+                    println!("can't deserialize settings from {}: {}", path.to_str().unwrap(), e);
+                    return None;
+                },
+            }
+        },
+    }
 }
 
 fn edit_terrain() -> bool {
@@ -43,10 +73,144 @@ fn default_brush_size() -> usize {
     return 1;
 }
 
+fn default_topology() -> WorldTopology {
+    return WorldTopology::BOUNDED;
+}
+
+fn default_season_length() -> f32 {
+    return 600.0;
+}
+
+fn default_season_amplitude() -> f32 {
+    return 0.5;
+}
+
+fn default_day_length() -> f32 {
+    return 120.0;
+}
+
+fn default_day_amplitude() -> f32 {
+    return 0.25;
+}
+
+fn default_slope_speed() -> f32 {
+    return 0.1;
+}
+
+fn default_climb_energy_cost() -> f32 {
+    return 0.1;
+}
+
+fn default_plant_moisture_range() -> i32 {
+    return 3;
+}
+
+fn default_plant_drown_depth() -> i32 {
+    return 40;
+}
+
+fn default_plant_dry_chance() -> f32 {
+    return 0.5;
+}
+
+fn default_evaporation() -> f32 {
+    return 0.1;
+}
+
+fn default_rain_chance() -> f32 {
+    return 0.02;
+}
+
+fn default_rain_radius() -> f32 {
+    return 300.0;
+}
+
+fn default_rain_intensity() -> f32 {
+    return 2.0;
+}
+
+fn default_rain_duration() -> f32 {
+    return 20.0;
+}
+
+fn default_decomposition() -> f32 {
+    return 0.5;
+}
+
+fn default_nutrient_uptake() -> f32 {
+    return 2.0;
+}
+
+fn default_carcass_share() -> f32 {
+    return 0.5;
+}
+
+fn default_body_eng() -> f32 {
+    return 0.2;
+}
+
+fn default_carcass_lifetime() -> f32 {
+    return 60.0;
+}
+
+fn default_plant_mutations() -> f32 {
+    return 0.2;
+}
+
+fn default_scent_decay() -> f32 {
+    return 0.2;
+}
+
+fn default_scent_diffusion() -> f32 {
+    return 0.5;
+}
+
+fn default_scent_emission() -> f32 {
+    return 5.0;
+}
+
+fn default_agent_hearing_range() -> f32 {
+    return 300.0;
+}
+
+fn default_reproduction() -> ReproductionMode {
+    return ReproductionMode::ASEXUAL;
+}
+
+fn default_mating_distance() -> f32 {
+    return 0.6;
+}
+
+fn default_compat_disjoint() -> f32 {
+    return 1.0;
+}
+
+fn default_compat_weight() -> f32 {
+    return 0.5;
+}
+
+fn default_compat_threshold() -> f32 {
+    return 0.3;
+}
+
+fn default_grass_share() -> f32 {
+    return 0.6;
+}
+
+fn default_bush_share() -> f32 {
+    return 0.3;
+}
+
+fn default_toxic_share() -> f32 {
+    return 0.1;
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Settings {
     pub world_w: i32,
     pub world_h: i32,
+    #[serde(default = "default_topology")]
+    pub topology: WorldTopology,
     pub agent_min_num: usize,
     pub agent_init_num: usize,
     pub plant_min_num: usize,
@@ -91,7 +255,6 @@ pub struct Settings {
     pub mut_add_node: f32,
     pub mut_del_node: f32,
     pub mut_change_val: f32,
-    pub rare_specie_mod: i32,
     pub born_eng: f32,
     pub born_eng_min: f32,
     pub born_eng_cost: f32,
@@ -107,6 +270,74 @@ pub struct Settings {
     pub terrain_edit: bool,
     #[serde(default = "default_brush_size")]
     pub brush_size: usize,
+    #[serde(default = "default_season_length")]
+    pub season_length: f32,
+    #[serde(default = "default_season_amplitude")]
+    pub season_amplitude: f32,
+    #[serde(default = "default_day_length")]
+    pub day_length: f32,
+    #[serde(default = "default_day_amplitude")]
+    pub day_amplitude: f32,
+    #[serde(default = "default_slope_speed")]
+    pub slope_speed: f32,
+    #[serde(default = "default_climb_energy_cost")]
+    pub climb_energy_cost: f32,
+    #[serde(default = "default_plant_moisture_range")]
+    pub plant_moisture_range: i32,
+    #[serde(default = "default_plant_drown_depth")]
+    pub plant_drown_depth: i32,
+    #[serde(default = "default_plant_dry_chance")]
+    pub plant_dry_chance: f32,
+    #[serde(default = "default_evaporation")]
+    pub evaporation: f32,
+    #[serde(default = "default_rain_chance")]
+    pub rain_chance: f32,
+    #[serde(default = "default_rain_radius")]
+    pub rain_radius: f32,
+    #[serde(default = "default_rain_intensity")]
+    pub rain_intensity: f32,
+    #[serde(default = "default_rain_duration")]
+    pub rain_duration: f32,
+    #[serde(default = "default_decomposition")]
+    pub decomposition: f32,
+    #[serde(default = "default_nutrient_uptake")]
+    pub nutrient_uptake: f32,
+    #[serde(default = "default_carcass_share")]
+    pub carcass_share: f32,
+    #[doc = r"Energy bound in body of agent as share of its max energy. Parents pay it at birth and it returns to world when agent dies."]
+    #[serde(default = "default_body_eng")]
+    pub body_eng: f32,
+    #[serde(default = "default_carcass_lifetime")]
+    pub carcass_lifetime: f32,
+    #[serde(default = "default_plant_mutations")]
+    pub plant_mutations: f32,
+    #[serde(default = "default_grass_share")]
+    pub grass_share: f32,
+    #[serde(default = "default_bush_share")]
+    pub bush_share: f32,
+    #[serde(default = "default_toxic_share")]
+    pub toxic_share: f32,
+    #[serde(default = "default_scent_decay")]
+    pub scent_decay: f32,
+    #[serde(default = "default_scent_diffusion")]
+    pub scent_diffusion: f32,
+    #[serde(default = "default_scent_emission")]
+    pub scent_emission: f32,
+    #[serde(default)]
+    pub show_scent: bool,
+    #[serde(default = "default_agent_hearing_range")]
+    pub agent_hearing_range: f32,
+    #[serde(default = "default_reproduction")]
+    pub reproduction: ReproductionMode,
+    #[doc = r"Maximal compatibility distance of networks of two agents which can mate."]
+    #[serde(default = "default_mating_distance")]
+    pub mating_distance: f32,
+    #[serde(default = "default_compat_disjoint")]
+    pub compat_disjoint: f32,
+    #[serde(default = "default_compat_weight")]
+    pub compat_weight: f32,
+    #[serde(default = "default_compat_threshold")]
+    pub compat_threshold: f32,
 }
 
 impl Default for Settings {
@@ -115,6 +346,7 @@ impl Default for Settings {
         Self {
             world_w: 2000,
             world_h: 2000,
+            topology: WorldTopology::BOUNDED,
             
             agent_eng_bar: true,
             agent_init_num: 100,
@@ -159,7 +391,6 @@ impl Default for Settings {
             
             ranking_size: 30,
             repro_points: 30.0,
-            rare_specie_mod: 10,
             born_eng: 0.5,
             born_eng_min: 0.9,
             born_eng_cost: 0.5,
@@ -181,6 +412,39 @@ impl Default for Settings {
             select_mode: SelectMode::RANDOM,
             terrain_edit: false,
             brush_size: 1,
+            season_length: 600.0,
+            season_amplitude: 0.5,
+            day_length: 120.0,
+            day_amplitude: 0.25,
+            slope_speed: 0.1,
+            climb_energy_cost: 0.1,
+            plant_moisture_range: 3,
+            plant_drown_depth: 40,
+            plant_dry_chance: 0.5,
+            evaporation: 0.1,
+            rain_chance: 0.02,
+            rain_radius: 300.0,
+            rain_intensity: 2.0,
+            rain_duration: 20.0,
+            decomposition: 0.5,
+            nutrient_uptake: 2.0,
+            carcass_share: 0.5,
+            body_eng: 0.2,
+            carcass_lifetime: 60.0,
+            plant_mutations: 0.2,
+            grass_share: 0.6,
+            bush_share: 0.3,
+            toxic_share: 0.1,
+            scent_decay: 0.2,
+            scent_diffusion: 0.5,
+            scent_emission: 5.0,
+            show_scent: false,
+            agent_hearing_range: 300.0,
+            reproduction: ReproductionMode::ASEXUAL,
+            mating_distance: 0.6,
+            compat_disjoint: 1.0,
+            compat_weight: 0.5,
+            compat_threshold: 0.3,
        }
     }
 
@@ -1,15 +1,7 @@
 //#![allow(unused)]
 
-use macroquad::{experimental::collections::storage, prelude::Vec2};
-
-pub fn set_signals(signals: Signals) {
-    storage::store(signals);
-}
-
-pub fn get_signals() -> Signals {
-    let signals = storage::get::<Signals>();
-    return signals.clone();
-}
+use crate::math::Vec2;
+use crate::terrain::Biome;
 
 
 #[derive(Clone)]
@@ -28,11 +20,14 @@ pub struct Signals {
     pub save_agent: bool,
     pub load_agent: bool,
     pub load_agent_name: Option<String>,
+    #[doc = r"Genome code pasted by user, agent with this genome is added to simulation."]
+    pub import_genome: Option<String>,
     pub del_agent_name: Option<String>,
     pub resize_world: Option<Vec2>,
     pub export_settings: bool,
     pub import_settings: bool,
     pub update_terrain: bool,
+    pub load_scenario_name: Option<String>,
 }
 
 impl Signals {
@@ -53,11 +48,13 @@ impl Signals {
             load_agent: false,
             del_agent_name: None,
             load_agent_name: None,
+            import_genome: None,
             save_agent: false,
             resize_world: None,
             export_settings: false,
             import_settings: false,
             update_terrain: false,
+            load_scenario_name: None,
         }
     }
 
@@ -71,6 +68,9 @@ pub enum UserAction {
     WaterAdd,
     WaterRemove,
     TerrainAdd,
+    WallAdd,
+    SpringAdd,
+    BiomePaint(Biome),
 }
 
 impl UserAction {
@@ -1,66 +1,71 @@
 //#![allow(unused)]
 
 use crate::agent::*;
-use crate::camera::*;
-use crate::net_draw::draw_network;
 use crate::plant::*;
 use crate::timer::Timer;
-use crate::ui::*;
 use crate::util::*;
 use crate::collector::*;
 use crate::terrain::*;
-use crate::Statistics;
-use macroquad::camera::Camera2D;
-use macroquad::prelude::*;
+use crate::statistics::{Statistics, MutationStats};
+use crate::math::*;
 use rapier2d::prelude::RigidBodyHandle;
-use std::collections::HashMap;
-use std::f32::consts::PI;
 use serde_json;
 use std::fs;
 use std::path::Path;
 use base64::prelude::*;
-use crate::monit::PerformanceMonitor;
 //use crate::stats::Stats;
 use crate::settings::*;
 use crate::signals::*;
 use crate::sketch::*;
 use crate::phyx::physics::Physics;
 use crate::ranking::Ranking;
+use crate::rng::SimRng;
+use crate::spatial::SpatialGrid;
+use crate::events::*;
+use crate::scenario::*;
+use crate::environment::Environment;
+use crate::obstacle::*;
+use crate::carcass::*;
+use crate::species::SpeciesRegistry;
+use crate::genome::AgentGenome;
+use rayon::prelude::*;
 
 
 //#[derive(Debug)]
 pub struct Simulation {
     pub simulation_name: String,
     pub world_size: Vec2,
-    pub font: Font,
     pub physics: Physics,
-    pub camera: Camera2D,
     pub running: bool,
-    user_action: UserAction,
     last_autosave: f64,
-    pub ui: UISystem,
+    accumulator: f32,
     pub sim_state: SimState,
+    pub settings: Settings,
     pub signals: Signals,
-    select_phase: f32,
-    pub selected: Option<RigidBodyHandle>,
-    pub mouse_state: MouseState,
+    pub mutation_stats: MutationStats,
+    pub species: SpeciesRegistry,
     pub agents: AgentBox,
     pub plants: PlantBox,
+    pub obstacles: ObstacleBox,
+    pub carcasses: CarcassBox,
     pub ranking: Ranking,
     population_timer: Timer,
     pub terrain: Terrain,
+    pub grid: SpatialGrid,
+    pub events: EventQueue,
+    pub scenario: Option<Scenario>,
+    pub environment: Environment,
+    scenario_actions: Vec<TimedAction>,
     coord_timer: Timer,
-    monitor: PerformanceMonitor,
     lifetimes: Vec<f32>,
     sizes: Vec<f32>,
     eyes: Vec<f32>,
+    ears: Vec<f32>,
     speeds: Vec<f32>,
     powers: Vec<f32>,
     mutations: Vec<f32>,
     shells: Vec<f32>,
     plot_x: i32,
-    borns: [i32; 4],
-    deaths: [i32; 2],
     points: Vec<f32>,
     nodes: Vec<i32>,
     links: Vec<i32>,
@@ -68,43 +73,46 @@ pub struct Simulation {
     population_plants: Vec<i32>,
     stats_timer: Timer,
     terrain_timer: Timer,
-    statistics: Statistics,
-    n: usize,
+    pub statistics: Statistics,
+    pub rng: SimRng,
 }
 
 impl Simulation {
     
-    pub fn new(font: Font) -> Self {
-        let settings = get_settings();
+    pub fn new(settings: Settings) -> Self {
+        let mut rng = SimRng::new(generate_seed());
         Self {
-            simulation_name: format!("Simulation{}", rand::gen_range(u8::MIN, u8::MAX)),
+            simulation_name: format!("Simulation{}", rng.gen_range(u8::MIN, u8::MAX)),
             world_size: Vec2 {
                 x: f32::NAN,
                 y: f32::NAN,
             },
-            font,
             physics: Physics::new(),
-            camera: create_camera(),
             running: false,
-            user_action: UserAction::new(),
-            ui: UISystem::new(),
             sim_state: SimState::new(),
             signals: Signals::new(),
-            selected: None,
-            select_phase: 0.0,
-            mouse_state: MouseState { pos: Vec2::NAN },
+            mutation_stats: MutationStats::new(0.0, 0.0),
+            species: SpeciesRegistry::new(),
             agents: AgentBox::new(),
             plants: PlantBox::new(),
+            obstacles: ObstacleBox::new(),
+            carcasses: CarcassBox::new(),
             ranking: Ranking::new(settings.ranking_size, 20, 10),
             last_autosave: 0.0,
-            population_timer: Timer::new(1.0, true, true, false),
-            terrain: Terrain::new(0.0, 0.0, settings.grid_size as f32),
-            coord_timer: Timer::new(0.25, true, true, true),
-            terrain_timer: Timer::new(0.1, true, true, false),
-            monitor: PerformanceMonitor::new(1.0),
+            accumulator: 0.0,
+            population_timer: Timer::new(1.0, true, true),
+            terrain: Terrain::new(0.0, 0.0, settings.grid_size as f32, &mut rng),
+            grid: SpatialGrid::new(),
+            events: EventQueue::new(),
+            scenario: None,
+            environment: Environment::new(),
+            scenario_actions: vec![],
+            coord_timer: Timer::new_random(0.25, true, true, &mut rng),
+            terrain_timer: Timer::new(0.1, true, true),
             lifetimes: vec![],
             sizes: vec![],
             eyes: vec![],
+            ears: vec![],
             speeds: vec![],
             powers: vec![],
             mutations: vec![],
@@ -113,19 +121,18 @@ impl Simulation {
             nodes: vec![],
             links: vec![],
             plot_x: 0,
-            borns: [0, 0, 0, 0],
-            deaths: [0, 0],
             population_agents: vec![],
             population_plants: vec![],
-            stats_timer: Timer::new(5.0, true, true, false),
+            stats_timer: Timer::new(5.0, true, true),
             statistics: Statistics::new(settings.stats_limit),
-            n: 0,
+            rng,
+            settings,
         }
     }
 
     fn init_stats(&mut self) {
-        self.statistics = Statistics::new(get_settings().stats_limit);
-        self.borns = [0, 0, 0, 0];
+        self.statistics = Statistics::new(self.settings.stats_limit);
+        self.events.reset_totals();
         self.statistics.add_data_type("borns");
         self.statistics.add_data_type("deaths");
         self.statistics.add_data_type("kills");
@@ -133,14 +140,26 @@ impl Simulation {
         self.statistics.add_data_type("lifetimes");
         self.statistics.add_data_type("sizes");
         self.statistics.add_data_type("eyes");
+        self.statistics.add_data_type("ears");
         self.statistics.add_data_type("speeds");
         self.statistics.add_data_type("powers");
         self.statistics.add_data_type("mutations");
         self.statistics.add_data_type("shells");
         self.statistics.add_data_type("nodes");
         self.statistics.add_data_type("links");
+        self.statistics.add_data_type("species");
         self.statistics.add_data_type("agents");
         self.statistics.add_data_type("plants");
+        self.statistics.add_data_type("nutrients");
+        self.statistics.add_data_type("plant_sizes");
+        self.statistics.add_data_type("plant_growth");
+        self.statistics.add_data_type("plant_lifetimes");
+        self.statistics.add_data_type("plant_clone_distance");
+        self.statistics.add_data_type("plant_density");
+        self.statistics.add_data_type("plant_toxicity");
+        self.statistics.add_data_type("grass");
+        self.statistics.add_data_type("bushes");
+        self.statistics.add_data_type("toxic_plants");
     }
 
     fn rename_sim(&mut self, sim_name: String) {
@@ -153,56 +172,99 @@ impl Simulation {
     }
 
     fn clear_sim(&mut self, sim_name: Option<&str>) {
+        self.rng = SimRng::new(generate_seed());
         self.simulation_name = match sim_name {
             Some(name) => name.to_string(),
-            None => format!("Simulation{}", rand::gen_range(u8::MIN, u8::MAX)),
+            None => format!("Simulation{}", self.rng.gen_range(u8::MIN, u8::MAX)),
         };
-        let settings = get_settings();
-        self.world_size = Vec2::new(settings.world_w as f32, settings.world_h as f32);
+        self.world_size = Vec2::new(self.settings.world_w as f32, self.settings.world_h as f32);
         self.physics = Physics::new();
         self.agents = AgentBox::new();
         self.plants = PlantBox::new();
-        self.ranking = Ranking::new(settings.ranking_size, 20, 10);
+        self.obstacles = ObstacleBox::new();
+        self.carcasses = CarcassBox::new();
+        self.ranking = Ranking::new(self.settings.ranking_size, 20, 10);
         self.sim_state = SimState::new();
         self.signals = Signals::new();
-        self.selected = None;
-        self.select_phase = 0.0;
-        self.mouse_state = MouseState { pos: Vec2::NAN };
+        self.mutation_stats = MutationStats::new(0.0, 0.0);
+        self.species = SpeciesRegistry::new();
+        self.scenario = None;
+        self.scenario_actions.clear();
+        self.environment = Environment::new();
         self.running = true;
     }
 
+    #[doc = r"Start new simulation described by scenario."]
+    pub fn load_scenario(&mut self, scenario: Scenario) {
+        self.settings = scenario.apply_settings(&self.settings);
+        self.clear_sim(scenario.name.as_deref());
+        if let Some(seed) = scenario.seed {
+            self.rng = SimRng::new(seed);
+        }
+        self.scenario_actions = scenario.actions.to_owned();
+        self.scenario = Some(scenario);
+        self.init();
+    }
+
     pub fn init(&mut self) {
-        let settings = get_settings();
-        self.terrain = Terrain::new(settings.world_w as f32, settings.world_h as f32, settings.grid_size as f32);
+        let settings = &self.settings;
+        self.world_size = Vec2::new(settings.world_w as f32, settings.world_h as f32);
+        self.terrain = match &self.scenario {
+            Some(scenario) => scenario.build_terrain(settings, &mut self.rng),
+            None => Terrain::new(settings.world_w as f32, settings.world_h as f32, settings.grid_size as f32, &mut self.rng),
+        };
+        if let Some(scenario) = &self.scenario {
+            self.sim_state.update_terrain = scenario.terrain.update;
+            for shape in scenario.obstacles.iter() {
+                self.obstacles.add_obstacle(shape.to_owned(), &mut self.physics);
+            }
+        }
         let agents_num = settings.agent_init_num;
-        self.agents.add_many_agents(agents_num as usize, &mut self.physics);
-        self.plants.add_many_plants(settings.plant_init_num as usize, &mut self.physics);
+        self.agents.add_many_agents(agents_num as usize, &mut self.physics, self.sim_state.sim_time, settings, &mut self.species, &mut self.rng, &mut self.events);
+        self.plants.add_many_plants(settings.plant_init_num as usize, &mut self.physics, &self.terrain, settings, &mut self.rng);
+        let seed_agents = match &self.scenario {
+            Some(scenario) => scenario.seed_agents.to_owned(),
+            None => vec![],
+        };
+        for seed in seed_agents.iter() {
+            self.spawn_saved_agents(&seed.file, seed.count);
+        }
+        self.coord_timer = Timer::new_random(0.25, true, true, &mut self.rng);
         self.plot_x = (self.sim_state.sim_time/100.0) as i32;
         self.init_stats();
     }
 
     fn update_agents(&mut self) {
-        let agents = self.agents.agents.clone();
-        let cloned_agents = self.agents.agents.clone();
-        for (_, agent) in self.agents.get_iter_mut() {
-            if agent.enemy.is_some() {
-                let enemy = cloned_agents.get(&agent.enemy.unwrap());
-                match enemy {
-                    None => {},
-                    Some(enemy) => {
-                        if agent.specie == enemy.specie {
-                            agent.enemy_family = Some(true);
-                        } else {
-                            agent.enemy_family = Some(false);
-                        }
-                    }
-                }
+        self.grid.rebuild(&self.terrain, &self.agents, &self.plants, &self.settings);
+        let settings = &self.settings;
+        let mut thinking: Vec<RigidBodyHandle> = vec![];
+        for (rbh, agent) in self.agents.get_iter_mut() {
+            if agent.update_timers(settings) {
+                thinking.push(*rbh);
+            }
+        }
+        let perceptions = self.perceive(&thinking);
+        let physics = &self.physics;
+        self.agents.agents.par_iter_mut().for_each(|(rbh, agent)| {
+            if let Some(perception) = perceptions.get(rbh) {
+                agent.think(perception, physics, settings);
             }
-            if !agent.update(&agents, &mut self.physics) {
+        });
+        for (_, agent) in self.agents.get_iter_mut() {
+            if !agent.update(&mut self.physics, &self.settings) {
+                let cause = if agent.pos.x.is_nan() || agent.pos.y.is_nan() {
+                    DeathCause::Lost
+                } else if agent.killer.is_some() {
+                    DeathCause::Killed
+                } else {
+                    DeathCause::Exhausted
+                };
+                self.events.push(self.sim_state.sim_time, SimEvent::Death { agent: agent.rbh, specie: agent.specie.to_owned(), pos: agent.pos, cause });
                 let lf = agent.lifetime.round();
                 self.lifetimes.push(lf);
                 self.sizes.push(agent.size);
                 self.eyes.push(agent.eyes as f32);
+                self.ears.push(agent.ears as f32);
                 self.speeds.push(agent.speed as f32);
                 self.powers.push(agent.power as f32);
                 self.mutations.push(agent.mutations as f32);
@@ -211,98 +273,198 @@ impl Simulation {
                 let (n, l) = agent.get_nodes_links_num();
                 self.nodes.push(n);
                 self.links.push(l);
-                let mut sketch = agent.get_sketch();
-                sketch.points = (sketch.points).round();
-                self.ranking.add_agent(sketch);
+                if cause != DeathCause::Lost {
+                    let mut sketch = agent.get_sketch();
+                    sketch.points = (sketch.points).round();
+                    self.ranking.add_agent(sketch);
+                }
                 self.physics.remove_object(agent.rbh);
-                self.deaths[0] += 1;
+                if cause != DeathCause::Lost {
+                    let remains = agent.eng.max(0.0) + agent.body_eng(&self.settings);
+                    let carcass_eng = self.settings.carcass_share.clamp(0.0, 1.0) * remains;
+                    let nutrients = self.settings.decomposition * (remains - carcass_eng);
+                    self.terrain.add_nutrients(&agent.pos, nutrients, self.settings.topology == WorldTopology::TORUS);
+                    if carcass_eng > 0.0 {
+                        let carcass = Carcass::new(agent.pos, agent.rot, agent.size, carcass_eng, &mut self.physics, &self.settings);
+                        self.carcasses.add_carcass(carcass);
+                    }
+                }
             }
         }
         self.agents.agents.retain(|_, agent| agent.alife == true);
         self.update_coordinates();
     }
 
+    #[doc = r"Perception of thinking agents. World is only read here, so agents are processed in parallel."]
+    fn perceive(&self, thinking: &[RigidBodyHandle]) -> StableMap<RigidBodyHandle, Perception> {
+        let agents = &self.agents.agents;
+        let carcasses = &self.carcasses;
+        let physics = &self.physics;
+        let grid = &self.grid;
+        let env = &self.environment;
+        let settings = &self.settings;
+        return thinking.par_iter()
+            .map(|rbh| (*rbh, agents[rbh].perceive(agents, carcasses, physics, grid, env, settings)))
+            .collect();
+    }
+
     fn update_rank(&mut self) {
         self.ranking.update();
     }
 
     fn update_plants(&mut self) {
-        let settings = get_settings();
-        let mut new_plants: Vec<Plant> = vec![];
+        let settings = &self.settings;
+        let env = &self.environment;
+        let mut new_plants: Vec<Box<dyn PlantType>> = vec![];
         let num = self.plants.count() as i32;
+        let torus = settings.topology == WorldTopology::TORUS;
         for (_, plant) in self.plants.get_iter_mut() {
-            match plant.update_cloning(num, &mut self.physics) {
+            match self.terrain.cell_at(&plant.get_base().pos, torus) {
+                Some(cell) => {
+                    plant.get_base_mut().set_biome_tile(cell.get_biome());
+                    plant.get_base_mut().set_water_tile(cell.get_water());
+                },
+                None => {},
+            }
+            match plant.update_cloning(num, &mut self.physics, &self.terrain, settings, env, &mut self.rng) {
                 None => {},
                 Some(new_plant) => {
+                    self.events.push(self.sim_state.sim_time, SimEvent::PlantCloned { plant: new_plant.get_body_handle(), parent: plant.get_body_handle(), pos: new_plant.get_base().pos });
                     new_plants.push(new_plant);
                 }
             }
-            plant.update(&mut self.physics);
+            plant.update(&mut self.physics, &mut self.terrain, settings, env);
             if !plant.is_alive() {
+                let base = plant.get_base();
+                self.terrain.add_nutrients(&base.pos, settings.decomposition * base.eng.max(0.0), torus);
                 self.physics.remove_object(plant.get_body_handle());
             }
         }
         self.plants.plants.retain(|_, p| p.is_alive() == true);
-        for plant in new_plants.iter() {
-            self.plants.add_plant(plant.to_owned())
+        for plant in new_plants.into_iter() {
+            self.plants.add_plant(plant)
         }
         if self.plants.count() < settings.plant_min_num {
-            self.plants.add_many_plants(2, &mut self.physics);
+            self.plants.add_many_plants(2, &mut self.physics, &self.terrain, settings, &mut self.rng);
+        }
+    }
+
+    #[doc = r"Decay carcasses. Decayed energy goes to nutrients of terrain."]
+    fn update_carcasses(&mut self) {
+        let settings = &self.settings;
+        let torus = settings.topology == WorldTopology::TORUS;
+        for (_, carcass) in self.carcasses.get_iter_mut() {
+            let decayed = carcass.update(&mut self.physics, settings);
+            self.terrain.add_nutrients(&carcass.pos, settings.decomposition * decayed, torus);
+            if !carcass.is_alive() {
+                self.physics.remove_object(carcass.rbh);
+            }
         }
+        self.carcasses.carcasses.retain(|_, carcass| carcass.is_alive());
     }
 
     fn update_coordinates(&mut self) {
-        if self.coord_timer.update(dt()*sim_speed()) {
+        if self.coord_timer.update(dt()) {
             let mut coords: Vec<[i32; 2]> = vec![];
+            let torus = self.settings.topology == WorldTopology::TORUS;
             for (_, agent) in self.agents.get_iter_mut() {
-                let coordinates = self.terrain.pos_to_coord(&agent.pos);
+                let mut coordinates = self.terrain.pos_to_coord(&agent.pos);
+                if torus {
+                    coordinates = self.terrain.wrap_coord(coordinates);
+                }
                 coords.push(coordinates);
                 match self.terrain.get_cell(coordinates[0] as usize, coordinates[1] as usize) {
                     Some(cell) => {
                         agent.set_water_tile(cell.get_water());
+                        agent.set_altitude_tile(cell.get_altitude(), self.terrain.get_slope(coordinates, torus));
+                        agent.set_biome_tile(cell.get_biome());
+                        let mut scent = [0.0; SCENT_CHANNELS];
+                        let mut gradient = [Vec2::ZERO; SCENT_CHANNELS];
+                        for ch in 0..SCENT_CHANNELS {
+                            scent[ch] = cell.get_scent(ch);
+                            gradient[ch] = self.terrain.get_scent_gradient(coordinates, ch, torus);
+                        }
+                        agent.set_scent_tile(scent, gradient);
                     },
                     None => {},
                 }
             }
             self.terrain.set_occupied(coords);
         }
-        let (mouse_x, mouse_y) = mouse_position();
-        let cursor = self.camera.screen_to_world(vec2(mouse_x, mouse_y));
-        self.terrain.set_cursor_vec2(cursor);
     }
 
     fn update_terrain(&mut self) {
-        if self.sim_state.update_terrain {
-            if self.terrain_timer.update(dt()) {
-                self.terrain.update();
+        if self.terrain_timer.update(dt()) {
+            if self.sim_state.update_terrain {
+                self.terrain.update(self.terrain_timer.duration, &self.environment, &self.settings, &mut self.rng);
                 //dbg!(self.terrain.update());
             }
+            self.terrain.update_scent(self.terrain_timer.duration, &self.settings);
+        }
+    }
+
+    #[doc = r"Agents leave scent in cells they stand on."]
+    fn emit_scent(&mut self) {
+        let settings = &self.settings;
+        let torus = settings.topology == WorldTopology::TORUS;
+        let amount = settings.scent_emission * dt();
+        for (_, agent) in self.agents.get_iter() {
+            for ch in 0..SCENT_CHANNELS {
+                if agent.emission[ch] > 0.0 {
+                    self.terrain.add_scent(&agent.pos, ch, agent.emission[ch] * amount, torus);
+                }
+            }
+        }
+    }
+
+    #[doc = r"Advances simulation by real frame time. Time is gathered in accumulator and spent in fixed ticks,
+every elapsed tick runs sim_speed updates, so results don't depend on FPS or speed setting. Returns number of updates."]
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.events.clear();
+        if self.settings.pause {
+            self.accumulator = 0.0;
+            self.check_signals();
+            self.update_sim_state();
+            return 0;
+        }
+        let max_time = SIM_TICK * MAX_CATCH_UP_TICKS as f32;
+        self.accumulator = (self.accumulator + frame_time).min(max_time);
+        let ticks_per_frame = self.settings.sim_speed.round().max(1.0) as u32;
+        let mut updates = 0;
+        while self.accumulator >= SIM_TICK {
+            self.accumulator -= SIM_TICK;
+            for _ in 0..ticks_per_frame {
+                self.update();
+                updates += 1;
+            }
         }
+        return updates;
     }
 
+    #[doc = r"Single simulation tick of SIM_TICK duration."]
     pub fn update(&mut self) {
+        self.events.cap(MAX_QUEUED_EVENTS);
         self.check_signals();
-        self.check_settings();
         self.update_sim_state();
+        self.run_scenario_actions();
+        self.environment.update(self.sim_state.sim_time, &self.settings);
         self.update_terrain();
         self.check_agents_num();
         self.update_plants();
-        self.calc_selection_time();
+        self.update_carcasses();
         self.attacks();
         self.eat();
         self.update_agents();
+        self.emit_scent();
         self.update_rank();
-        let (i, _, _) = self.agents.populate(&mut self.physics, self.sim_state.sim_time);
-        self.borns[0] += i;
-        self.borns[1] += i;
-        self.monitor.monitor();
+        self.agents.populate(&mut self.physics, self.sim_state.sim_time, &self.settings, &mut self.mutation_stats, &mut self.species, &mut self.rng, &mut self.events);
         self.physics.step();
     }
 
     fn attacks(&mut self) {
-        let settings = get_settings();
-        let dt = dt()*sim_speed();
-        let mut hits: HashMap<RigidBodyHandle, (f32, RigidBodyHandle)> = HashMap::new();
+        let settings = &self.settings;
+        let dt = dt();
+        let mut hits: StableMap<RigidBodyHandle, (f32, RigidBodyHandle)> = StableMap::default();
         for (id, agent) in self.agents.get_iter() {
             if !agent.attacking { continue; }
             let attacks = agent.attack();
@@ -316,11 +478,11 @@ impl Simulation {
                     let power2 = target.power as f32; 
                     let attack1 = size1 + power1 - shell1;
                     let attack2 = size2 + power2 - shell2;
-                    let pow1 = attack1*0.5 + attack1*random_unit()*1.5;
-                    let pow2 = attack2*0.5 + attack2*random_unit()*1.5;
+                    let pow1 = attack1*0.5 + attack1*random_unit(&mut self.rng)*1.5;
+                    let pow2 = attack2*0.5 + attack2*random_unit(&mut self.rng)*1.5;
                     if pow1 > pow2 {
                         let mut a = agent.power as f32;
-                        a = a + a*random_unit();
+                        a = a + a*random_unit(&mut self.rng);
                         let d = target.shell as f32;
                         let mut dmg = (a-d) * dt * settings.damage;
                         if dmg > 0.0 {
@@ -344,7 +506,7 @@ impl Simulation {
                 });
             }
         }
-        let mut killers: Vec<RigidBodyHandle> = vec![];
+        let mut killers: Vec<(RigidBodyHandle, RigidBodyHandle)> = vec![];
         for (id1, (dmg, id2)) in hits.iter() {
             let agent1 = self.agents.agents.get_mut(id1).unwrap();
             let damage = *dmg;
@@ -356,45 +518,61 @@ impl Simulation {
                 let dmg = damage.abs() * settings.dmg_to_hp;
                 agent1.get_hit(dmg);
                 agent1.pain = 1.0;
-                if agent1.is_death() { killers.push(*id2); }
+                if agent1.is_death() {
+                    agent1.killer = Some(*id2);
+                    killers.push((*id2, *id1));
+                }
             }
         }
-        for killer_rbh in killers.iter() {
+        for (killer_rbh, victim_rbh) in killers.iter() {
+            let victim = &self.agents.agents[victim_rbh];
+            let (victim_specie, pos) = (victim.specie.to_owned(), victim.pos);
             let killer = self.agents.agents.get_mut(killer_rbh).unwrap();
             killer.points += 30.0;
             killer.kills += 1;
-            self.deaths[0] += 1;
-            self.deaths[1] += 1;
+            self.events.push(self.sim_state.sim_time, SimEvent::Kill { killer: *killer_rbh, killer_specie: killer.specie.to_owned(), victim: *victim_rbh, victim_specie, pos });
         }
     }
 
     fn eat(&mut self) {
-        let settings = get_settings();
-        let dt = dt()*sim_speed();
-        let mut hits: HashMap<RigidBodyHandle, f32> = HashMap::new();
+        let settings = &self.settings;
+        let dt = dt();
+        let mut hits: StableMap<RigidBodyHandle, f32> = StableMap::default();
+        let mut poison: StableMap<RigidBodyHandle, f32> = StableMap::default();
+        let mut left: StableMap<RigidBodyHandle, f32> = StableMap::default();
         for (id, agent) in self.agents.get_iter() {
             if agent.eating && !agent.attacking {
                 let attacks = agent.eat();
                 for tg in attacks.iter() {
-                    self.plants.plants.get(tg).inspect(|_target| {
-                        let power1 = agent.size/3.0 + 12.0;
-                        let mut food = settings.eat_to_eng * power1 * dt;
-                        let mut bite = -food;
-                        if hits.contains_key(id) {
-                            let old_food = *hits.get_mut(id).unwrap();
-                            food = old_food + food;
-                            hits.insert(*id, food);
-                        } else {
-                            hits.insert(*id, food);
-                        }
-                        if hits.contains_key(tg) {
-                            let old_food = *hits.get_mut(tg).unwrap();
-                            bite = bite + old_food;
-                            hits.insert(*tg, bite);
-                        } else {
-                            hits.insert(*tg, bite);
-                        }
+                    if !self.plants.plants.contains_key(tg) && !self.carcasses.contains(tg) { continue; }
+                    let toxicity = self.plants.plants.get(tg).map_or(0.0, |plant| plant.get_toxicity());
+                    let power1 = agent.size/3.0 + 12.0;
+                    let food_left = left.entry(*tg).or_insert_with(|| {
+                        let eng = self.plants.plants.get(tg).map_or_else(|| self.carcasses.get(*tg).map_or(0.0, |c| c.eng), |p| p.get_base().eng);
+                        eng.max(0.0)
                     });
+                    // food can't be bitten off more than source still has
+                    let mut food = (settings.eat_to_eng * power1 * dt).min(*food_left);
+                    *food_left -= food;
+                    let mut bite = -food;
+                    if toxicity > 0.0 {
+                        *poison.entry(*id).or_insert(0.0) += food * toxicity;
+                        food *= 1.0 - toxicity;
+                    }
+                    if hits.contains_key(id) {
+                        let old_food = *hits.get_mut(id).unwrap();
+                        food = old_food + food;
+                        hits.insert(*id, food);
+                    } else {
+                        hits.insert(*id, food);
+                    }
+                    if hits.contains_key(tg) {
+                        let old_food = *hits.get_mut(tg).unwrap();
+                        bite = bite + old_food;
+                        hits.insert(*tg, bite);
+                    } else {
+                        hits.insert(*tg, bite);
+                    }
                 }
             }
         }
@@ -418,96 +596,27 @@ impl Simulation {
                         source.drain_eng(damage.abs());
                     },
                 }
+                match self.carcasses.carcasses.get_mut(id) {
+                    None => {},
+                    Some(carcass) => {
+                        carcass.drain_eng(dmg.abs());
+                    },
+                }
             }
         }
-    }
-
-    pub fn draw(&self) {
-        //set_default_camera();
-        set_camera(&self.camera);
-        clear_background(color_u8!(0,0,0,255));
-        draw_rectangle_lines(0.0, 0.0, self.world_size.x, self.world_size.y, 3.0, WHITE);
-        self.draw_terrain();
-        self.draw_plants();
-        //self.draw_grid();
-        self.draw_agents();
-        if get_settings().show_network {
-            match self.selected {
-                Some(selected) => {
-                    match self.agents.get(selected) {
-                        Some(selected_agent) => {
-                            let phase = self.sim_state.sim_time % 1.0;
-                            draw_network(
-                                selected_agent, 
-                                phase as f32, 
-                                self.camera.target, 
-                                self.camera.zoom
-                            );
-                        },
-                        None => {},
-                    }
+        for (id, dmg) in poison.iter() {
+            match self.agents.agents.get_mut(id) {
+                Some(agent) => {
+                    agent.hp -= *dmg;
                 },
                 None => {},
             }
         }
     }
 
-    pub fn debug_physic(&mut self) {
-        if get_settings().debug {
-            self.physics.debug_draw();
-        }
-    }
-
-    pub fn draw_terrain(&self) {
-        let settings = get_settings();
-        self.terrain.draw(settings.show_cells, settings.terrain_edit);
-    }
-
-    fn draw_plants(&self) {
-        let settings = get_settings();
-        for (_, res) in self.plants.get_iter() {
-            res.draw(settings.show_plant_rad);
-        }
-    }
-
-    fn draw_agents(&self) {
-        for (id, agent) in self.agents.get_iter() {
-            let mut draw_field_of_view: bool = false;
-            if self.selected.is_some() {
-                if *id == self.selected.unwrap() {
-                    draw_field_of_view = true;
-                };
-            }
-            agent.draw(draw_field_of_view, &self.font, &self.physics);
-        }
-
-        match self.selected {
-            Some(selected) => {
-                match self.agents.get(selected) {
-                    Some(selected_agent) => {
-                        let pos = Vec2::new(selected_agent.pos.x, selected_agent.pos.y);
-                        let s = selected_agent.size;
-                        draw_circle_lines(
-                            pos.x,
-                            pos.y,
-                            2.0 * s + (self.select_phase.sin() * s * 0.5),
-                            1.0,
-                            ORANGE,
-                        );
-                    },
-                    None => {},
-                }
-            },
-            None => {},
-        }
-    }
-
     pub fn check_signals(&mut self) {
-        let sign = get_signals();
-        let sign2 = sign.clone();
-        let sign3 = sign.clone();
         if self.signals.spawn_agent {
-            self.agents.add_many_agents(1, &mut self.physics);
+            self.agents.add_many_agents(1, &mut self.physics, self.sim_state.sim_time, &self.settings, &mut self.species, &mut self.rng, &mut self.events);
             self.signals.spawn_agent = false;
         }
         if self.signals.new_sim {
@@ -521,101 +630,59 @@ impl Simulation {
         if self.signals.new_settings {
             self.signals.new_settings = false;
         }
-        if self.signals.save_selected {
-            self.signals.save_selected = false;
-            match self.selected {
-                Some(handle) => {
-                    self.save_encoded_agent(handle);
-                },
-                None => {},
-            }
-        }
         if self.signals.save_sim {
             self.signals.save_sim = false;
             self.save_sim();
         }
-        if sign.load_sim_name.is_some() {
-            match sign.load_sim_name.clone() {
-                None => {},
-                Some(name) => {
-                    let sim_name = name.to_owned();
-                    let mut signals = get_signals();
-                    signals.load_sim_name = None;
-                    set_signals(signals);
-                    self.load_sim(&sim_name, false);
-                    self.init_stats();
-                },
-            }
+        if let Some(name) = self.signals.load_sim_name.take() {
+            self.load_sim(&name, false);
+            self.init_stats();
         }
-        if sign2.del_sim_name.is_some() {
-            match sign2.del_sim_name {
-                None => {},
-                Some(name) => {
-                    let sim_name = name.to_owned();
-                    let mut signals = get_signals();
-                    self.delete_sim(&sim_name);
-                    signals.del_sim_name = None;
-                    set_signals(signals);
-                },
-            }
+        if let Some(name) = self.signals.del_sim_name.take() {
+            self.delete_sim(&name);
         }
-        if sign3.load_agent_name.is_some() {
-            match sign3.load_agent_name {
-                Some(agent_file_name) => {
-                    self.load_encoded_agent(&agent_file_name);
-                    let mut signals = get_signals();
-                    signals.load_agent_name = None;
-                    set_signals(signals);
-                },
-                None => {},
+        if let Some(agent_file_name) = self.signals.load_agent_name.take() {
+            self.spawn_saved_agents(&agent_file_name, 1);
+        }
+        if let Some(code) = self.signals.import_genome.take() {
+            match AgentGenome::decode(&code) {
+                Ok(genome) => self.spawn_agents(genome.to_sketch(), 1),
+                Err(e) => eprintln!("{}", e),
             }
         }
-        if sign3.del_agent_name.is_some() {
-            match sign3.del_agent_name {
-                Some(agent_file_name) => {
-                    self.delete_agent(&agent_file_name);
-                    let mut signals = get_signals();
-                    signals.del_agent_name = None;
-                    set_signals(signals);
-                },
-                None => {},
+        if let Some(path) = self.signals.load_scenario_name.take() {
+            match Scenario::from_file(Path::new(&path)) {
+                Ok(scenario) => self.load_scenario(scenario),
+                Err(e) => eprintln!("{}", e),
             }
         }
-        if get_signals().resize_world.is_some() {
-            let xy = get_signals().resize_world.unwrap();
-            let mut settings = get_settings();
-            settings.world_w = xy.x as i32; settings.world_h = xy.y as i32;
-            set_settings(settings.clone());
+        if let Some(agent_file_name) = self.signals.del_agent_name.take() {
+            self.delete_agent(&agent_file_name);
+        }
+        if let Some(xy) = self.signals.resize_world.take() {
+            self.settings.world_w = xy.x as i32; self.settings.world_h = xy.y as i32;
+            let settings = &self.settings;
             self.world_size = Vec2::new(settings.world_w as f32, settings.world_h as f32);
-            self.terrain = Terrain::new(settings.world_w as f32, settings.world_h as f32, settings.grid_size as f32);
-            let mut signals = get_signals();
-            signals.resize_world = None;
-            set_signals(signals);
-        }
-        if get_signals().export_settings {
-            let mut signals = get_signals();
-            signals.export_settings = false;
-            set_signals(signals);
+            self.terrain = Terrain::new(settings.world_w as f32, settings.world_h as f32, settings.grid_size as f32, &mut self.rng);
+        }
+        if self.signals.export_settings {
+            self.signals.export_settings = false;
             self.export_settings();
         }
 
-        if get_signals().import_settings {
-            let mut signals = get_signals();
-            signals.import_settings = false;
-            set_signals(signals);
+        if self.signals.import_settings {
+            self.signals.import_settings = false;
             self.import_settings();
         }
 
-        if get_signals().update_terrain {
-            let mut signals = get_signals();
-            signals.update_terrain = false;
-            set_signals(signals);
+        if self.signals.update_terrain {
+            self.signals.update_terrain = false;
             self.sim_state.update_terrain = !self.sim_state.update_terrain;
         }
     }
 
     fn export_settings(&self) {
-        let export_set = get_settings();
+        let export_set = &self.settings;
         let p = format!("saves/settings/");
         let encoded = BASE64_STANDARD.encode(serde_json::to_string(&export_set).unwrap().as_bytes());
         match fs::DirBuilder::new().recursive(true).create(p) {
@@ -641,7 +708,7 @@ impl Simulation {
 
     }
 
-    fn save_sim(&self) {
+    pub fn save_sim(&self) {
         let data = SimulationSketch::from_sim(self);
         let p = format!("saves/simulations/{}/", self.simulation_name.to_lowercase());
         match serde_json::to_string(&data) {
@@ -661,12 +728,40 @@ impl Simulation {
                         }
                     },
                     Err(e) => {
-                        error!("Error creating path: {}", e);
+                        eprintln!("Error creating path: {}", e);
+                    },
+                }
+            },
+            Err(e) => {
+                eprintln!("Failed to serialize simulation: {:?}", e);
+            },
+        }
+    }
+
+    pub fn save_statistics(&self) {
+        let p = format!("saves/simulations/{}/", self.simulation_name.to_lowercase());
+        match serde_json::to_string_pretty(&self.statistics) {
+            Ok(serial) => {
+                match fs::DirBuilder::new().recursive(true).create(p) {
+                    Ok(_) => {
+                        let f = format!("saves/simulations/{}/statistics.json", self.simulation_name.to_lowercase());
+                        match fs::write(f.clone(), &serial) {
+                            Ok(_) => {
+                                println!("Statistics saved as statistics.json.");
+                            },
+                            Err(e) => {
+                                println!("{}", e);
+                                println!("{}", f.clone());
+                            },
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("Error creating path: {}", e);
                     },
                 }
             },
             Err(e) => {
-                error!("Failed to serialize simulation: {:?}", e);
+                eprintln!("Failed to serialize statistics: {:?}", e);
             },
         }
     }
@@ -682,60 +777,124 @@ impl Simulation {
         }
     }
 
-    pub fn load_sim(&mut self, sim_name: &str, absolute_path: bool) {
+    #[doc = r"Loads saved simulation by its name or from file path. Returns false when save can't be read."]
+    pub fn load_sim(&mut self, sim_name: &str, absolute_path: bool) -> bool {
         let path: &Path;
         let f: String;
         if absolute_path {
             f = sim_name.to_string().to_lowercase();
             path = Path::new(sim_name);
         } else {
-            f = format!("saves/simulations/{}/last.sim", sim_name);
+            f = format!("saves/simulations/{}/last.sim", sim_name.to_lowercase());
             path = Path::new(&f);
         }
-        match fs::read_to_string(path) {
+        let save = match fs::read_to_string(path) {
             Err(_) => {
-                warn!("can't read from {}", path.to_str().unwrap());
+                eprintln!("can't read from {}", path.to_str().unwrap());
+                return false;
+            },
+            Ok(save) => save,
+        };
+        let decoded = match BASE64_STANDARD.decode(save.into_bytes()) {
+            Err(_) => {
+                eprintln!("error during decoding of saved sim... [{}]", &f);
+                return false;
+            },
+            Ok(decoded) => decoded,
+        };
+        let save = String::from_utf8(decoded).expect("error during converting from utf8");
+        match serde_json::from_str::<SimulationSketch>(&save) {
+            Err(_) => {
+                eprintln!("can't deserialize saved sim... [{}]", &f);
+                return false;
+            },
+            Ok(sim_sketch) => {
+                self.load_sketch(sim_sketch);
+                return true;
             },
-            Ok(save) => {
-                match BASE64_STANDARD.decode(save.clone().into_bytes()) {
-                    Err(_) => {
-                        println!("error during decoding of saved sim...");
-                    },
-                    Ok(decoded) => {
-                        let save = String::from_utf8(decoded).expect("error during converting from utf8");
-                        match serde_json::from_str::<SimulationSketch>(&save) {
-                            Err(_) => {
-                                println!("can't deserialize saved sim... [{}]", &f);
-                            },
-                            Ok(sim_sketch) => {
-                                self.reset_sim(Some(sim_sketch.simulation_name.as_str()));
-                                //self.simulation_name = sim_sketch.simulation_name.to_owned();
-                                self.sim_state.sim_time = sim_sketch.sim_time;
-                                self.plot_x = sim_sketch.sim_time as i32 / 100;
-                                self.last_autosave = sim_sketch.last_autosave;
-                                self.world_size = sim_sketch.world_size.to_vec2();
-                                let mut settings = sim_sketch.settings.to_owned();
-                                settings.world_h = sim_sketch.world_size.y as i32;
-                                settings.world_w = sim_sketch.world_size.x as i32;
-                                set_settings(settings);
-                                self.terrain = Terrain::from_serialized_terrain(&sim_sketch.terrain);
-                                for agent_sketch in sim_sketch.agents.iter() {
-                                    let agent = Agent::from_sketch(agent_sketch.clone(), &mut self.physics, self.sim_state.sim_time);
-                                    self.agents.add_agent(agent);
-                                }
-                                let settings = get_settings();
-                                self.plants.add_many_plants(settings.plant_init_num, &mut self.physics);
-                                self.ranking.general = sim_sketch.ranking.to_owned();
-                                self.ranking.school =  sim_sketch.school.to_owned();
-                            },
-                        }
-                    }
-                }
-            }
         }
-    } 
+    }
 
-    fn load_encoded_agent(&mut self, file_name: &str) {
+    #[doc = r"Rebuilds world and physics from save. Nothing is spawned and rng continues from saved state, so loaded simulation goes on like the saved one."]
+    pub fn load_sketch(&mut self, sim_sketch: SimulationSketch) {
+        self.settings = sim_sketch.settings.to_owned();
+        self.settings.world_h = sim_sketch.world_size.y as i32;
+        self.settings.world_w = sim_sketch.world_size.x as i32;
+        self.clear_sim(Some(sim_sketch.simulation_name.as_str()));
+        self.rng = sim_sketch.rng;
+        self.species = sim_sketch.species;
+        self.sim_state.sim_time = sim_sketch.sim_time;
+        self.last_autosave = sim_sketch.last_autosave;
+        self.world_size = sim_sketch.world_size.to_vec2();
+        self.terrain = Terrain::from_serialized_terrain(&sim_sketch.terrain);
+        let settings = &self.settings;
+        for shape in sim_sketch.obstacles.into_iter() {
+            self.obstacles.add_obstacle(shape, &mut self.physics);
+        }
+        for agent_sketch in sim_sketch.agents.into_iter() {
+            let agent = Agent::restore(agent_sketch, &mut self.physics, settings, &mut self.rng);
+            self.agents.agents.insert(agent.rbh, agent);
+        }
+        for plant_sketch in sim_sketch.plants.iter() {
+            let plant = plant_from_sketch(plant_sketch, &mut self.physics, settings);
+            self.plants.add_plant(plant);
+        }
+        for carcass_sketch in sim_sketch.carcasses.iter() {
+            self.carcasses.add_carcass(Carcass::from_sketch(carcass_sketch, &mut self.physics));
+        }
+        self.physics.update_queries();
+        if let Some(scenario) = &sim_sketch.scenario {
+            self.sim_state.update_terrain = scenario.terrain.update;
+        }
+        self.scenario = sim_sketch.scenario;
+        self.scenario_actions = sim_sketch.scenario_actions;
+        self.ranking.general = sim_sketch.ranking;
+        self.ranking.school = sim_sketch.school;
+        match sim_sketch.timers {
+            Some(timers) => {
+                self.population_timer = timers.population;
+                self.coord_timer = timers.coords;
+                self.stats_timer = timers.stats;
+                self.terrain_timer = timers.terrain;
+            },
+            None => {
+                self.population_timer = Timer::new(1.0, true, true);
+                self.coord_timer = Timer::new(0.25, true, true);
+                self.stats_timer = Timer::new(5.0, true, true);
+                self.terrain_timer = Timer::new(0.1, true, true);
+            },
+        }
+        self.environment.update(self.sim_state.sim_time, &self.settings);
+        self.accumulator = 0.0;
+        self.plot_x = (self.sim_state.sim_time/100.0) as i32;
+        self.init_stats();
+    }
+
+    #[doc = r"Timed actions of scenario which haven't happened yet."]
+    pub fn get_scenario_actions(&self) -> &[TimedAction] {
+        return &self.scenario_actions;
+    }
+
+    #[doc = r"Clocks saved together with simulation."]
+    pub fn get_timers(&self) -> SimTimers {
+        return SimTimers {
+            population: self.population_timer,
+            coords: self.coord_timer,
+            stats: self.stats_timer,
+            terrain: self.terrain_timer,
+        };
+    }
+
+    pub fn add_obstacle(&mut self, shape: ObstacleShape) {
+        self.obstacles.add_obstacle(shape, &mut self.physics);
+    }
+
+    #[doc = r"Remove obstacle closest to position if it is not further than range."]
+    pub fn remove_obstacle_at(&mut self, pos: Vec2, range: f32) {
+        self.obstacles.remove_obstacle_at(pos, range, &mut self.physics);
+    }
+
+    fn load_encoded_agent(&self, file_name: &str) -> Option<AgentSketch> {
         let f = format!("saves/agents/{}", file_name);
         let path = Path::new(&f);
         match fs::read_to_string(path) {
@@ -747,10 +906,7 @@ impl Simulation {
                         let save = String::from_utf8(decoded).expect("error during decode Vec<u8> to String");
                         match serde_json::from_str::<AgentSketch>(&save) {
                             Ok(agent_save) => {
-                                let mut agent = Agent::from_sketch(agent_save.clone(), &mut self.physics, self.sim_state.sim_time);
-                                let settings = get_settings();
-                                agent.pos = random_position(settings.world_w as f32, settings.world_h as f32);
-                                self.agents.add_agent(agent);
+                                return Some(agent_save);
                             },
                             Err(_) => {
 
@@ -760,6 +916,51 @@ impl Simulation {
                 }
             }
         }
+        return None;
+    }
+
+    #[doc = r"Add n agents built from saved .agent file at random positions."]
+    fn spawn_saved_agents(&mut self, file_name: &str, n: usize) {
+        if let Some(agent_save) = self.load_encoded_agent(file_name) {
+            self.spawn_agents(agent_save, n);
+        }
+    }
+
+    #[doc = r"Add n agents built from sketch at random positions."]
+    fn spawn_agents(&mut self, agent_save: AgentSketch, n: usize) {
+        for _ in 0..n {
+            let settings = &self.settings;
+            let mut agent = Agent::from_sketch(agent_save.clone(), &mut self.physics, self.sim_state.sim_time, settings, &mut self.mutation_stats, &mut self.rng);
+            self.species.assign(&mut agent, self.sim_state.sim_time, settings, &mut self.rng);
+            agent.pos = random_position(settings.world_w as f32, settings.world_h as f32, &mut self.rng);
+            let rbh = agent.rbh;
+            self.agents.add_agent(agent, settings, &mut self.rng);
+            let agent = &self.agents.agents[&rbh];
+            self.events.push(self.sim_state.sim_time, SimEvent::Birth { agent: rbh, parent: None, partner: None, specie: agent.specie.to_owned(), pos: agent.pos, cause: BirthCause::Placed });
+        }
+    }
+
+    fn run_scenario_actions(&mut self) {
+        while !self.scenario_actions.is_empty() && self.scenario_actions[0].time <= self.sim_state.sim_time {
+            let timed = self.scenario_actions.remove(0);
+            match timed.action {
+                ScenarioAction::Settings { values } => {
+                    self.settings = override_settings(&self.settings, &values);
+                },
+                ScenarioAction::AddWater { x, y, radius, amount } => {
+                    self.terrain.add_water_in_region(vec2(x, y), radius, amount);
+                },
+                ScenarioAction::AddTerrain { x, y, radius, amount } => {
+                    self.terrain.add_terrain_in_region(vec2(x, y), radius, amount);
+                },
+                ScenarioAction::Rain { x, y, radius, duration } => {
+                    self.terrain.add_cloud(vec2(x, y), radius, duration, &mut self.rng);
+                },
+                ScenarioAction::SpawnAgents { file, count } => {
+                    self.spawn_saved_agents(&file, count);
+                },
+            }
+        }
     }
 
     fn delete_agent(&mut self, file_name: &str) {
@@ -775,7 +976,7 @@ impl Simulation {
 
 
 
-    fn save_encoded_agent(&self, handle: RigidBodyHandle) {
+    pub fn save_encoded_agent(&self, handle: RigidBodyHandle) {
         match self.agents.get(handle) {
             Some(agent) => {
                 let agent_sketch = agent.get_sketch();
@@ -798,141 +999,20 @@ impl Simulation {
                 }
             },
             None => {
-                warn!("WARN: agent not selected");
-            },
-        }
-    }
-
-    pub fn input(&mut self) {
-        self.mouse_input();
-        self.keyboard_input();
-        control_camera(&mut self.camera);
-    }
-
-    fn keyboard_input(&mut self) {
-        if is_key_pressed(KeyCode::Tab) {
-            match get_settings().select_mode {
-                SelectMode::RANDOM => {
-                    self.random_selection();
-                },
-                SelectMode::POINTS => {
-                    self.points_selection();
-                },
-                SelectMode::LIFETIME => {
-                    self.lifetime_selection();
-                },
-                _ => {
-                    self.random_selection();
-                }
-            }
-        }
-        if is_key_pressed(KeyCode::Kp6) {
-            let mut n = self.n + 1;
-            n = clamp(n, 0, self.agents.count()-1);
-            self.select_n(n);
-        }
-        if is_key_pressed(KeyCode::Kp4) {
-            let mut n = self.n - 1;
-            n = clamp(n, 0, self.agents.count()-1);
-            self.select_n(n);
-        }
-        if is_key_pressed(KeyCode::Kp5) {
-            self.select_n(0);
-        }
-    }
-
-    fn select_n(&mut self, n: usize) {
-        self.n = n;
-        match self.agents.get_iter().nth(self.n) {
-            Some((id, _)) => {
-                self.selected = Some(*id);
-            },
-            None => {
+                eprintln!("WARN: agent not selected");
             },
         }
     }
 
-    fn mouse_input(&mut self) {
-        match self.user_action {
-            UserAction::Idle => {
-                if is_mouse_button_released(MouseButton::Left) {
-                    if !self.ui.pointer_over {
-                        self.selected = None;
-                        let (mouse_posx, mouse_posy) = mouse_position();
-                        let mouse_pos = Vec2::new(mouse_posx, mouse_posy);
-                        let rel_coords = self.camera.screen_to_world(mouse_pos);
-                        for (id, agent) in self.agents.get_iter() {
-                            if contact_mouse(rel_coords, agent.pos, agent.size) {
-                                self.selected = Some(*id);
-                                break;
-                            }
-                        }
-                        if self.selected.is_some() { return; }
-                        for (id, plant) in self.plants.get_iter() {
-                            if contact_mouse(rel_coords, plant.pos, plant.size) {
-                                self.selected = Some(*id);
-                                break;
-                            }
-                        }
-                    }
-                }
-            },
-            UserAction::WaterAdd => {
-                if self.ui.pointer_over {
-
-                } else if is_mouse_button_released(MouseButton::Left) {
-                    self.terrain.add_water_at_cursor(50);
-                } else if is_mouse_button_released(MouseButton::Right) {
-                    self.terrain.add_water_at_cursor(-50);
-                }
-            },
-            UserAction::TerrainAdd => {
-                if self.ui.pointer_over {
-                } else if is_mouse_button_released(MouseButton::Left) {
-                    self.terrain.add_terrain_at_cursor(10);
-                } else if is_mouse_button_released(MouseButton::Right) {
-                    self.terrain.add_terrain_at_cursor(-10);
-                }
-            },
-            _ => {},
-        }
-    }
-
-    fn check_settings(&mut self) {
-        let settings = get_settings();
-        if settings.follow_mode && self.selected.is_some() {
-            match self.selected {
-                None => {},
-                Some(sel) => {
-                    match self.agents.get(sel) {
-                        None => {},
-                        Some(agent) => {
-                            let pos = agent.pos;
-                            self.camera.target = pos;
-                        },
-                    }
-                }
-            }
-        } else if settings.follow_mode && self.selected.is_none() {
-            self.random_selection();
-        } else if self.selected.is_some() {
-            if !self.agents.agents.contains_key(&self.selected.unwrap()) {
-                self.points_selection();
-            }
-        }
-    }
-
     fn update_sim_state(&mut self) {
         if self.stats_timer.update(dt()) {
             self.population_agents.push(self.agents.count() as i32);
             self.population_plants.push(self.plants.count() as i32);
+            self.sim_state.total_nutrients = self.terrain.total_nutrients();
         }
-        self.sim_state.fps = self.monitor.fps();
-        self.sim_state.dt = self.monitor.dt();
-        self.sim_state.sim_time += (dt()*sim_speed()) as f64;
-        let (mouse_x, mouse_y) = mouse_position();
-        self.mouse_state.pos = Vec2::new(mouse_x, mouse_y);
+        self.sim_state.sim_time += (dt()) as f64;
         self.sim_state.agents_num = self.agents.agents.len() as i32;
+        self.sim_state.species_num = self.species.count() as i32;
         self.sim_state.sources_num = self.plants.plants.len() as i32;
         self.sim_state.physics_num = self.physics.get_bodies_num() as i32;
         (self.sim_state.rigid_num, self.sim_state.colliders_num) = self.physics.get_bodies_and_colliders_num();
@@ -950,6 +1030,7 @@ impl Simulation {
             let powers: f32 = self.powers.iter().sum::<f32>()/l2;
             let speeds: f32 = self.speeds.iter().sum::<f32>()/l2;
             let eyes: f32 = self.eyes.iter().sum::<f32>()/l2;
+            let ears: f32 = self.ears.iter().sum::<f32>()/l2;
             let shells: f32 = self.shells.iter().sum::<f32>()/l2;
             let mutations: f32 = self.mutations.iter().sum::<f32>()/l2;
             let points: f32 = self.points.iter().sum::<f32>()/self.points.len() as f32;
@@ -963,6 +1044,7 @@ impl Simulation {
             self.powers.clear();
             self.speeds.clear();
             self.eyes.clear();
+            self.ears.clear();
             self.mutations.clear();
             self.lifetimes.clear();
             self.sizes.clear();
@@ -971,134 +1053,449 @@ impl Simulation {
             self.links.clear();
             self.sim_state.lifetime.push([(next-1) as f64, avg as f64]);
             self.statistics.add_data("lifetimes", (next-1, avg as f64));
-            self.statistics.add_data("borns", (next-1, self.borns[1] as f64));
-            self.statistics.add_data("deaths", (next-1, self.deaths[0] as f64));
-            self.statistics.add_data("kills", (next-1, self.deaths[1] as f64));
+            let totals = *self.events.totals();
+            self.statistics.add_data("borns", (next-1, totals.births as f64));
+            self.statistics.add_data("deaths", (next-1, totals.deaths as f64));
+            self.statistics.add_data("kills", (next-1, totals.kills as f64));
             self.statistics.add_data("points", (next-1, points as f64));
             self.statistics.add_data("sizes", (next-1, sizes as f64));
             self.statistics.add_data("eyes", (next-1, eyes as f64));
+            self.statistics.add_data("ears", (next-1, ears as f64));
             self.statistics.add_data("speeds", (next-1, speeds as f64));
             self.statistics.add_data("powers", (next-1, powers as f64));
             self.statistics.add_data("mutations", (next-1, mutations as f64));
             self.statistics.add_data("shells", (next-1, shells as f64));
             self.statistics.add_data("nodes", (next-1, nodes as f64));
             self.statistics.add_data("links", (next-1, links as f64));
+            self.prune_species();
+            self.statistics.add_data("species", (next-1, self.species.count() as f64));
             self.statistics.add_data("agents", (next-1, pop_agents as f64));
             self.statistics.add_data("plants", (next-1, pop_plants as f64));
-            self.borns = [0, 0, 0, 0];
-            self.deaths = [0, 0];
+            self.statistics.add_data("nutrients", (next-1, self.sim_state.total_nutrients as f64));
+            let plants_num = self.plants.count().max(1) as f64;
+            let genomes: Vec<PlantGenome> = self.plants.get_iter().map(|(_, plant)| plant.get_base().genome).collect();
+            self.statistics.add_data("plant_sizes", (next-1, genomes.iter().map(|g| g.max_size as f64).sum::<f64>()/plants_num));
+            self.statistics.add_data("plant_growth", (next-1, genomes.iter().map(|g| g.growth as f64).sum::<f64>()/plants_num));
+            self.statistics.add_data("plant_lifetimes", (next-1, genomes.iter().map(|g| g.lifetime as f64).sum::<f64>()/plants_num));
+            self.statistics.add_data("plant_clone_distance", (next-1, genomes.iter().map(|g| g.clone_distance as f64).sum::<f64>()/plants_num));
+            self.statistics.add_data("plant_density", (next-1, genomes.iter().map(|g| g.energy_density as f64).sum::<f64>()/plants_num));
+            self.statistics.add_data("plant_toxicity", (next-1, genomes.iter().map(|g| g.toxicity as f64).sum::<f64>()/plants_num));
+            let kind_count = |kind: PlantKind| self.plants.get_iter().filter(|(_, plant)| plant.get_kind() == kind).count() as f64;
+            self.statistics.add_data("grass", (next-1, kind_count(PlantKind::Grass)));
+            self.statistics.add_data("bushes", (next-1, kind_count(PlantKind::Bush)));
+            self.statistics.add_data("toxic_plants", (next-1, kind_count(PlantKind::Toxic)));
+            self.events.reset_totals();
         }
         if (self.sim_state.sim_time-self.last_autosave).round() >= 1000.0 {
             self.last_autosave = self.sim_state.sim_time.round();
             self.save_sim();
+            self.events.push(self.sim_state.sim_time, SimEvent::Autosave { sim_name: self.simulation_name.to_owned(), sim_time: self.sim_state.sim_time });
         } 
     }
 
     fn check_agents_num(&mut self) {
-        let settings = get_settings();
-        let dt = dt()*sim_speed();
-        if self.sim_state.agents_num < (settings.agent_min_num as i32) {
+        let dt = dt();
+        let new_one_probability = self.settings.new_one_probability;
+        if self.sim_state.agents_num < (self.settings.agent_min_num as i32) {
             self.agent_from_zero();
             self.agent_from_sketch();
         }
         if self.population_timer.update(dt) {
-            if random_unit_unsigned() < settings.new_one_probability  {
+            if random_unit_unsigned(&mut self.rng) < new_one_probability  {
                 self.agent_from_zero();
             }
-            if random_unit_unsigned() < settings.new_one_probability  {
+            if random_unit_unsigned(&mut self.rng) < new_one_probability  {
                 self.agent_from_sketch();
             }
         }
     }
 
+    #[doc = r"Forgets species without living members or agents in ranking."]
+    fn prune_species(&mut self) {
+        let mut names: StableSet<String> = self.agents.get_iter().map(|(_, agent)| agent.specie.to_owned()).collect();
+        for sketch in self.ranking.get_general_rank().iter().chain(self.ranking.get_school_rank().iter()) {
+            names.insert(sketch.specie.to_owned());
+        }
+        self.species.retain(&names);
+    }
+
     fn agent_from_zero(&mut self) {
-        _ = self.agents.add_many_agents(1, &mut self.physics);
-        self.borns[0] += 1;
-        self.borns[3] += 1;
+        let mut agent = Agent::new(&mut self.physics, &self.settings, &mut self.rng);
+        self.species.assign(&mut agent, self.sim_state.sim_time, &self.settings, &mut self.rng);
+        let rbh = agent.rbh;
+        _ = self.agents.add_agent(agent, &self.settings, &mut self.rng);
+        let agent = &self.agents.agents[&rbh];
+        self.events.push(self.sim_state.sim_time, SimEvent::Birth { agent: rbh, parent: None, partner: None, specie: agent.specie.to_owned(), pos: agent.pos, cause: BirthCause::Spontaneous });
     }
 
     fn agent_from_sketch(&mut self) {
-        match self.ranking.get_random_agent() {
+        match self.ranking.get_random_agent(&mut self.rng) {
             Some(sketch) => {
                 let s = sketch.to_owned();
-                let agent = Agent::from_sketch(s, &mut self.physics, self.sim_state.sim_time);
-                _ = self.agents.add_agent(agent);
-                self.borns[0] += 1;
-                self.borns[2] += 1;
+                let ancestor_specie = s.specie.to_owned();
+                let mut agent = Agent::from_sketch(s, &mut self.physics, self.sim_state.sim_time, &self.settings, &mut self.mutation_stats, &mut self.rng);
+                self.species.assign(&mut agent, self.sim_state.sim_time, &self.settings, &mut self.rng);
+                let rbh = agent.rbh;
+                _ = self.agents.add_agent(agent, &self.settings, &mut self.rng);
+                let agent = &self.agents.agents[&rbh];
+                let time = self.sim_state.sim_time;
+                self.events.push(time, SimEvent::Birth { agent: rbh, parent: None, partner: None, specie: agent.specie.to_owned(), pos: agent.pos, cause: BirthCause::FromRanking });
+                if agent.specie != ancestor_specie {
+                    self.events.push(time, SimEvent::Speciation { agent: rbh, specie: agent.specie.to_owned(), ancestor_specie, pos: agent.pos });
+                }
             },
             None => {},
         }
     }
 
-    fn calc_selection_time(&mut self) {
-        self.select_phase += self.sim_state.dt * 4.0;
-        self.select_phase = self.select_phase % (2.0 * PI as f32);
+    pub fn is_running(&self) -> bool {
+        return self.running;
     }
 
-    pub fn process_ui(&mut self) {
-        let selected_agent = match self.selected {
-            Some(selected) => {
-                self.agents.get(selected)
-            },
-            None => None,
-        };
-        let selected_plant = match self.selected {
-            Some(selected) => {
-                self.plants.get(selected)
-            },
-            None => None,
-        };
-        self.ui.ui_process(
-            self.simulation_name.clone(),
-            &self.sim_state, 
-            &mut self.signals, 
-            &self.camera, 
-            selected_agent, 
-            selected_plant, 
-            &self.ranking, 
-            &self.statistics,
-            &mut self.user_action
-        );
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    fn small_sim(seed: u64) -> Simulation {
+        let mut settings = Settings::default();
+        settings.world_w = 600;
+        settings.world_h = 600;
+        settings.agent_init_num = 12;
+        settings.agent_min_num = 4;
+        settings.plant_init_num = 60;
+        settings.plant_min_num = 10;
+        let mut sim = Simulation::new(settings);
+        sim.rng = SimRng::new(seed);
+        sim.init();
+        sim.settings.pause = false;
+        return sim;
     }
 
-    pub fn draw_ui(&self) {
-        self.ui.ui_draw();
+    fn run(sim: &mut Simulation, ticks: usize) {
+        for _ in 0..ticks {
+            sim.update();
+            sim.events.clear();
+        }
     }
 
-    pub fn is_running(&self) -> bool {
-        return self.running;
+    fn reload(sim: &Simulation) -> Simulation {
+        let serial = serde_json::to_string(&SimulationSketch::from_sim(sim)).unwrap();
+        let sketch: SimulationSketch = serde_json::from_str(&serial).unwrap();
+        let mut loaded = Simulation::new(Settings::default());
+        loaded.load_sketch(sketch);
+        return loaded;
+    }
+
+    fn agent_states(sim: &Simulation) -> Vec<[f32; 3]> {
+        let mut states: Vec<[f32; 3]> = sim.agents.get_iter()
+            .map(|(_, agent)| [agent.pos.x, agent.pos.y, agent.eng])
+            .collect();
+        states.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        return states;
     }
 
-    fn random_selection(&mut self) {
-        let n = self.agents.count();
-        let r = rand::gen_range(0, n);
-        let keys: Vec<&RigidBodyHandle> = self.agents.agents.keys().collect();
-        self.selected = Some(*keys[r]);
+    fn total_energy(sim: &Simulation) -> f32 {
+        let agents: f32 = sim.agents.get_iter().map(|(_, agent)| agent.eng.max(0.0) + agent.body_eng(&sim.settings)).sum();
+        let plants: f32 = sim.plants.get_iter().map(|(_, plant)| plant.get_base().eng.max(0.0)).sum();
+        let carcasses: f32 = sim.carcasses.get_iter().map(|(_, carcass)| carcass.eng.max(0.0)).sum();
+        return agents + plants + carcasses + sim.terrain.total_nutrients();
+    }
+
+    #[test]
+    fn energy_does_not_grow_without_sources() {
+        let mut sim = small_sim(9);
+        // sunlight, spawning, reproduction, plant cloning and predation bring energy from outside
+        sim.settings.growth = 0.0;
+        sim.settings.agent_min_num = 0;
+        sim.settings.plant_min_num = 0;
+        sim.settings.new_one_probability = 0.0;
+        sim.settings.repro_time = f32::INFINITY;
+        sim.settings.plant_balance = 0;
+        sim.settings.atk_to_eng = 0.0;
+        sim.settings.decomposition = 1.0;
+        // weak and hungry agents starve in first seconds
+        for (_, agent) in sim.agents.get_iter_mut() {
+            agent.hp = 1.0;
+            agent.eng = agent.eng.min(agent.max_eng * 0.1);
+        }
+        let mut last = total_energy(&sim);
+        let mut deaths = 0;
+        for _ in 0..600 {
+            sim.update();
+            deaths += sim.events.drain().filter(|(_, event)| matches!(event, SimEvent::Death { cause: DeathCause::Exhausted, .. })).count();
+            let total = total_energy(&sim);
+            assert!(total <= last + last*1e-5, "energy grew from {} to {}", last, total);
+            last = total;
+        }
+        assert!(deaths > 0);
     }
 
-    fn points_selection(&mut self) {
-        let mut selected: Option<RigidBodyHandle> = None;
-        let mut points = 0.0;
-        for (handle, agent) in self.agents.get_iter() {
-            if agent.points > points {
-                selected = Some(*handle);
-                points = agent.points;
+    #[test]
+    fn parallel_perception_matches_serial() {
+        let mut sim = small_sim(5);
+        run(&mut sim, 30);
+        sim.grid.rebuild(&sim.terrain, &sim.agents, &sim.plants, &sim.settings);
+        let thinking: Vec<RigidBodyHandle> = sim.agents.get_iter().map(|(rbh, _)| *rbh).collect();
+        let parallel = sim.perceive(&thinking);
+        let agents = &sim.agents.agents;
+        for rbh in thinking.iter() {
+            let serial = agents[rbh].perceive(agents, &sim.carcasses, &sim.physics, &sim.grid, &sim.environment, &sim.settings);
+            assert_eq!(format!("{:?}", parallel[rbh]), format!("{:?}", serial));
+        }
+        let mut serial_agents = sim.agents.agents.clone();
+        for (rbh, agent) in serial_agents.iter_mut() {
+            agent.think(&parallel[rbh], &sim.physics, &sim.settings);
+        }
+        sim.agents.agents.par_iter_mut().for_each(|(rbh, agent)| {
+            agent.think(&parallel[rbh], &sim.physics, &sim.settings);
+        });
+        for (rbh, agent) in sim.agents.get_iter() {
+            assert_eq!(format!("{:?}", agent), format!("{:?}", serial_agents[rbh]));
+        }
+    }
+
+    #[test]
+    fn load_restores_world_without_spawning_or_rng() {
+        let mut sim = small_sim(7);
+        run(&mut sim, 30);
+        let loaded = reload(&sim);
+        assert_eq!(loaded.agents.count(), sim.agents.count());
+        assert_eq!(loaded.plants.count(), sim.plants.count());
+        assert_eq!(loaded.sim_state.sim_time, sim.sim_state.sim_time);
+        assert_eq!(agent_states(&loaded), agent_states(&sim));
+        let mut rng = sim.rng.clone();
+        let mut loaded_rng = loaded.rng.clone();
+        assert_eq!(loaded_rng.next_u64(), rng.next_u64());
+    }
+
+    #[test]
+    fn imported_genome_adds_agent() {
+        let mut sim = small_sim(17);
+        let (_, agent) = sim.agents.get_iter().next().unwrap();
+        let code = AgentGenome::from_sketch(&agent.get_sketch()).encode();
+        let n = sim.agents.count();
+        sim.signals.import_genome = Some("broken code".to_string());
+        sim.check_signals();
+        assert_eq!(sim.agents.count(), n);
+        sim.signals.import_genome = Some(code);
+        sim.check_signals();
+        assert_eq!(sim.agents.count(), n + 1);
+    }
+
+    #[test]
+    fn hearing_wraps_across_edges_in_torus() {
+        for topology in [WorldTopology::BOUNDED, WorldTopology::TORUS] {
+            let mut sim = small_sim(4);
+            sim.settings.topology = topology;
+            let handles: Vec<RigidBodyHandle> = sim.agents.get_iter().map(|(rbh, _)| *rbh).collect();
+            let (listener, speaker) = (handles[0], handles[1]);
+            for (i, rbh) in handles.iter().enumerate() {
+                let pos = match i {
+                    0 => Vec2::new(5.0, 5.0),
+                    1 => Vec2::new(595.0, 595.0),
+                    _ => Vec2::new(300.0, 300.0),
+                };
+                sim.physics.get_object_mut(*rbh).unwrap().set_translation(rapier2d::na::Vector2::new(pos.x, pos.y), true);
+                let agent = sim.agents.agents.get_mut(rbh).unwrap();
+                agent.pos = pos;
+                agent.sound = 0.0;
+            }
+            sim.agents.agents.get_mut(&speaker).unwrap().sound = 1.0;
+            sim.agents.agents.get_mut(&listener).unwrap().hearing_range = 50.0;
+            // step moves colliders to new body positions and updates queries
+            sim.physics.step();
+            let agents = &sim.agents.agents;
+            let perception = agents[&listener].perceive(agents, &sim.carcasses, &sim.physics, &sim.grid, &sim.environment, &sim.settings);
+            match topology {
+                WorldTopology::BOUNDED => assert_eq!(perception.hearing, 0.0),
+                WorldTopology::TORUS => assert!(perception.hearing > 0.5),
             }
         }
-        self.selected = selected;
     }
-  
-    fn lifetime_selection(&mut self) {
-        let mut selected: Option<RigidBodyHandle> = None;
-        let mut lifetime = 0.0;
-        for (handle, agent) in self.agents.get_iter() {
-            if agent.lifetime > lifetime {
-                selected = Some(*handle);
-                lifetime = agent.points;
+
+    #[test]
+    fn walls_hide_agents_from_vision() {
+        for wall in [false, true] {
+            let mut sim = small_sim(8);
+            let handles: Vec<RigidBodyHandle> = sim.agents.get_iter().map(|(rbh, _)| *rbh).collect();
+            let (observer, target) = (handles[0], handles[1]);
+            let place = |i: usize| match i {
+                0 => Vec2::new(200.0, 300.0),
+                1 => Vec2::new(280.0, 300.0),
+                _ => Vec2::new(100.0 + 40.0 * i as f32, 550.0),
+            };
+            for (i, rbh) in handles.iter().enumerate() {
+                let pos = place(i);
+                sim.physics.get_object_mut(*rbh).unwrap().set_translation(rapier2d::na::Vector2::new(pos.x, pos.y), true);
+            }
+            if wall {
+                sim.obstacles.add_obstacle(ObstacleShape::Segment { a: [240.0, 200.0], b: [240.0, 400.0] }, &mut sim.physics);
+            }
+            sim.physics.step();
+            for (i, rbh) in handles.iter().enumerate() {
+                sim.agents.agents.get_mut(rbh).unwrap().pos = place(i);
+            }
+            let agent = sim.agents.agents.get_mut(&observer).unwrap();
+            agent.rot = 0.0;
+            agent.vision_range = 150.0;
+            agent.vision_angle = std::f32::consts::PI;
+            sim.grid.rebuild(&sim.terrain, &sim.agents, &sim.plants, &sim.settings);
+            let agents = &sim.agents.agents;
+            let perception = agents[&observer].perceive(agents, &sim.carcasses, &sim.physics, &sim.grid, &sim.environment, &sim.settings);
+            match wall {
+                false => assert_eq!(perception.enemy, Some(target)),
+                true => assert_eq!(perception.enemy, None),
             }
         }
-        self.selected = selected;
     }
 
-}
+    #[test]
+    fn water_flows_by_default_and_across_torus_edges() {
+        for topology in [WorldTopology::BOUNDED, WorldTopology::TORUS] {
+            let mut sim = small_sim(6);
+            sim.settings.topology = topology;
+            sim.settings.rain_chance = 0.0;
+            sim.settings.evaporation = 0.0;
+            let (w, h) = (sim.terrain.width, sim.terrain.height);
+            for c in 0..w {
+                for r in 0..h {
+                    sim.terrain.get_mut_cell(c, r).unwrap().set_cell(0, 0);
+                }
+            }
+            sim.terrain.get_mut_cell(0, h/2).unwrap().set_water(100);
+            run(&mut sim, 60);
+            let across = sim.terrain.get_cell(w-1, h/2).unwrap().get_water();
+            match topology {
+                WorldTopology::BOUNDED => assert_eq!(across, 0),
+                WorldTopology::TORUS => assert!(across > 0),
+            }
+        }
+    }
+
+    #[test]
+    fn initial_agents_emit_births() {
+        let sim = small_sim(2);
+        let births: Vec<RigidBodyHandle> = sim.events.get_iter()
+            .filter_map(|(_, event)| match event {
+                SimEvent::Birth { agent, cause: BirthCause::Spontaneous, .. } => Some(*agent),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(births.len(), sim.agents.count());
+        assert!(births.iter().all(|rbh| sim.agents.agents.contains_key(rbh)));
+    }
+
+    #[test]
+    fn same_seed_gives_identical_runs() {
+        let state = |sim: &Simulation| {
+            let mut agents: Vec<(RigidBodyHandle, [u32; 3], String)> = sim.agents.get_iter()
+                .map(|(rbh, agent)| (*rbh, [agent.pos.x.to_bits(), agent.pos.y.to_bits(), agent.eng.to_bits()], format!("{:?}", agent.network)))
+                .collect();
+            agents.sort_by_key(|(rbh, _, _)| rbh.into_raw_parts());
+            let mut plants: Vec<(RigidBodyHandle, [u32; 3])> = sim.plants.get_iter()
+                .map(|(rbh, plant)| (*rbh, [plant.get_base().pos.x.to_bits(), plant.get_base().pos.y.to_bits(), plant.get_base().eng.to_bits()]))
+                .collect();
+            plants.sort_by_key(|(rbh, _)| rbh.into_raw_parts());
+            return (agents, plants);
+        };
+        let mut first = small_sim(21);
+        let mut second = small_sim(21);
+        run(&mut first, 500);
+        run(&mut second, 500);
+        assert!(first.agents.count() > 0);
+        assert_eq!(state(&first), state(&second));
+        assert_ne!(state(&small_sim(21)), state(&small_sim(22)));
+    }
+
+    #[test]
+    fn plants_store_no_more_than_max_energy() {
+        let mut sim = small_sim(12);
+        sim.settings.nutrient_uptake = 1000.0;
+        for _ in 0..200 {
+            let nutrients = sim.terrain.total_nutrients();
+            let room: f32 = sim.plants.get_iter().map(|(_, plant)| (plant.get_base().max_eng - plant.get_base().eng).max(0.0)).sum();
+            sim.update();
+            sim.events.clear();
+            assert!(nutrients - sim.terrain.total_nutrients() <= room + 1e-4 * nutrients);
+            for (_, plant) in sim.plants.get_iter() {
+                assert!(plant.get_base().eng <= plant.get_base().max_eng);
+            }
+        }
+    }
 
+    #[test]
+    fn save_keeps_carcasses() {
+        let mut sim = small_sim(13);
+        for (_, agent) in sim.agents.get_iter_mut() {
+            agent.hp = 0.1;
+            agent.eng = 0.0;
+        }
+        run(&mut sim, 10);
+        assert!(sim.carcasses.count() > 0);
+        let loaded = reload(&sim);
+        let carcasses = |sim: &Simulation| {
+            let mut carcasses: Vec<(f32, f32, f32, f32)> = sim.carcasses.get_iter().map(|(_, c)| (c.pos.x, c.pos.y, c.eng, c.max_eng)).collect();
+            carcasses.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            carcasses
+        };
+        assert_eq!(carcasses(&loaded), carcasses(&sim));
+        let bodies = |sim: &Simulation| {
+            let mut bodies: Vec<[f32; 3]> = sim.carcasses.get_iter().map(|(_, c)| c.get_sketch(&sim.physics, &sim.settings).body).collect();
+            bodies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            bodies
+        };
+        let (saved, restored) = (bodies(&sim), bodies(&loaded));
+        for (a, b) in saved.iter().zip(restored.iter()) {
+            assert!((a[0]-b[0]).abs() < 1e-3 && (a[1]-b[1]).abs() < 1e-3);
+            assert!(Vec2::from_angle(a[2]).distance(Vec2::from_angle(b[2])) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn save_keeps_scenario_and_pending_actions() {
+        let mut values = serde_json::Map::new();
+        values.insert("agent_min_num".to_string(), serde_json::json!(2));
+        let scenario = Scenario {
+            seed: Some(3),
+            world: Some([600, 600]),
+            agents: Some(8),
+            plants: Some(40),
+            actions: vec![TimedAction { time: 100.0, action: ScenarioAction::Settings { values } }],
+            ..Default::default()
+        };
+        let mut sim = Simulation::new(Settings::default());
+        sim.load_scenario(scenario);
+        run(&mut sim, 10);
+        let mut loaded = reload(&sim);
+        assert!(loaded.scenario.is_some());
+        assert_eq!(loaded.get_scenario_actions().len(), 1);
+        loaded.sim_state.sim_time = 100.0;
+        loaded.run_scenario_actions();
+        assert_eq!(loaded.settings.agent_min_num, 2);
+        assert!(loaded.get_scenario_actions().is_empty());
+    }
+
+    #[test]
+    fn loaded_sim_continues_like_uninterrupted_run() {
+        let mut sim = small_sim(11);
+        run(&mut sim, 30);
+        let mut loaded = reload(&sim);
+        // contact caches of physics engine are not saved, so bodies may drift slightly
+        run(&mut sim, 60);
+        run(&mut loaded, 60);
+        assert_eq!(loaded.sim_state.sim_time, sim.sim_state.sim_time);
+        assert_eq!(loaded.agents.count(), sim.agents.count());
+        assert_eq!(loaded.plants.count(), sim.plants.count());
+        let expected = agent_states(&sim);
+        let states = agent_states(&loaded);
+        for (state, exp) in states.iter().zip(expected.iter()) {
+            assert!((state[0]-exp[0]).abs() < 1.0 && (state[1]-exp[1]).abs() < 1.0, "{:?} != {:?}", state, exp);
+            assert!((state[2]-exp[2]).abs() < 1.0, "{:?} != {:?}", state, exp);
+        }
+    }
+}
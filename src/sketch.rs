@@ -3,20 +3,31 @@
 use crate::neuro::*;
 use crate::terrain::*;
 use crate::sim::Simulation;
-use crate::util::random_position;
-use macroquad::prelude::*;
-use std::collections::HashMap;
+use crate::util::{StableMap, generate_seed, random_unit_unsigned};
+use crate::rng::SimRng;
 use std::fmt::Debug;
 use serde::{Serialize, Deserialize};
 use crate::settings::*;
 use crate::statistics::*;
 use crate::misc::*;
+use crate::obstacle::ObstacleShape;
+use crate::plant::PlantGenome;
+use crate::species::SpeciesRegistry;
+use crate::timer::Timer;
+use crate::scenario::{Scenario, TimedAction};
 
-fn random_location() -> [f32; 2] {
-    let w = get_settings().world_w as f32;
-    let h = get_settings().world_h as f32;
-    let pos =  random_position(w, h);
-    return [pos.x, pos.y];
+#[doc = r"Location outside of world, agent added to simulation gets random position instead."]
+fn unknown_location() -> [f32; 2] {
+    return [-1.0, -1.0];
+}
+
+#[doc = r"Average hearing gene for agents saved before hearing existed."]
+fn default_ears() -> i32 {
+    return 5;
+}
+
+fn default_rng() -> SimRng {
+    return SimRng::new(generate_seed());
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,7 +39,7 @@ pub struct AgentSketch {
     pub color: [f32; 4],
     pub color_second: [f32; 4],
     pub network: NetworkSketch,
-    #[serde(default = "random_location")]
+    #[serde(default = "unknown_location")]
     pub pos: [f32; 2],
     pub points: f32,
     pub neuro_map: NeuroMap,
@@ -37,7 +48,81 @@ pub struct AgentSketch {
     pub shell: i32,
     pub mutations: i32,
     pub eyes: i32,
+    #[serde(default = "default_ears")]
+    pub ears: i32,
     pub ancestors: Ancestors,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<AgentState>,
+}
+
+#[doc = r"Runtime state of living agent. It is stored only in simulation saves, so loaded agent continues exactly where it stopped."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AgentState {
+    pub rot: f32,
+    #[doc = r"Position and rotation of physics body, which is one step ahead of agent position."]
+    pub body: [f32; 3],
+    pub vel: f32,
+    pub ang_vel: f32,
+    pub eng: f32,
+    pub hp: f32,
+    pub lifetime: f32,
+    pub repro_time: f32,
+    pub childs: usize,
+    pub kills: usize,
+    pub pain: f32,
+    pub run: bool,
+    pub attacking: bool,
+    pub eating: bool,
+    pub blocked: f32,
+    pub mood: [f32; 4],
+    pub emission: [f32; SCENT_CHANNELS],
+    pub sound: f32,
+    #[doc = r"Terrain under agent, sampled only every few ticks."]
+    pub water: i32,
+    pub altitude: i32,
+    pub slope: [f32; 2],
+    pub biome: Biome,
+    pub scent: [f32; SCENT_CHANNELS],
+    pub scent_gradient: [[f32; 2]; SCENT_CHANNELS],
+    pub timer_analize: Timer,
+    pub timer_contact: Timer,
+    pub node_values: StableMap<u64, f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlantSketch {
+    pub pos: [f32; 2],
+    pub size: f32,
+    pub eng: f32,
+    pub time: f32,
+    pub generation: u32,
+    pub genome: PlantGenome,
+    #[serde(default)]
+    pub clone_timer: Option<Timer>,
+    #[serde(default)]
+    pub growth_timer: Option<Timer>,
+}
+
+#[doc = r"Plant saved together with data specific for its kind."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum PlantKindSketch {
+    Grass { plant: PlantSketch },
+    Bush { plant: PlantSketch, regrowths: u32 },
+    Toxic { plant: PlantSketch },
+}
+
+#[doc = r"Carcass of dead agent with its remaining energy and decay rate."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarcassSketch {
+    pub pos: [f32; 2],
+    pub rot: f32,
+    #[doc = r"Position and rotation of physics body, which is one step ahead of carcass position."]
+    pub body: [f32; 3],
+    pub size: f32,
+    pub max_eng: f32,
+    pub eng: f32,
+    pub decay: f32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -51,6 +136,31 @@ pub struct SimulationSketch {
     pub school: Vec<AgentSketch>,
     pub settings: Settings,
     pub terrain: SerializedTerrain,
+    #[serde(default = "default_rng")]
+    pub rng: SimRng,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleShape>,
+    #[serde(default)]
+    pub plants: Vec<PlantKindSketch>,
+    #[serde(default)]
+    pub carcasses: Vec<CarcassSketch>,
+    #[serde(default)]
+    pub species: SpeciesRegistry,
+    #[serde(default)]
+    pub timers: Option<SimTimers>,
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+    #[serde(default)]
+    pub scenario_actions: Vec<TimedAction>,
+}
+
+#[doc = r"Clocks of simulation, saved so periodic tasks keep their phase after loading."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SimTimers {
+    pub population: Timer,
+    pub coords: Timer,
+    pub stats: Timer,
+    pub terrain: Timer,
 }
 
 impl SimulationSketch {
@@ -60,7 +170,8 @@ impl SimulationSketch {
         let mut ranking: Vec<AgentSketch> = vec![];
         let mut school: Vec<AgentSketch> = vec![];
         for (_, agent) in sim.agents.get_iter() {
-            let sketch = agent.get_sketch();
+            let mut sketch = agent.get_sketch();
+            sketch.state = Some(agent.get_state(&sim.physics, &sim.settings));
             agents.push(sketch);
         }
         for sketch in sim.ranking.get_general_rank().iter() {
@@ -71,18 +182,25 @@ impl SimulationSketch {
             let sketch2 = sketch.to_owned();
             school.push(sketch2);
         }
-        let settings = get_settings();
+        let settings = &sim.settings;
         Self { 
             simulation_name: sim.simulation_name.to_owned(), 
             world_size: MyPos2::from_vec(&sim.world_size), 
-            sim_time: sim.sim_state.sim_time.round(), 
+            sim_time: sim.sim_state.sim_time, 
             agents: agents.to_owned(), 
             ranking: ranking.to_owned(),
             school: school.to_owned(),
             last_autosave: sim.sim_state.sim_time.round(),
             settings: settings.to_owned(),
             terrain: SerializedTerrain::new(&sim.terrain),
-
+            rng: sim.rng.clone(),
+            obstacles: sim.obstacles.get_shapes(),
+            plants: sim.plants.get_iter().filter(|(_, plant)| plant.get_base().pos.is_finite()).map(|(_, plant)| plant.get_sketch()).collect(),
+            carcasses: sim.carcasses.get_iter().map(|(_, carcass)| carcass.get_sketch(&sim.physics, settings)).collect(),
+            species: sim.species.to_owned(),
+            timers: Some(sim.get_timers()),
+            scenario: sim.scenario.to_owned(),
+            scenario_actions: sim.get_scenario_actions().to_vec(),
         }
     }
 
@@ -97,10 +215,6 @@ fn default_memory() -> Option<MemStore> {
     return None;
 }
 
-fn default_active_rate() -> f32 {
-    return 1.0;
-}
-
 fn default_lazy_num() -> u32 {
     return 0;
 }
@@ -130,19 +244,19 @@ pub struct LinkSketch {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NetworkSketch {
-    pub nodes: HashMap<u64, NodeSketch>,
-    pub links: HashMap<u64, LinkSketch>,
+    pub nodes: StableMap<u64, NodeSketch>,
+    pub links: StableMap<u64, LinkSketch>,
 }
 
 impl NetworkSketch {
     
-    pub fn from_sketch(&self) -> Network {
-        let mut nodes: HashMap<u64, Node> = HashMap::new();
-        let mut links: HashMap<u64, Link> = HashMap::new();
+    pub fn from_sketch(&self, rng: &mut SimRng) -> Network {
+        let mut nodes: StableMap<u64, Node> = StableMap::default();
+        let mut links: StableMap<u64, Link> = StableMap::default();
         for (key, sketch_node) in self.nodes.iter() {
             let mut node = Node::from_sketch(sketch_node.to_owned());
             if sketch_node.memory_type && sketch_node.memory.is_none() {
-                node.memory = Some(MemStore::new_random());
+                node.memory = Some(MemStore::new_random(rng));
             }
             nodes.insert(*key, node);
         }
@@ -165,4 +279,26 @@ impl NetworkSketch {
         return net;
     }
 
+    #[doc = r"Combines two parent networks aligned by node and link ids. Matching genes come from random parent, disjoint ones from first parent.
+Link rewired by node insertion keeps its id, so link of other parent is used only if it connects nodes child has."]
+    pub fn crossover(&self, other: &NetworkSketch, rng: &mut SimRng) -> NetworkSketch {
+        let mut nodes: StableMap<u64, NodeSketch> = StableMap::default();
+        let mut links: StableMap<u64, LinkSketch> = StableMap::default();
+        for (key, node) in self.nodes.iter() {
+            let node = match other.nodes.get(key) {
+                Some(other_node) if random_unit_unsigned(rng) < 0.5 => other_node,
+                _ => node,
+            };
+            nodes.insert(*key, node.to_owned());
+        }
+        for (key, link) in self.links.iter() {
+            let link = match other.links.get(key) {
+                Some(other_link) if nodes.contains_key(&other_link.node_from) && nodes.contains_key(&other_link.node_to) && random_unit_unsigned(rng) < 0.5 => other_link,
+                _ => link,
+            };
+            links.insert(*key, link.to_owned());
+        }
+        return NetworkSketch { nodes, links };
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,360 @@
+//#![allow(unused)]
+
+use crate::math::*;
+use rapier2d::prelude::RigidBodyHandle;
+use crate::collector::{AgentBox, PlantBox};
+use crate::settings::*;
+use crate::terrain::Terrain;
+use crate::util::torus_offset;
+
+
+#[doc = r"Minimal side of spatial grid bucket. Bucket is made of whole terrain cells."]
+const BUCKET_SIZE: f32 = 100.0;
+
+#[derive(Clone, Copy, Debug)]
+struct SpatialEntry {
+    rbh: RigidBodyHandle,
+    pos: Vec2,
+    size: f32,
+}
+
+#[doc = r"Uniform grid of agents and plants positions used for perception queries.
+It is rebuilt once per tick and its buckets are aligned with terrain cells. In torus world queries wrap across edges."]
+#[derive(Clone, Debug)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    factor: i32,
+    cols: i32,
+    rows: i32,
+    max_size: f32,
+    torus: bool,
+    world_size: Vec2,
+    agents: Vec<Vec<SpatialEntry>>,
+    plants: Vec<Vec<SpatialEntry>>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpatialGrid {
+
+    pub fn new() -> Self {
+        Self {
+            cell_size: 1.0,
+            factor: 1,
+            cols: 0,
+            rows: 0,
+            max_size: 0.0,
+            torus: false,
+            world_size: Vec2::ZERO,
+            agents: vec![],
+            plants: vec![],
+        }
+    }
+
+    #[doc = r"Fill grid with current positions of all agents and plants."]
+    pub fn rebuild(&mut self, terrain: &Terrain, agents: &AgentBox, plants: &PlantBox, settings: &Settings) {
+        let world_size = Vec2::new(settings.world_w as f32, settings.world_h as f32);
+        self.reset(terrain.cell_size, terrain.width, terrain.height, settings.topology == WorldTopology::TORUS, world_size);
+        for (rbh, agent) in agents.get_iter() {
+            self.insert_agent(*rbh, agent.pos, agent.size);
+        }
+        for (rbh, plant) in plants.get_iter() {
+            let plant = plant.get_base();
+            self.insert_plant(*rbh, plant.pos, plant.size);
+        }
+    }
+
+    fn reset(&mut self, cell_size: f32, width: usize, height: usize, torus: bool, world_size: Vec2) {
+        self.cell_size = cell_size;
+        self.max_size = 0.0;
+        self.torus = torus;
+        self.world_size = world_size;
+        self.factor = ((BUCKET_SIZE / cell_size).ceil() as i32).max(1);
+        self.cols = ((width as i32 + self.factor - 1) / self.factor).max(1);
+        self.rows = ((height as i32 + self.factor - 1) / self.factor).max(1);
+        let n = (self.cols * self.rows) as usize;
+        for bucket in self.agents.iter_mut() { bucket.clear(); }
+        for bucket in self.plants.iter_mut() { bucket.clear(); }
+        self.agents.resize(n, vec![]);
+        self.plants.resize(n, vec![]);
+    }
+
+    fn insert_agent(&mut self, rbh: RigidBodyHandle, pos: Vec2, size: f32) {
+        let i = self.bucket_index(&pos);
+        self.max_size = self.max_size.max(size);
+        self.agents[i].push(SpatialEntry { rbh, pos, size });
+    }
+
+    fn insert_plant(&mut self, rbh: RigidBodyHandle, pos: Vec2, size: f32) {
+        let i = self.bucket_index(&pos);
+        self.max_size = self.max_size.max(size);
+        self.plants[i].push(SpatialEntry { rbh, pos, size });
+    }
+
+    #[doc = r"Closest agent in front of observer (inside vision angle) or very close to it (inside peripheral vision).
+Candidates for which visible (called with offset from observer and size of candidate) is false, e.g. hidden behind wall, are skipped."]
+    #[allow(clippy::too_many_arguments)]
+    pub fn closest_agent(&self, rbh: RigidBodyHandle, pos: Vec2, detection_range: f32, detection_angle: f32, direction: Vec2, settings: &Settings, visible: impl Fn(Vec2, f32) -> bool) -> Option<RigidBodyHandle> {
+        let mut small_vision = settings.peripheral_vision*settings.agent_vision_range;
+        small_vision = clamp(small_vision, 0.0, detection_range);
+        return self.closest(&self.agents, rbh, pos, detection_range, detection_angle, direction, small_vision, visible);
+    }
+
+    #[doc = r"Closest plant in front of observer (inside vision angle) or very close to it. Candidates which aren't visible are skipped."]
+    pub fn closest_plant(&self, rbh: RigidBodyHandle, pos: Vec2, detection_range: f32, detection_angle: f32, direction: Vec2, visible: impl Fn(Vec2, f32) -> bool) -> Option<RigidBodyHandle> {
+        return self.closest(&self.plants, rbh, pos, detection_range, detection_angle, direction, detection_range*0.1, visible);
+    }
+
+    pub fn count_near_plants(&self, pos: Vec2, detection_range: f32) -> usize {
+        let mut n: usize = 0;
+        self.for_each_in_range(&self.plants, pos, detection_range, |entry| {
+            if self.offset(&pos, &entry.pos).length() - entry.size <= detection_range {
+                n += 1;
+            }
+        });
+        return n;
+    }
+
+    #[doc = r"Agents and plants touching observer across world edges. Physics engine doesn't see such contacts, so they are found here. Empty in bounded world."]
+    pub fn contacts_across_edges(&self, rbh: RigidBodyHandle, pos: Vec2, radius: f32) -> Vec<RigidBodyHandle> {
+        let mut contacts: Vec<RigidBodyHandle> = vec![];
+        if !self.torus { return contacts; }
+        for buckets in [&self.agents, &self.plants] {
+            self.for_each_in_range(buckets, pos, radius, |entry| {
+                if entry.rbh == rbh { return; }
+                let direct = pos.distance(entry.pos) - entry.size;
+                let wrapped = self.offset(&pos, &entry.pos).length() - entry.size;
+                if wrapped <= radius && direct > radius {
+                    contacts.push(entry.rbh);
+                }
+            });
+        }
+        return contacts;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn closest(&self, buckets: &Vec<Vec<SpatialEntry>>, rbh: RigidBodyHandle, pos: Vec2, detection_range: f32, detection_angle: f32, direction: Vec2, near_range: f32, visible: impl Fn(Vec2, f32) -> bool) -> Option<RigidBodyHandle> {
+        let mut dist = f32::INFINITY;
+        let mut target: Option<RigidBodyHandle> = None;
+        self.for_each_in_range(buckets, pos, detection_range, |entry| {
+            if entry.rbh == rbh { return; }
+            let local_pos = self.offset(&pos, &entry.pos);
+            let new_dist = local_pos.length();
+            if new_dist - entry.size > detection_range { return; }
+            let ang = direction.angle_between(local_pos.normalize_or_zero());
+            if new_dist < dist && (new_dist <= near_range || ang.abs() <= detection_angle/2.0) && visible(local_pos, entry.size) {
+                dist = new_dist;
+                target = Some(entry.rbh);
+            }
+        });
+        return target;
+    }
+
+    fn for_each_in_range<F: FnMut(&SpatialEntry)>(&self, buckets: &Vec<Vec<SpatialEntry>>, pos: Vec2, range: f32, mut f: F) {
+        if buckets.is_empty() { return; }
+        let reach = range + self.max_size;
+        let xs = self.axis_buckets(pos.x, reach, self.world_size.x, self.cols);
+        let ys = self.axis_buckets(pos.y, reach, self.world_size.y, self.rows);
+        for y in ys.iter() {
+            for x in xs.iter() {
+                for entry in buckets[(y * self.cols + x) as usize].iter() {
+                    f(entry);
+                }
+            }
+        }
+    }
+
+    #[doc = r"Bucket indexes along one axis covering center +/- reach. In torus world range is split at world edges."]
+    fn axis_buckets(&self, center: f32, reach: f32, world: f32, n: i32) -> Vec<i32> {
+        let bucket = |v: f32| -> i32 {
+            let [cell, _] = Terrain::pos_to_cell(&Vec2::new(v, 0.0), self.cell_size);
+            return clamp(cell.div_euclid(self.factor), 0, n-1);
+        };
+        let lo = center - reach;
+        let hi = center + reach;
+        if !self.torus {
+            return (bucket(lo)..=bucket(hi)).collect();
+        }
+        if hi - lo >= world {
+            return (0..n).collect();
+        }
+        let mut buckets: Vec<i32> = vec![];
+        if lo < 0.0 {
+            buckets.extend(0..=bucket(hi));
+            buckets.extend(bucket(lo + world)..n);
+        } else if hi >= world {
+            buckets.extend(0..=bucket(hi - world));
+            buckets.extend(bucket(lo)..n);
+        } else {
+            buckets.extend(bucket(lo)..=bucket(hi));
+        }
+        buckets.sort();
+        buckets.dedup();
+        return buckets;
+    }
+
+    fn offset(&self, from: &Vec2, to: &Vec2) -> Vec2 {
+        if self.torus {
+            return torus_offset(from, to, &self.world_size);
+        }
+        return *to - *from;
+    }
+
+    fn bucket_coord(&self, pos: &Vec2) -> [i32; 2] {
+        let [x, y] = Terrain::pos_to_cell(pos, self.cell_size);
+        let bx = clamp(x.div_euclid(self.factor), 0, self.cols-1);
+        let by = clamp(y.div_euclid(self.factor), 0, self.rows-1);
+        return [bx, by];
+    }
+
+    fn bucket_index(&self, pos: &Vec2) -> usize {
+        let [x, y] = self.bucket_coord(pos);
+        return (y * self.cols + x) as usize;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SimRng;
+    use crate::util::{random_unit_unsigned, random_rotation};
+
+    const WORLD: f32 = 1000.0;
+
+    fn entries(num: usize, first: u32, rng: &mut SimRng) -> Vec<SpatialEntry> {
+        return (0..num).map(|i| SpatialEntry {
+            rbh: RigidBodyHandle::from_raw_parts(first + i as u32, 0),
+            pos: Vec2::new(random_unit_unsigned(rng)*WORLD, random_unit_unsigned(rng)*WORLD),
+            size: 2.0 + random_unit_unsigned(rng)*8.0,
+        }).collect();
+    }
+
+    fn build_grid(agents: &[SpatialEntry], plants: &[SpatialEntry], torus: bool) -> SpatialGrid {
+        let mut grid = SpatialGrid::new();
+        grid.reset(20.0, 50, 50, torus, Vec2::new(WORLD, WORLD));
+        for entry in agents.iter() {
+            grid.insert_agent(entry.rbh, entry.pos, entry.size);
+        }
+        for entry in plants.iter() {
+            grid.insert_plant(entry.rbh, entry.pos, entry.size);
+        }
+        return grid;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn brute_closest(grid: &SpatialGrid, all: &[SpatialEntry], rbh: RigidBodyHandle, pos: Vec2, range: f32, angle: f32, direction: Vec2, near_range: f32, visible: impl Fn(Vec2, f32) -> bool) -> Option<f32> {
+        let mut dist = f32::INFINITY;
+        for entry in all.iter() {
+            if entry.rbh == rbh { continue; }
+            let local_pos = grid.offset(&pos, &entry.pos);
+            let d = local_pos.length();
+            if d - entry.size > range { continue; }
+            let ang = direction.angle_between(local_pos.normalize_or_zero());
+            if d < dist && (d <= near_range || ang.abs() <= angle/2.0) && visible(local_pos, entry.size) {
+                dist = d;
+            }
+        }
+        return match dist.is_finite() {
+            true => Some(dist),
+            false => None,
+        };
+    }
+
+    #[test]
+    fn closest_agent_matches_brute_force() {
+        let mut rng = SimRng::new(3);
+        let settings = Settings::default();
+        let mut hits = 0;
+        for torus in [false, true] {
+            let agents = entries(300, 0, &mut rng);
+            let grid = build_grid(&agents, &[], torus);
+            for observer in agents.iter().take(100) {
+                let direction = Vec2::from_angle(random_rotation(&mut rng));
+                let range = 50.0 + random_unit_unsigned(&mut rng)*250.0;
+                let angle = 0.5 + random_unit_unsigned(&mut rng)*2.5;
+                let near_range = clamp(settings.peripheral_vision*settings.agent_vision_range, 0.0, range);
+                let found = grid.closest_agent(observer.rbh, observer.pos, range, angle, direction, &settings, |_, _| true)
+                    .map(|rbh| grid.offset(&observer.pos, &agents[rbh.into_raw_parts().0 as usize].pos).length());
+                let expected = brute_closest(&grid, &agents, observer.rbh, observer.pos, range, angle, direction, near_range, |_, _| true);
+                assert_eq!(found, expected, "torus: {} observer: {:?}", torus, observer.pos);
+                hits += found.is_some() as usize;
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn closest_agent_skips_hidden_candidates() {
+        let mut rng = SimRng::new(4);
+        let settings = Settings::default();
+        // everything on the left of observer is hidden
+        let visible = |offset: Vec2, _size: f32| offset.x >= 0.0;
+        let mut hits = 0;
+        for torus in [false, true] {
+            let agents = entries(300, 0, &mut rng);
+            let grid = build_grid(&agents, &[], torus);
+            for observer in agents.iter().take(100) {
+                let direction = Vec2::from_angle(random_rotation(&mut rng));
+                let range = 50.0 + random_unit_unsigned(&mut rng)*250.0;
+                let angle = 0.5 + random_unit_unsigned(&mut rng)*2.5;
+                let near_range = clamp(settings.peripheral_vision*settings.agent_vision_range, 0.0, range);
+                let found = grid.closest_agent(observer.rbh, observer.pos, range, angle, direction, &settings, visible)
+                    .map(|rbh| grid.offset(&observer.pos, &agents[rbh.into_raw_parts().0 as usize].pos));
+                let expected = brute_closest(&grid, &agents, observer.rbh, observer.pos, range, angle, direction, near_range, visible);
+                assert_eq!(found.map(|offset| offset.length()), expected, "torus: {} observer: {:?}", torus, observer.pos);
+                assert!(found.map_or(true, |offset| offset.x >= 0.0));
+                hits += found.is_some() as usize;
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn contacts_across_edges_match_brute_force() {
+        let mut rng = SimRng::new(5);
+        let agents = entries(200, 0, &mut rng);
+        let plants = entries(200, 1000, &mut rng);
+        let grid = build_grid(&agents, &plants, true);
+        let mut observers = agents.to_vec();
+        observers.push(SpatialEntry { rbh: RigidBodyHandle::from_raw_parts(5000, 0), pos: Vec2::new(1.0, 1.0), size: 5.0 });
+        observers.push(SpatialEntry { rbh: RigidBodyHandle::from_raw_parts(5001, 0), pos: Vec2::new(WORLD-1.0, 500.0), size: 5.0 });
+        let mut wrapped_found = 0;
+        for observer in observers.iter() {
+            let radius = 20.0 + observer.size;
+            let mut found = grid.contacts_across_edges(observer.rbh, observer.pos, radius);
+            let mut expected: Vec<RigidBodyHandle> = agents.iter().chain(plants.iter())
+                .filter(|entry| entry.rbh != observer.rbh)
+                .filter(|entry| {
+                    let direct = observer.pos.distance(entry.pos) - entry.size;
+                    let wrapped = grid.offset(&observer.pos, &entry.pos).length() - entry.size;
+                    wrapped <= radius && direct > radius
+                })
+                .map(|entry| entry.rbh)
+                .collect();
+            found.sort_by_key(|rbh| rbh.into_raw_parts());
+            expected.sort_by_key(|rbh| rbh.into_raw_parts());
+            wrapped_found += found.len();
+            assert_eq!(found, expected, "observer: {:?}", observer.pos);
+        }
+        assert!(wrapped_found > 0);
+        let bounded = build_grid(&agents, &[], false);
+        assert!(bounded.contacts_across_edges(observers[200].rbh, observers[200].pos, 50.0).is_empty());
+    }
+
+    #[test]
+    fn axis_buckets_wrap_in_torus() {
+        let grid = build_grid(&[], &[], true);
+        assert_eq!(grid.cols, 10);
+        assert_eq!(grid.axis_buckets(10.0, 50.0, WORLD, grid.cols), vec![0, 9]);
+        assert_eq!(grid.axis_buckets(990.0, 50.0, WORLD, grid.cols), vec![0, 9]);
+        assert_eq!(grid.axis_buckets(500.0, 50.0, WORLD, grid.cols), vec![4, 5]);
+        assert_eq!(grid.axis_buckets(500.0, 600.0, WORLD, grid.cols), (0..10).collect::<Vec<i32>>());
+        let bounded = build_grid(&[], &[], false);
+        assert_eq!(bounded.axis_buckets(10.0, 50.0, WORLD, bounded.cols), vec![0]);
+    }
+}
@@ -0,0 +1,133 @@
+//#![allow(unused)]
+
+use serde::{Serialize, Deserialize};
+use crate::agent::Agent;
+use crate::neuro::Genome;
+use crate::settings::Settings;
+use crate::rng::SimRng;
+use crate::util::*;
+
+#[doc = r"Cluster of agents whose networks are within compatibility threshold of representative genome of its founder."]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Species {
+    pub name: String,
+    pub representative: Genome,
+    pub origin: i32,
+}
+
+#[doc = r"All species of simulation. Agents are clustered by compatibility distance of their networks, so names of species follow real divergence of genomes."]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SpeciesRegistry {
+    species: Vec<Species>,
+}
+
+impl SpeciesRegistry {
+
+    pub fn new() -> Self {
+        Self {
+            species: vec![],
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Species> {
+        return self.species.iter().find(|s| s.name == name);
+    }
+
+    pub fn count(&self) -> usize {
+        return self.species.len();
+    }
+
+    #[doc = r"Keeps agent in its specie while compatible with it, otherwise moves it to closest compatible specie or founds new one.
+Name of new specie is derived from previous one, more distant genome changes more syllables. Agent with unknown specie founds specie under its own name.
+Returns true if new specie was founded."]
+    pub fn assign(&mut self, agent: &mut Agent, time: f64, settings: &Settings, rng: &mut SimRng) -> bool {
+        let genome = agent.network.get_genome();
+        let distance = match self.get(&agent.specie) {
+            Some(specie) => genome.distance(&specie.representative, settings),
+            None => {
+                self.species.push(Species { name: agent.specie.to_owned(), representative: genome, origin: time.round() as i32 });
+                return true;
+            },
+        };
+        if distance < settings.compat_threshold {
+            return false;
+        }
+        let mut closest: Option<(usize, f32)> = None;
+        for (i, specie) in self.species.iter().enumerate() {
+            let d = genome.distance(&specie.representative, settings);
+            if d < settings.compat_threshold && closest.map_or(true, |(_, c)| d < c) {
+                closest = Some((i, d));
+            }
+        }
+        match closest {
+            Some((i, _)) => {
+                let name = self.species[i].name.to_owned();
+                agent.change_specie(&name, time);
+                return false;
+            },
+            None => {
+                let syllables = ((distance / settings.compat_threshold) as usize).clamp(1, 4);
+                let mut name = derive_name(&agent.specie, syllables, rng);
+                while self.get(&name).is_some() {
+                    name = derive_name(&agent.specie, syllables, rng);
+                }
+                self.species.push(Species { name: name.to_owned(), representative: genome, origin: time.round() as i32 });
+                agent.found_specie(&name, time, rng);
+                return true;
+            },
+        }
+    }
+
+    #[doc = r"Removes species which have no members among given names."]
+    pub fn retain(&mut self, names: &StableSet<String>) {
+        self.species.retain(|s| names.contains(&s.name));
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phyx::physics::Physics;
+
+    fn syllables_changed(a: &str, b: &str) -> usize {
+        let a: Vec<char> = a.chars().collect();
+        let b: Vec<char> = b.chars().collect();
+        return a.chunks(2).zip(b.chunks(2)).filter(|(x, y)| x != y).count();
+    }
+
+    #[test]
+    fn assign_keeps_founds_and_moves_species() {
+        let settings = Settings::default();
+        let mut rng = SimRng::new(1);
+        let mut physics = Physics::new();
+        let mut registry = SpeciesRegistry::new();
+        let mut first = Agent::new(&mut physics, &settings, &mut rng);
+        let mut second = Agent::new(&mut physics, &settings, &mut rng);
+        assert!(first.network.compatibility(&second.network, &settings) >= settings.compat_threshold);
+        assert!(registry.assign(&mut first, 0.0, &settings, &mut rng));
+        assert!(registry.assign(&mut second, 0.0, &settings, &mut rng));
+        assert_eq!(registry.count(), 2);
+
+        let mut clone = Agent::new(&mut physics, &settings, &mut rng);
+        clone.network = first.network.replicate(&mut rng);
+        clone.specie = first.specie.to_owned();
+        assert!(!registry.assign(&mut clone, 1.0, &settings, &mut rng));
+        assert_eq!(clone.specie, first.specie);
+
+        clone.network = second.network.replicate(&mut rng);
+        assert!(!registry.assign(&mut clone, 2.0, &settings, &mut rng));
+        assert_eq!(clone.specie, second.specie);
+        assert_eq!(registry.count(), 2);
+
+        let mut stray = Agent::new(&mut physics, &settings, &mut rng);
+        stray.specie = first.specie.to_owned();
+        assert!(registry.assign(&mut stray, 3.0, &settings, &mut rng));
+        assert_eq!(registry.count(), 3);
+        assert_ne!(stray.specie, first.specie);
+        assert_eq!(stray.specie.len(), first.specie.len());
+        assert!((1..=4).contains(&syllables_changed(&stray.specie, &first.specie)));
+        assert!(registry.get(&stray.specie).is_some());
+    }
+}
@@ -1,16 +1,16 @@
 //#![allow(unused)]
 
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::VecDeque;
+use crate::util::StableMap;
 use std::fmt::Debug;
-use macroquad::experimental::collections::storage;
 use serde::{Serialize, Deserialize};
 
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Statistics {
     limit: usize,
-    data: HashMap<String, VecDeque<[f64; 2]>>,
+    data: StableMap<String, VecDeque<[f64; 2]>>,
 }
 
 impl Statistics {
@@ -18,7 +18,7 @@ impl Statistics {
     pub fn new(limit: usize) -> Statistics {
         Statistics {
             limit,
-            data: HashMap::new()
+            data: StableMap::default()
         }
     }
     
@@ -80,9 +80,13 @@ impl Ancestor {
 }
 
 
+const MATES_LIMIT: usize = 20;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Ancestors {
-    list: Vec<Ancestor>
+    list: Vec<Ancestor>,
+    #[serde(default)]
+    mates: Vec<Ancestor>,
 }
 
 impl Ancestors {
@@ -90,6 +94,7 @@ impl Ancestors {
     pub fn new() -> Self {
         Self {
             list: vec![],
+            mates: vec![],
         }
     }
 
@@ -101,6 +106,18 @@ impl Ancestors {
         return self.list.clone();
     }
 
+    #[doc = r"Records second parent of sexually born agent. Only the most recent mates of lineage are kept."]
+    pub fn add_mate(&mut self, mate: Ancestor) {
+        self.mates.push(mate);
+        if self.mates.len() > MATES_LIMIT {
+            self.mates.remove(0);
+        }
+    }
+
+    pub fn get_mates(&self) -> Vec<Ancestor> {
+        return self.mates.clone();
+    }
+
 }
 
 
@@ -132,12 +149,3 @@ impl MutationStats {
     }
 
 }
-
-pub fn set_mutations(stats: MutationStats) {
-    storage::store(stats);
-}
-
-pub fn get_mutations() -> MutationStats {
-    let stats = storage::get::<MutationStats>();
-    return stats.clone();
-}
\ No newline at end of file
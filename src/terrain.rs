@@ -1,30 +1,152 @@
 //#![allow(unused)]
 
-use std::i32::*;
-use crate::{get_settings, util::*};
-use macroquad::prelude::*;
+use crate::util::*;
+use crate::math::*;
 use std::fmt::Debug;
 use serde::{Serialize, Deserialize};
 use noise::{
-    core::perlin::perlin_2d, 
-    permutationtable::PermutationTable, 
     utils::{NoiseMap, NoiseMapBuilder, PlaneMapBuilder}, 
-    Fbm, NoiseFn, OpenSimplex, Perlin
+    Fbm, Perlin
 };
-use ::rand::prelude::*;
+use ::rand::Rng;
+use crate::rng::SimRng;
+use crate::settings::*;
+use crate::environment::Environment;
+
+#[doc = r"Number of independent scent channels agents can emit and sense."]
+pub const SCENT_CHANNELS: usize = 3;
+
+#[doc = r"Parameters of fractal noise used to generate terrain altitude."]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainNoise {
+    pub frequency: f64,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub persistence: f64,
+    pub offset: f32,
+}
+
+impl Default for TerrainNoise {
+
+    fn default() -> Self {
+        Self {
+            frequency: 0.6,
+            octaves: 4,
+            lacunarity: 0.8,
+            persistence: 0.8,
+            offset: 0.15,
+        }
+    }
+}
+
+#[doc = r"Type of land covering terrain cell. Derived from altitude and water or painted by hand in terrain editor."]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Biome {
+    Desert,
+    Grassland,
+    Forest,
+    Swamp,
+}
+
+impl Default for Biome {
+
+    fn default() -> Self {
+        Biome::Grassland
+    }
+}
+
+impl Biome {
+
+    pub const ALL: [Biome; 4] = [Biome::Desert, Biome::Grassland, Biome::Forest, Biome::Swamp];
+
+    #[doc = r"Biome of cell with given altitude and water: wet cells are swamps, lowlands are forests, uplands are deserts."]
+    pub fn from_terrain(altitude: i32, water: i32) -> Self {
+        if water > 0 {
+            return Biome::Swamp;
+        } else if altitude < 35 {
+            return Biome::Forest;
+        } else if altitude < 65 {
+            return Biome::Grassland;
+        } else {
+            return Biome::Desert;
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Desert => "DESERT",
+            Biome::Grassland => "GRASSLAND",
+            Biome::Forest => "FOREST",
+            Biome::Swamp => "SWAMP",
+        }
+    }
+
+    #[doc = r"Multiplier of chance that plant clones itself."]
+    pub fn plant_density(&self) -> f32 {
+        match self {
+            Biome::Desert => 0.2,
+            Biome::Grassland => 1.0,
+            Biome::Forest => 1.5,
+            Biome::Swamp => 0.8,
+        }
+    }
+
+    #[doc = r"Multiplier of plant growth."]
+    pub fn plant_growth(&self) -> f32 {
+        match self {
+            Biome::Desert => 0.4,
+            Biome::Grassland => 1.0,
+            Biome::Forest => 1.3,
+            Biome::Swamp => 1.2,
+        }
+    }
+
+    #[doc = r"Multiplier of energy used by agent for movement."]
+    pub fn move_cost(&self) -> f32 {
+        match self {
+            Biome::Desert => 1.2,
+            Biome::Grassland => 1.0,
+            Biome::Forest => 1.3,
+            Biome::Swamp => 1.8,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Biome::Desert => Color::new(0.85, 0.75, 0.45, 1.0),
+            Biome::Grassland => Color::new(0.55, 0.75, 0.35, 1.0),
+            Biome::Forest => Color::new(0.2, 0.5, 0.25, 1.0),
+            Biome::Swamp => Color::new(0.35, 0.45, 0.3, 1.0),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Cell {
     alt: i32,
     water: i32,
+    #[serde(default)]
+    biome: Biome,
+    #[serde(default)]
+    painted: bool,
+    #[serde(default)]
+    nutrients: f32,
+    #[serde(default)]
+    scent: [f32; SCENT_CHANNELS],
 }
 
 impl Cell {
 
     pub fn new(altitude: i32, water: i32) -> Self {
+        let alt = clamp(altitude, 0, 100);
         Self {
-            alt: clamp(altitude, 0, 100),
+            alt,
             water: water,
+            biome: Biome::from_terrain(alt, water),
+            painted: false,
+            nutrients: 0.0,
+            scent: [0.0; SCENT_CHANNELS],
         }
     }
 
@@ -35,10 +157,56 @@ impl Cell {
 
     pub fn set_altitude(&mut self, altitude: i32) {
         self.alt = clamp(altitude, 0, 100);
+        self.update_biome();
     }
 
     pub fn set_water(&mut self, water: i32) {
         self.water = clamp(water, 0, 100);
+        self.update_biome();
+    }
+
+    #[doc = r"Paint biome by hand, so it is no longer derived from terrain. None restores derived biome."]
+    pub fn set_biome(&mut self, biome: Option<Biome>) {
+        match biome {
+            Some(biome) => {
+                self.biome = biome;
+                self.painted = true;
+            },
+            None => {
+                self.painted = false;
+                self.update_biome();
+            },
+        }
+    }
+
+    pub fn get_biome(&self) -> Biome {
+        return self.biome;
+    }
+
+    pub fn set_nutrients(&mut self, nutrients: f32) {
+        self.nutrients = nutrients.max(0.0);
+    }
+
+    pub fn get_nutrients(&self) -> f32 {
+        return self.nutrients;
+    }
+
+    pub fn set_scent(&mut self, channel: usize, scent: f32) {
+        self.scent[channel] = scent.max(0.0);
+    }
+
+    pub fn get_scent(&self, channel: usize) -> f32 {
+        return self.scent[channel];
+    }
+
+    pub fn is_painted(&self) -> bool {
+        return self.painted;
+    }
+
+    fn update_biome(&mut self) {
+        if !self.painted {
+            self.biome = Biome::from_terrain(self.alt, self.water);
+        }
     }
 
     pub fn get_altitude(&self) -> i32 {
@@ -51,8 +219,9 @@ impl Cell {
 
     pub fn get_colors(&self) -> (Color, Option<Color>) {
         let alt = self.alt as f32 / 100.0;
-        let c0 = alt*0.8 + 0.2;;
-        let terrain = Color::new(c0, c0, c0, 0.8);
+        let c0 = alt*0.6 + 0.4;
+        let biome = self.biome.color();
+        let terrain = Color::new(biome.r*c0, biome.g*c0, biome.b*c0, 0.8);
         if self.water == 0 {
             return (terrain, None);
         } else {
@@ -66,30 +235,53 @@ impl Cell {
 }
 
 
+#[doc = r"Cell which gives (positive flow) or drains (negative flow) water. Flow is in units of water per second."]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Spring {
+    pub x: f32,
+    pub y: f32,
+    pub flow: f32,
+}
+
+#[doc = r"Rain cloud drifting over terrain. Zero radius means rain over whole world."]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Cloud {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub radius: f32,
+    pub time: f32,
+}
+
 #[derive(Clone, Debug)]
 pub struct Terrain {
     pub cells: Vec<Vec<Cell>>,
-    width: usize,
-    height: usize,
-    cell_size: f32,
-    occupied: Vec<[i32; 2]>,
-    brushed: Vec<(IVec2, f32)>,
-    cursor: Option<[i32; 2]>,
+    pub width: usize,
+    pub height: usize,
+    pub cell_size: f32,
+    pub occupied: Vec<[i32; 2]>,
+    pub brushed: Vec<(IVec2, f32)>,
+    pub cursor: Option<[i32; 2]>,
+    pub springs: Vec<Spring>,
+    pub clouds: Vec<Cloud>,
     brush_size: u32,
 }
 
 impl Terrain {
 
-    pub fn new(w: f32, h: f32, s: f32) -> Self {
+    pub fn new(w: f32, h: f32, s: f32, rng: &mut SimRng) -> Self {
+        return Self::with_noise(w, h, s, &TerrainNoise::default(), rng);
+    }
+
+    pub fn with_noise(w: f32, h: f32, s: f32, noise: &TerrainNoise, rng: &mut SimRng) -> Self {
         let row_num = (h/s) as usize;
         let col_num = (w/s) as usize;
-        let map = Self::generate_noise_map(col_num, row_num);
+        let map = Self::generate_noise_map(col_num, row_num, noise, rng.gen::<u32>());
         let mut cells: Vec<Vec<Cell>> = Vec::new();
         for c in 0..col_num {
             let mut row: Vec<Cell> = Vec::new();
             for r in 0..row_num {
                 let mut v = map.get_value(c, r) as f32;
-                v = v+0.15;
+                v = v+noise.offset;
                 v = clamp(v*100.0, 0.0, 100.0);
                 let cell = Cell::new(v as i32, 0);
                 row.push(cell);
@@ -104,24 +296,129 @@ impl Terrain {
             occupied: Vec::new(), 
             brushed: Vec::new(), 
             cursor: None,
+            springs: vec![],
+            clouds: vec![],
             brush_size: 1,
         }
     }
 
     pub fn from_serialized_terrain(serialized: &SerializedTerrain) -> Self {
+        let mut cells = serialized.cells.to_vec();
+        for cell in cells.iter_mut().flatten() {
+            cell.update_biome();
+        }
         Self { 
-            cells: serialized.cells.to_vec(), 
+            cells, 
             width: serialized.columns_num,
             height: serialized.rows_num,
             cell_size: serialized.cell_size,
             occupied: vec![],
             brushed: Vec::new(), 
             cursor: None,
+            springs: serialized.springs.to_vec(),
+            clouds: serialized.clouds.to_vec(),
             brush_size: 1,
         }
     }
 
-    pub fn update(&mut self) {
+    #[doc = r"Water cycle step lasting dt seconds: springs and sinks, rain from clouds, evaporation and flow of water downhill."]
+    pub fn update(&mut self, dt: f32, env: &Environment, settings: &Settings, rng: &mut SimRng) {
+        self.update_springs(dt, rng);
+        self.update_rain(dt, settings, rng);
+        self.update_evaporation(dt, env, settings, rng);
+        self.update_flow(settings.topology == WorldTopology::TORUS, rng);
+    }
+
+    fn update_springs(&mut self, dt: f32, rng: &mut SimRng) {
+        for spring in self.springs.to_vec().iter() {
+            let [c, r] = self.pos_to_coord(&vec2(spring.x, spring.y));
+            let amount = Self::random_round(spring.flow * dt, rng);
+            if let Some(cell) = self.get_mut_cell(c as usize, r as usize) {
+                let w = cell.get_water();
+                cell.set_water(w + amount);
+            }
+        }
+    }
+
+    fn update_rain(&mut self, dt: f32, settings: &Settings, rng: &mut SimRng) {
+        if rng.gen::<f32>() < settings.rain_chance * dt {
+            let pos = random_position(self.width as f32 * self.cell_size, self.height as f32 * self.cell_size, rng);
+            self.add_cloud(pos, settings.rain_radius, settings.rain_duration, rng);
+        }
+        let world = vec2(self.width as f32 * self.cell_size, self.height as f32 * self.cell_size);
+        let mut clouds = std::mem::take(&mut self.clouds);
+        for cloud in clouds.iter_mut() {
+            cloud.time -= dt;
+            cloud.pos += cloud.vel * dt;
+            cloud.pos = vec2(cloud.pos.x.rem_euclid(world.x.max(1.0)), cloud.pos.y.rem_euclid(world.y.max(1.0)));
+            let cells: Vec<(usize, usize, f32)> = match cloud.radius > 0.0 {
+                true => self.cells_in_region(cloud.pos, cloud.radius),
+                false => (0..self.width).flat_map(|c| (0..self.height).map(move |r| (c, r, 1.0))).collect(),
+            };
+            for (c, r, intens) in cells {
+                let amount = Self::random_round(settings.rain_intensity * intens * dt, rng);
+                if amount > 0 {
+                    let cell = &mut self.cells[c][r];
+                    let w = cell.get_water();
+                    cell.set_water(w + amount);
+                }
+            }
+        }
+        clouds.retain(|cloud| cloud.time > 0.0);
+        self.clouds = clouds;
+    }
+
+    #[doc = r"Water evaporates faster at high altitude and in summer."]
+    fn update_evaporation(&mut self, dt: f32, env: &Environment, settings: &Settings, rng: &mut SimRng) {
+        if settings.evaporation <= 0.0 { return; }
+        let season = 0.5 + env.season;
+        for cell in self.cells.iter_mut().flatten() {
+            let w = cell.get_water();
+            if w == 0 { continue; }
+            let altitude = 0.5 + cell.get_altitude() as f32 / 100.0;
+            let amount = Self::random_round(settings.evaporation * altitude * season * dt, rng);
+            if amount > 0 {
+                cell.set_water(w - amount);
+            }
+        }
+    }
+
+    #[doc = r"Integer part of value plus one more with probability equal to fractional part."]
+    fn random_round(value: f32, rng: &mut SimRng) -> i32 {
+        let base = value.floor();
+        let frac = value - base;
+        if rng.gen::<f32>() < frac {
+            return base as i32 + 1;
+        } else {
+            return base as i32;
+        }
+    }
+
+    pub fn add_cloud(&mut self, pos: Vec2, radius: f32, duration: f32, rng: &mut SimRng) {
+        let vel = random_unit_vec2(rng) * 20.0;
+        self.clouds.push(Cloud { pos, vel, radius, time: duration });
+    }
+
+    #[doc = r"Add spring (or sink if flow is negative) in cell at position, or remove spring which is already there."]
+    pub fn toggle_spring(&mut self, pos: Vec2, flow: f32) {
+        let coord = self.pos_to_coord(&pos);
+        let len = self.springs.len();
+        self.springs.retain(|spring| Self::pos_to_cell(&vec2(spring.x, spring.y), self.cell_size) != coord);
+        if self.springs.len() == len {
+            let center = self.coord_to_pos(coord) + Vec2::splat(self.cell_size/2.0);
+            self.springs.push(Spring { x: center.x, y: center.y, flow });
+        }
+    }
+
+    pub fn toggle_spring_at_cursor(&mut self, flow: f32) {
+        if let Some(coord) = self.cursor {
+            let pos = self.coord_to_pos(coord) + Vec2::splat(self.cell_size/2.0);
+            self.toggle_spring(pos, flow);
+        }
+    }
+
+    #[doc = r"Moves water down to lower neighbour cells. In torus world water flows across world edges."]
+    fn update_flow(&mut self, wrap: bool, rng: &mut SimRng) {
         let mut water_buf: Vec<Vec<i32>> = Vec::new();
         for c in 0..self.cells.len() {
             let mut col: Vec<i32> = Vec::new();
@@ -132,15 +429,15 @@ impl Terrain {
                     },
                     None => {
                         let msg = format!("cell not exist: (x: {} | y {})", c, r);
-                        warn!("{}", msg);
+                        eprintln!("{}", msg);
                         col.push(0);
                     },
                 }
             }
             water_buf.push(col);
         }
-        for c in 0..self.cells.len()-1 {
-            for r in 0..self.cells[c].len()-1 {
+        for c in 0..self.cells.len() {
+            for r in 0..self.cells[c].len() {
                 if let Some(cell) = self.get_cell(c, r) {
                     let w0 = cell.get_water();
                     let a0 = cell.get_altitude();
@@ -148,9 +445,9 @@ impl Terrain {
                     for x in 0_i32..=2_i32 {
                         for y in 0_i32..=2_i32 {
                             if x.abs() == y.abs() { continue; }
-                            let c2 = c as i32 + x as i32 - 1;
-                            let r2 = r as i32 + y as i32 - 1;
-                            if c2 < 0 || r2 < 0 { continue; }
+                            let coord = [c as i32 + x - 1, r as i32 + y - 1];
+                            if !wrap && (coord[0] < 0 || coord[1] < 0 || coord[0] >= self.width as i32 || coord[1] >= self.height as i32) { continue; }
+                            let [c2, r2] = self.wrap_coord(coord);
                             if water0 >= w0 { continue; }
                             if let Some(cell2) = self.get_cell(c2 as usize, r2 as usize) {
                                 let w1 = cell2.get_water();
@@ -159,14 +456,14 @@ impl Terrain {
                                 if d > 0 && w0 > 0 {
                                     let over = clamp(d, 0, w0);
                                     let p = clamp(over as f64/5.0 as f64, 0.0, 1.0);
-                                    if thread_rng().gen_bool(p) {
+                                    if rng.gen_bool(p) {
                                         water0 += 1;
                                         water_buf[c2 as usize][r2 as usize] += 1;
                                     }
                                 }
                             } else {
                                 let msg = format!("cell not exist: (x: {} | y {})", c2, r2);
-                                warn!("{}", msg);
+                                eprintln!("{}", msg);
                             }
                         }
                     }
@@ -174,8 +471,8 @@ impl Terrain {
                 }
             }
         }
-        for c in 0..self.cells.len()-1 {
-            for r in 0..self.cells[c].len()-1 {
+        for c in 0..self.cells.len() {
+            for r in 0..self.cells[c].len() {
                 self.cells[c][r].set_water(water_buf[c][r]);
             }
         }
@@ -185,45 +482,156 @@ impl Terrain {
         return self.cells[x][y].get_altitude();
     }
 
-    pub fn get_color(&self, x: usize, y: usize) -> (Color, Option<Color>) {
-        return self.cells[x][y].get_colors();
+    pub fn get_biome(&self, x: usize, y: usize) -> Biome {
+        return self.cells[x][y].get_biome();
     }
 
-    pub fn draw(&self, show_occupied: bool, edit: bool) {
-        for c in 0..self.cells.len() {
-            for r in 0..self.cells[c].len() {
-                let (terrain, water) = self.get_color(c, r);
-                let x0 = c as f32 * self.cell_size;
-                let y0 = r as f32 * self.cell_size;
-                draw_rectangle(x0, y0, self.cell_size, self.cell_size, terrain);
-                match water {
-                    None => {},
-                    Some(water) => {
-                        draw_rectangle(x0, y0, self.cell_size, self.cell_size, water);
-                    },
+    #[doc = r"Cell containing world position. Positions outside terrain are wrapped in torus world or clamped to edge."]
+    pub fn cell_at(&self, position: &Vec2, wrap: bool) -> Option<&Cell> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let [x, y] = self.bound_coord(self.pos_to_coord(position), wrap);
+        return self.get_cell(x as usize, y as usize);
+    }
+
+    pub fn cell_at_mut(&mut self, position: &Vec2, wrap: bool) -> Option<&mut Cell> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let [x, y] = self.bound_coord(self.pos_to_coord(position), wrap);
+        return self.get_mut_cell(x as usize, y as usize);
+    }
+
+    pub fn biome_at(&self, position: &Vec2, wrap: bool) -> Biome {
+        return self.cell_at(position, wrap).map_or(Biome::default(), |cell| cell.get_biome());
+    }
+
+    pub fn water_at(&self, position: &Vec2, wrap: bool) -> i32 {
+        return self.cell_at(position, wrap).map_or(0, |cell| cell.get_water());
+    }
+
+    #[doc = r"Deposit nutrients (e.g. from decomposing body) in cell at position."]
+    pub fn add_nutrients(&mut self, position: &Vec2, amount: f32, wrap: bool) {
+        if let Some(cell) = self.cell_at_mut(position, wrap) {
+            let n = cell.get_nutrients();
+            cell.set_nutrients(n + amount);
+        }
+    }
+
+    #[doc = r"Take up to amount of nutrients from cell at position. Returns amount actually taken."]
+    pub fn take_nutrients(&mut self, position: &Vec2, amount: f32, wrap: bool) -> f32 {
+        match self.cell_at_mut(position, wrap) {
+            Some(cell) => {
+                let n = cell.get_nutrients();
+                let taken = amount.clamp(0.0, n);
+                cell.set_nutrients(n - taken);
+                return taken;
+            },
+            None => {
+                return 0.0;
+            },
+        }
+    }
+
+    #[doc = r"Leave scent of channel in cell at position."]
+    pub fn add_scent(&mut self, position: &Vec2, channel: usize, amount: f32, wrap: bool) {
+        if let Some(cell) = self.cell_at_mut(position, wrap) {
+            let s = cell.get_scent(channel);
+            cell.set_scent(channel, s + amount);
+        }
+    }
+
+    #[doc = r"Scent step lasting dt seconds: every channel spreads to four neighbouring cells and fades by scent_decay per second."]
+    pub fn update_scent(&mut self, dt: f32, settings: &Settings) {
+        let wrap = settings.topology == WorldTopology::TORUS;
+        let spread = clamp(settings.scent_diffusion * dt, 0.0, 1.0);
+        let keep = clamp(1.0 - settings.scent_decay * dt, 0.0, 1.0);
+        let mut scent: Vec<Vec<[f32; SCENT_CHANNELS]>> = vec![vec![[0.0; SCENT_CHANNELS]; self.height]; self.width];
+        for x in 0..self.width as i32 {
+            for y in 0..self.height as i32 {
+                let here = self.cells[x as usize][y as usize].scent;
+                let mut around = [0.0; SCENT_CHANNELS];
+                for [nx, ny] in [[x+1, y], [x-1, y], [x, y+1], [x, y-1]] {
+                    let [cx, cy] = self.bound_coord([nx, ny], wrap);
+                    let neighbour = self.cells[cx as usize][cy as usize].scent;
+                    for ch in 0..SCENT_CHANNELS {
+                        around[ch] += neighbour[ch] / 4.0;
+                    }
+                }
+                for ch in 0..SCENT_CHANNELS {
+                    let s = (here[ch] + spread * (around[ch] - here[ch])) * keep;
+                    scent[x as usize][y as usize][ch] = if s < 0.001 { 0.0 } else { s };
                 }
             }
         }
-        if show_occupied {
-            for occupied in self.occupied.iter() {
-                let x0 = occupied[0] as f32 * self.cell_size;
-                let y0 = occupied[1] as f32 * self.cell_size;
-                draw_rectangle_lines(x0, y0, self.cell_size, self.cell_size, 2.0, color_u8!(255, 0, 0, 255));
+        for (column, scent_column) in self.cells.iter_mut().zip(scent.iter()) {
+            for (cell, s) in column.iter_mut().zip(scent_column.iter()) {
+                cell.scent = *s;
             }
         }
-        if edit {
-            match self.cursor {
-                None => {},
-                Some(_coord) => {
-                    for (cell_loc, i) in self.brushed.iter() {
-                        let x = cell_loc[0] as f32 * self.cell_size;
-                        let y = cell_loc[1] as f32 * self.cell_size;
-                        let a = clamp((100.0+155.0*i) as u8, 0, 255) as u8;
-                        draw_rectangle_lines(x, y, self.cell_size, self.cell_size, 2.0, color_u8!(0, 0, 255, a));
-                    }
-                },
+    }
+
+    pub fn total_nutrients(&self) -> f32 {
+        return self.cells.iter().flatten().map(|cell| cell.get_nutrients()).sum();
+    }
+
+    #[doc = r"Chance (0.0-1.0) that plant takes root at position. Plants drown in deep water, need moisture within plant_moisture_range cells
+(otherwise plant_dry_chance is used) and grow worse at high altitude."]
+    pub fn plant_suitability(&self, position: &Vec2, settings: &Settings) -> f32 {
+        let wrap = settings.topology == WorldTopology::TORUS;
+        let cell = match self.cell_at(position, wrap) {
+            Some(cell) => cell,
+            None => return 1.0,
+        };
+        if cell.get_water() >= settings.plant_drown_depth {
+            return 0.0;
+        }
+        let [x, y] = self.bound_coord(self.pos_to_coord(position), wrap);
+        let range = settings.plant_moisture_range.max(0);
+        let mut moisture = false;
+        'search: for c in x-range..=x+range {
+            for r in y-range..=y+range {
+                if !wrap && (c < 0 || r < 0 || c >= self.width as i32 || r >= self.height as i32) { continue; }
+                let [c, r] = self.wrap_coord([c, r]);
+                if self.cells[c as usize][r as usize].get_water() > 0 {
+                    moisture = true;
+                    break 'search;
+                }
             }
         }
+        let chance = match moisture {
+            true => 1.0,
+            false => settings.plant_dry_chance.clamp(0.0, 1.0),
+        };
+        return chance * (1.0 - 0.5 * cell.get_altitude() as f32 / 100.0);
+    }
+
+    #[doc = r"Random position where plant takes root with chance given by plant_suitability. Best of tried positions is used if all of them failed."]
+    pub fn random_plant_position(&self, settings: &Settings, rng: &mut SimRng) -> Vec2 {
+        let mut best = (Vec2::ZERO, -1.0);
+        for _ in 0..20 {
+            let pos = random_position(settings.world_w as f32, settings.world_h as f32, rng);
+            let suitability = self.plant_suitability(&pos, settings);
+            if rng.gen::<f32>() < suitability {
+                return pos;
+            }
+            if suitability > best.1 {
+                best = (pos, suitability);
+            }
+        }
+        return best.0;
+    }
+
+    fn bound_coord(&self, coordinates: [i32; 2], wrap: bool) -> [i32; 2] {
+        return match wrap {
+            true => self.wrap_coord(coordinates),
+            false => [clamp(coordinates[0], 0, self.width as i32 - 1), clamp(coordinates[1], 0, self.height as i32 - 1)],
+        };
+    }
+
+    pub fn get_color(&self, x: usize, y: usize) -> (Color, Option<Color>) {
+        return self.cells[x][y].get_colors();
     }
 
     pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
@@ -273,24 +681,60 @@ impl Terrain {
     }
 
     pub fn pos_to_coord(&self, position: &Vec2) -> [i32; 2] {
-        let x = ((position.x/self.cell_size).floor()) as i32;
-        let y = ((position.y/self.cell_size).floor()) as i32;
+        return Self::pos_to_cell(position, self.cell_size);
+    }
+
+    #[doc = r"Coordinates of terrain cell of given size containing position."]
+    pub fn pos_to_cell(position: &Vec2, cell_size: f32) -> [i32; 2] {
+        let x = ((position.x/cell_size).floor()) as i32;
+        let y = ((position.y/cell_size).floor()) as i32;
         return [x, y];
     }
 
+    #[doc = r"Cell coordinates moved to opposite edge if they are outside terrain (for torus world)."]
+    pub fn wrap_coord(&self, coordinates: [i32; 2]) -> [i32; 2] {
+        let x = coordinates[0].rem_euclid((self.width as i32).max(1));
+        let y = coordinates[1].rem_euclid((self.height as i32).max(1));
+        return [x, y];
+    }
+
+    #[doc = r"Gradient of altitude at cell (difference of altitude per cell, pointing uphill).
+Neighbours outside terrain are clamped to edge or wrapped around in torus world."]
+    pub fn get_slope(&self, coordinates: [i32; 2], wrap: bool) -> Vec2 {
+        let alt = |x: i32, y: i32| -> f32 {
+            let [cx, cy] = self.bound_coord([x, y], wrap);
+            return self.get_cell(cx as usize, cy as usize).map_or(0.0, |cell| cell.get_altitude() as f32);
+        };
+        let [x, y] = coordinates;
+        let dx = (alt(x+1, y) - alt(x-1, y)) / 2.0;
+        let dy = (alt(x, y+1) - alt(x, y-1)) / 2.0;
+        return Vec2::new(dx, dy);
+    }
+
+    #[doc = r"Gradient of scent of channel at cell (pointing towards stronger scent). Neighbours are treated like in get_slope."]
+    pub fn get_scent_gradient(&self, coordinates: [i32; 2], channel: usize, wrap: bool) -> Vec2 {
+        let scent = |x: i32, y: i32| -> f32 {
+            let [cx, cy] = self.bound_coord([x, y], wrap);
+            return self.get_cell(cx as usize, cy as usize).map_or(0.0, |cell| cell.get_scent(channel));
+        };
+        let [x, y] = coordinates;
+        let dx = (scent(x+1, y) - scent(x-1, y)) / 2.0;
+        let dy = (scent(x, y+1) - scent(x, y-1)) / 2.0;
+        return Vec2::new(dx, dy);
+    }
+
     pub fn coord_to_pos(&self, coordinates: [i32; 2]) -> Vec2 {
         let x = coordinates[0] as f32 * self.cell_size;
         let y = coordinates[1] as f32 * self.cell_size;
         return vec2(x, y);
     }
 
-    fn generate_noise_map(w: usize, h: usize) -> NoiseMap {
-        let seed = generate_seed() as u32;
+    fn generate_noise_map(w: usize, h: usize, noise: &TerrainNoise, seed: u32) -> NoiseMap {
         let mut fbm = Fbm::<Perlin>::new(seed);
-        fbm.frequency = 0.6;
-        fbm.octaves = 4;
-        fbm.lacunarity = 0.8;
-        fbm.persistence = 0.8;
+        fbm.frequency = noise.frequency;
+        fbm.octaves = noise.octaves;
+        fbm.lacunarity = noise.lacunarity;
+        fbm.persistence = noise.persistence;
         
         return PlaneMapBuilder::new(&fbm)
             .set_size(w, h)
@@ -299,6 +743,59 @@ impl Terrain {
             .build();
     }
 
+    #[doc = r"Set altitude of all cells from heightmap rows (values 0-100), stretched to terrain size."]
+    pub fn apply_heightmap(&mut self, heightmap: &Vec<Vec<i32>>) {
+        let rows = heightmap.len();
+        if rows == 0 { return; }
+        for c in 0..self.width {
+            for r in 0..self.height {
+                let hr = r * rows / self.height;
+                let row = &heightmap[hr];
+                if row.is_empty() { continue; }
+                let hc = c * row.len() / self.width;
+                if let Some(cell) = self.get_mut_cell(c, r) {
+                    cell.set_altitude(row[hc]);
+                }
+            }
+        }
+    }
+
+    #[doc = r"Add water to cells in circle around world position. Amount fades out towards edge of circle."]
+    pub fn add_water_in_region(&mut self, center: Vec2, radius: f32, amount: i32) {
+        for (c, r, intens) in self.cells_in_region(center, radius) {
+            if let Some(cell) = self.get_mut_cell(c, r) {
+                let w = cell.get_water();
+                cell.set_water(w + (amount as f32*intens) as i32);
+            }
+        }
+    }
+
+    #[doc = r"Raise or lower cells in circle around world position. Amount fades out towards edge of circle."]
+    pub fn add_terrain_in_region(&mut self, center: Vec2, radius: f32, amount: i32) {
+        for (c, r, intens) in self.cells_in_region(center, radius) {
+            if let Some(cell) = self.get_mut_cell(c, r) {
+                let a = cell.get_altitude();
+                cell.set_altitude(a + (amount as f32*intens) as i32);
+            }
+        }
+    }
+
+    fn cells_in_region(&self, center: Vec2, radius: f32) -> Vec<(usize, usize, f32)> {
+        let mut cells: Vec<(usize, usize, f32)> = vec![];
+        let [x0, y0] = self.pos_to_coord(&(center - Vec2::splat(radius)));
+        let [x1, y1] = self.pos_to_coord(&(center + Vec2::splat(radius)));
+        for c in x0.max(0)..=x1.min(self.width as i32 - 1) {
+            for r in y0.max(0)..=y1.min(self.height as i32 - 1) {
+                let cell_center = self.coord_to_pos([c, r]) + Vec2::splat(self.cell_size/2.0);
+                let dist = center.distance(cell_center);
+                if dist <= radius {
+                    cells.push((c as usize, r as usize, 1.0 - dist/radius.max(f32::EPSILON)));
+                }
+            }
+        }
+        return cells;
+    }
+
     pub fn add_water_at_cursor(&mut self, amount: i32) {
         match self.cursor {
             None => {},
@@ -345,9 +842,22 @@ impl Terrain {
         }
     }
 
+    #[doc = r"Paint biome on cells under brush. None restores biomes derived from terrain."]
+    pub fn paint_biome_at_cursor(&mut self, biome: Option<Biome>) {
+        if self.cursor.is_none() { return; }
+        let brushed: Vec<IVec2> = self.brushed.iter().map(|(cell_loc, _)| *cell_loc).collect();
+        for cell_loc in brushed.iter() {
+            match self.get_mut_cell(cell_loc.x as usize, cell_loc.y as usize) {
+                None => {},
+                Some(cell) => {
+                    cell.set_biome(biome);
+                },
+            }
+        }
+    }
+
     fn collect_cells_under_brush(&mut self) {
         self.brushed.clear();
-        self.brush_size = get_settings().brush_size as u32;
         let s = self.brush_size;
         match self.cursor {
             None => {},
@@ -387,6 +897,10 @@ pub struct SerializedTerrain {
     columns_num: usize,
     rows_num: usize,
     cells: Vec<Vec<Cell>>,
+    #[serde(default)]
+    springs: Vec<Spring>,
+    #[serde(default)]
+    clouds: Vec<Cloud>,
 }
 
 impl SerializedTerrain {
@@ -397,6 +911,8 @@ impl SerializedTerrain {
             columns_num: terrain.width,
             rows_num: terrain.height,
             cells: terrain.cells.to_vec(),
+            springs: terrain.springs.to_vec(),
+            clouds: terrain.clouds.to_vec(),
         };
         return serialized_terrain;
     }
@@ -1,8 +1,9 @@
 #![allow(unused)]
 
-use macroquad::rand::*;
+use serde::{Serialize, Deserialize};
+use crate::rng::SimRng;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Timer {
     pub time: f32,
     pub duration: f32,
@@ -12,19 +13,21 @@ pub struct Timer {
 
 impl Timer {
 
-    pub fn new(duration: f32, repeat: bool, autostart: bool, random_start: bool) -> Self {
-        let mut start_time: f32 = 0.0;
-        if random_start {
-            start_time = gen_range(0.0, duration);
-            //start_time = gen_range::<f32>(0.0, duration);
-        }
+    pub fn new(duration: f32, repeat: bool, autostart: bool) -> Self {
         Self {
-            time: start_time,
+            time: 0.0,
             duration,
             repeat,
             run: autostart,
         }
     }
+
+    #[doc = r"Timer with random start time drawn from simulation rng."]
+    pub fn new_random(duration: f32, repeat: bool, autostart: bool, rng: &mut SimRng) -> Self {
+        let mut timer = Self::new(duration, repeat, autostart);
+        timer.time = rng.gen_range(0.0, duration);
+        return timer;
+    }
     pub fn update(&mut self, dt: f32) -> bool {
         if self.run {
             self.time += dt;
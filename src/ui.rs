@@ -17,24 +17,28 @@ use egui_macroquad::egui::{
 use macroquad::prelude::*;
 use macroquad::math::vec2;
 use base64::prelude::*;
-use crate::plant::Plant;
-use crate::util::*;
-use crate::agent::*;
-use crate::neuro::*;
-use crate::settings::*;
-use crate::statistics::*;
-use crate::signals::*;
-use crate::sketch::*;
-use crate::ranking::Ranking;
+use evolve_core::plant::PlantType;
+use evolve_core::util::*;
+use crate::gui_util::*;
+use evolve_core::agent::*;
+use evolve_core::neuro::*;
+use evolve_core::settings::*;
+use evolve_core::statistics::*;
+use evolve_core::signals::*;
+use evolve_core::sketch::*;
+use evolve_core::terrain::Biome;
+use evolve_core::ranking::Ranking;
+use evolve_core::genome::AgentGenome;
 
 
 struct TempValues {
     pub world_size: Option<macroquad::prelude::Vec2>,
+    pub genome_code: String,
 }
 
 impl Default for TempValues {
     fn default() -> Self {
-        Self { world_size: None }
+        Self { world_size: None, genome_code: String::new() }
     }
 }
 
@@ -132,22 +136,24 @@ impl UISystem {
         sim_name: String,
         sim_state: &SimState,
         signals: &mut Signals,
+        settings: &mut Settings,
+        mutations: &MutationStats,
         camera2d: &Camera2D,
         agent: Option<&Agent>,
-        plant: Option<&Plant>,
+        plant: Option<&dyn PlantType>,
         ranking: &Ranking,
         statistics: &Statistics,
         user_action: &mut UserAction
     ) {
-        self.timer += dt();
+        self.timer += get_frame_time();
         self.timer = self.timer%1.0;
         egui_macroquad::ui(|egui_ctx| {
             self.set_fonts_styles(egui_ctx);
             self.pointer_over = egui_ctx.is_pointer_over_area();
-            self.build_top_menu(egui_ctx, sim_name.as_str(), signals);
+            self.build_top_menu(egui_ctx, sim_name.as_str(), signals, settings);
             self.build_quit_window(egui_ctx);
-            self.build_debug_window(egui_ctx, camera2d, &sim_state, agent);
-            self.build_new_sim_window(egui_ctx, signals);
+            self.build_debug_window(egui_ctx, camera2d, &sim_state, agent, settings);
+            self.build_new_sim_window(egui_ctx, signals, settings);
             match agent {
                 Some(agent) => {
                     self.build_ancestors_window(egui_ctx, agent);
@@ -161,23 +167,24 @@ impl UISystem {
                 None => {},
             }
             self.build_about_window(egui_ctx);
-            self.build_terrain_editor_window(egui_ctx, user_action);
+            self.build_terrain_editor_window(egui_ctx, user_action, settings);
             self.build_doc_window(egui_ctx);
-            self.build_settings_enviro_window(egui_ctx, signals);
-            self.build_settings_agent_window(egui_ctx, signals);
-            self.build_load_sim_window(egui_ctx);
+            self.build_settings_enviro_window(egui_ctx, signals, settings);
+            self.build_settings_agent_window(egui_ctx, signals, settings);
+            self.build_load_sim_window(egui_ctx, signals);
             self.build_main_menu_win(egui_ctx);
-            self.build_load_agent_window(egui_ctx);
-            self.build_settings_neuro_window(egui_ctx, signals);
-            self.build_info_window(egui_ctx);
-            self.build_resize_world_window(egui_ctx);
-            self.build_left_panel(egui_ctx, &sim_state, agent, ranking, statistics);
+            self.build_load_agent_window(egui_ctx, signals);
+            self.build_load_scenario_window(egui_ctx, signals);
+            self.build_settings_neuro_window(egui_ctx, signals, settings);
+            self.build_info_window(egui_ctx, mutations);
+            self.build_resize_world_window(egui_ctx, signals);
+            self.build_left_panel(egui_ctx, &sim_state, agent, ranking, statistics, settings);
             self.build_right_panel(egui_ctx, agent, statistics, ranking);
             self.build_rename_window(egui_ctx, signals);
         });
     }
 
-    fn build_top_menu(&mut self, egui_ctx: &Context, sim_name: &str, signals: &mut Signals) {
+    fn build_top_menu(&mut self, egui_ctx: &Context, sim_name: &str, signals: &mut Signals, settings: &mut Settings) {
         TopBottomPanel::top("top_panel").default_height(100.0).show(egui_ctx, |ui| {
             if !self.pointer_over {
                 self.pointer_over = ui.ui_contains_pointer();
@@ -198,6 +205,9 @@ impl UISystem {
                     if ui.button(RichText::new("Load Simulation").strong().color(Color32::WHITE)).clicked() {
                         self.state.load_sim = true;
                     }
+                    if ui.button(RichText::new("Load Scenario").strong().color(Color32::WHITE)).clicked() {
+                        self.state.load_scenario = true;
+                    }
                     if ui.button(RichText::new("Save Simulation").weak().color(Color32::WHITE)).clicked() {
                         signals.save_sim = true;
                     }
@@ -220,7 +230,6 @@ impl UISystem {
                     }
                     if ui.button(RichText::new("Resize World").strong().color(Color32::WHITE),).clicked() {
                         if !self.state.resize_world {
-                            let settings = get_settings();
                             self.temp_values.world_size = Some(macroquad::prelude::Vec2::new(settings.world_w as f32, settings.world_h as f32));
                         }
                         self.state.resize_world = !self.state.resize_world;
@@ -234,7 +243,7 @@ impl UISystem {
                 ui.add_space(10.0);
                 ui.separator();
                 ui.add_space(10.0);
-                let speed = sim_speed();
+                let speed = settings.sim_speed;
                 let accel_label = format!("SPEED [+]");
                 let mut deccel_color = Color32::YELLOW;
                 let mut deccel_label = format!("SPEED [-]");
@@ -244,33 +253,25 @@ impl UISystem {
                 }
                 let mut pause_color = Color32::YELLOW;
                 let mut pause_label = format!("Pause");
-                if get_settings().pause {
+                if settings.pause {
                     pause_color = Color32::GREEN;
                     pause_label = format!("Run");
                 }
 
                 menu::menu_button(ui, RichText::new("SIMULATE").strong(), |ui| {
                     if ui.button(RichText::new("Normal").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.sim_speed = 1.0;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new(accel_label).strong().color(Color32::LIGHT_BLUE)).clicked() {
-                        let mut settings = get_settings();
                         settings.sim_speed += 1.0;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new(deccel_label).strong().color(deccel_color)).clicked() {
-                        if sim_speed() > 1.0 {
-                            let mut settings = get_settings();
+                        if settings.sim_speed > 1.0 {
                             settings.sim_speed -= 1.0;
-                            set_settings(settings);
                         }
                     }
                     if ui.button(RichText::new(pause_label).strong().color(pause_color)).clicked() {
-                            let mut settings = get_settings();
                             settings.pause = !settings.pause;
-                            set_settings(settings);
                     }
                 });
 
@@ -282,29 +283,19 @@ impl UISystem {
                 
                 menu::menu_button(ui, RichText::new("SELECTION").strong(), |ui| {
                     if ui.button(RichText::new("Random").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.select_mode = SelectMode::RANDOM;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Points").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.select_mode = SelectMode::POINTS;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Lifetime").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.select_mode = SelectMode::LIFETIME;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Childs").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.select_mode = SelectMode::CHILDS;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Kills").strong().color(Color32::GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.select_mode = SelectMode::KILLS;
-                        set_settings(settings);
                     }
                 });
 
@@ -331,24 +322,16 @@ impl UISystem {
 
                 menu::menu_button(ui, RichText::new("CAMERA").strong(), |ui| {
                     if ui.button(RichText::new("Follow Mode").strong().color(Color32::GOLD)).clicked() {
-                        let mut settings = get_settings();
                         settings.follow_mode = !settings.follow_mode;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Show Name").strong().color(Color32::GOLD)).clicked() {
-                        let mut settings = get_settings();
                         settings.show_specie = !settings.show_specie;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Show Generation").strong().color(Color32::GOLD)).clicked() {
-                        let mut settings = get_settings();
                         settings.show_generation = !settings.show_generation;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Show Energy Bar").strong().color(Color32::GOLD)).clicked() {
-                        let mut settings = get_settings();
                         settings.agent_eng_bar = !settings.agent_eng_bar;
-                        set_settings(settings);
                     }
                 });
 
@@ -388,9 +371,7 @@ impl UISystem {
                         self.state.inspect = !self.state.inspect;
                     }
                     if ui.button(RichText::new("Neural Network").strong().color(Color32::LIGHT_GREEN)).clicked() {
-                        let mut settings = get_settings();
                         settings.show_network = !settings.show_network;
-                        set_settings(settings);
                     }
                     if ui.button(RichText::new("Ranking").strong().color(Color32::LIGHT_GREEN)).clicked() {
                         self.state.ranking = !self.state.ranking;
@@ -415,9 +396,7 @@ impl UISystem {
                         self.state.info = !self.state.info;
                     }
                     if ui.button(RichText::new("Physic Debuger").strong().color(Color32::LIGHT_BLUE)).clicked() {
-                        let mut settings = get_settings();
                         settings.debug = ! settings.debug;
-                        set_settings(settings);
                     }
                 });
 
@@ -428,15 +407,11 @@ impl UISystem {
                 menu::menu_button(ui, RichText::new("TERRAIN").strong(), |ui| {
                     if ui.button(RichText::new("Show Tools").strong().color(Color32::WHITE)).clicked() {
                         self.state.terrain_tools = !self.state.terrain_tools;
-                        let mut settings = get_settings();
                         settings.terrain_edit = !settings.terrain_edit;
-                        set_settings(settings);
 
                     }
                     if ui.button(RichText::new("Update").strong().color(Color32::WHITE)).clicked() {
-                        let mut signals = get_signals();
                         signals.update_terrain = !signals.update_terrain;
-                        set_signals(signals);
                     }
                 });
 
@@ -518,9 +493,8 @@ impl UISystem {
         }
     }
 
-    fn build_load_sim_window(&mut self, egui_ctx: &Context) {
+    fn build_load_sim_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
         if self.state.load_sim {
-            let mut signals = get_signals();
             let mut saved_sims: Vec<String> = vec![];
             let path = Path::new("saves\\simulations\\");
             let sims =  fs::read_dir(path).unwrap();
@@ -541,13 +515,11 @@ impl UISystem {
                             columns[1].horizontal(|col| {
                                     if col.button(RichText::new("[LOAD]").strong().color(Color32::GREEN)).clicked()  {
                                         signals.load_sim_name = Some(String::from(&sim));
-                                        set_signals(signals.clone());
                                         self.state.load_sim = false;
                                     }
                                     col.separator();
                                     if col.button(RichText::new("[DEL]").strong().color(Color32::RED)).clicked()  {
                                         signals.del_sim_name = Some(String::from(&sim));
-                                        set_signals(signals.clone());
                                         self.state.load_sim = false;
                                     }
                             })
@@ -566,9 +538,54 @@ impl UISystem {
         }
     }
 
-    fn build_load_agent_window(&mut self, egui_ctx: &Context) {
+    fn build_load_scenario_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
+        if self.state.load_scenario {
+            let mut scenarios: Vec<PathBuf> = vec![];
+            match fs::read_dir(Path::new("saves/scenarios/")) {
+                Ok(entries) => {
+                    for entry in entries {
+                        if let Ok(file) = entry {
+                            let path = file.path();
+                            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                                scenarios.push(path);
+                            }
+                        }
+                    }
+                },
+                Err(_) => {},
+            }
+            scenarios.sort();
+            Window::new("LOAD SCENARIO").default_pos((SCREEN_WIDTH / 2.0 - 65.0, SCREEN_HEIGHT / 4.0)).default_width(260.0).show(egui_ctx, |ui| {
+                if scenarios.is_empty() {
+                    ui.label(RichText::new("no scenarios in saves/scenarios/").color(Color32::GRAY));
+                }
+                for path in scenarios {
+                    let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+                    ui.vertical_centered(|row| {
+                        row.columns(2, |columns| {
+                            columns[0].label(RichText::new(name.to_uppercase()).strong().color(Color32::WHITE));
+                            columns[1].horizontal(|col| {
+                                if col.button(RichText::new("[LOAD]").strong().color(Color32::GREEN)).clicked()  {
+                                    signals.load_scenario_name = Some(path.to_str().unwrap().to_owned());
+                                    self.state.load_scenario = false;
+                                }
+                            })
+                        })
+                    });
+                    ui.add_space(4.0);
+                }
+                ui.add_space(16.0);
+                ui.vertical_centered(|ctn| {
+                    if ctn.button(RichText::new("CLOSE").strong().color(Color32::YELLOW)).clicked() {
+                        self.state.load_scenario = false;
+                    }
+                })
+            });
+        }
+    }
+
+    fn build_load_agent_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
         if self.state.load_agent {
-            let mut signals = get_signals();
             let mut saved_agents: Vec<String> = vec![];
             let path = Path::new("saves\\agents\\");
             let agents =  fs::read_dir(path).unwrap();
@@ -634,18 +651,23 @@ impl UISystem {
                             columns[1].horizontal(|col| {
                                 if col.button(RichText::new("[LOAD]").strong().color(Color32::GREEN)).clicked()  {
                                     signals.load_agent_name = Some(filename.clone());
-                                    set_signals(signals.clone());
                                 }
                                 col.separator();
                                 if col.button(RichText::new("[DEL]").strong().color(Color32::RED)).clicked()  {
                                     signals.del_agent_name = Some(String::from(filename.clone()));
-                                    set_signals(signals.clone());
                                 }
                             });
                         });
                     });
                     ui.add_space(4.0);
                 }
+                ui.add_space(8.0);
+                ui.horizontal(|row| {
+                    row.text_edit_singleline(&mut self.temp_values.genome_code);
+                    if row.button(RichText::new("[IMPORT GENOME]").strong().color(Color32::GREEN)).clicked() && !self.temp_values.genome_code.trim().is_empty() {
+                        signals.import_genome = Some(std::mem::take(&mut self.temp_values.genome_code));
+                    }
+                });
                 ui.add_space(16.0);
                 
                 ui.vertical_centered(|ctn| {
@@ -657,7 +679,7 @@ impl UISystem {
         }
     }
 
-    fn build_debug_window(&self, egui_ctx: &Context, camera2d: &Camera2D, sim_state: &SimState, agent: Option<&Agent>) {
+    fn build_debug_window(&self, egui_ctx: &Context, camera2d: &Camera2D, sim_state: &SimState, agent: Option<&Agent>, settings: &Settings) {
         if self.state.dbg {
             let (mouse_x, mouse_y) = mouse_position();
             Window::new("DEBUG INFO").default_pos((375.0, 5.0)).default_width(175.0).show(egui_ctx, |ui| {
@@ -710,11 +732,8 @@ impl UISystem {
                         }
                     },
                 }
-                let factor = get_settings().rare_specie_mod;
-                let r1 = specie_mod_rate(factor, 1);
-                let r5 = specie_mod_rate(factor, 5);
                 ui.separator();
-                ui.label(format!("mod spec: 1log: {} | 5log: {}", r1, r5));
+                ui.label(format!("species: {} | threshold: {:.2}", sim_state.species_num, settings.compat_threshold));
 
             });
         }
@@ -795,7 +814,7 @@ impl UISystem {
         }
     }
 
-    fn build_new_sim_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
+    fn build_new_sim_window(&mut self, egui_ctx: &Context, signals: &mut Signals, settings: &mut Settings) {
         if self.state.new_sim {
             let names0 = vec![
                 "NEW", "IDEAL", "DANGER", "DARK", "FIRST", "EXPERIMENTAL", 
@@ -809,7 +828,6 @@ impl UISystem {
                 "SYSTEM", "TERRAIN", "GLOBE", "REALM", "COSMOS"
             ];
 
-            let mut settings = get_settings();
             let w = 500.0; let h = 220.0;
             Window::new("EVOLVE").default_pos((SCREEN_WIDTH / 2.0 - w/2.0, 100.0)).default_size([w, h]).show(egui_ctx, |ui| {
                 let big_logo = self.big_logo.clone().unwrap();
@@ -873,14 +891,22 @@ impl UISystem {
                     row.columns(2, |columns| {
                         if columns[0].add(Slider::new(&mut w, 800..=10000).step_by(100.0)).changed() {
                             settings.world_w = w;
-                            set_settings(settings.clone());
                         }
                         if columns[1].add(Slider::new(&mut h, 600..=7500).step_by(100.0)).changed() {
                             settings.world_h = h;
-                            set_settings(settings.clone());
                         }
                     });
                 });
+                ui.add_space(2.0);
+                ui.vertical_centered(|row| {
+                    let mut torus = settings.topology == WorldTopology::TORUS;
+                    if row.checkbox(&mut torus, "TORUS WORLD (WRAP AROUND EDGES)").changed() {
+                        settings.topology = match torus {
+                            true => WorldTopology::TORUS,
+                            false => WorldTopology::BOUNDED,
+                        };
+                    }
+                });
                 ui.add_space(4.0);
                 ui.spacing();
                 ui.vertical_centered(|mid| {
@@ -902,7 +928,7 @@ impl UISystem {
         }
     }
 
-    fn build_resize_world_window(&mut self, egui_ctx: &Context) {
+    fn build_resize_world_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
         if self.state.resize_world {
             let win_w = 500.0; let win_h = 220.0;
             let xy = self.temp_values.world_size.unwrap_or(vec2(1800.0, 900.0));
@@ -937,15 +963,11 @@ impl UISystem {
                         if columns[0].button(RichText::new("CANCEL").color(Color32::YELLOW).strong()).clicked() {
                             self.state.resize_world = false;
                             self.temp_values.world_size = None;
-                            let mut signals = get_signals();
                             signals.resize_world = None;
-                            set_signals(signals);
                         }
                         if columns[1].button(RichText::new("APPLY").color(Color32::BLUE).strong()).clicked() {
                             self.state.resize_world = false;
-                            let mut signals = get_signals();
                             signals.resize_world = self.temp_values.world_size;
-                            set_signals(signals);
                         }
                     });
                 });
@@ -964,22 +986,41 @@ impl UISystem {
                         row.label(RichText::new(format!("{} | G:{} | T:{}", name.to_uppercase(), gen, time)).strong().color(Color32::WHITE));
                     });
                 }
+                let mates = agent.mates();
+                if !mates.is_empty() {
+                    ui.separator();
+                    ui.label(RichText::new("MATES").strong().color(Color32::LIGHT_BLUE));
+                    for m in mates.iter() {
+                        let (name, gen, time) = m.get_name_gen_time();
+                        ui.horizontal(|row| {
+                            row.label(RichText::new(format!("{} | G:{} | T:{}", name.to_uppercase(), gen, time)).strong().color(Color32::LIGHT_BLUE));
+                        });
+                    }
+                }
             });
         }
     }
 
-    fn build_plant_window(&self, egui_ctx: &Context, plant: &Plant) {
+    fn build_plant_window(&self, egui_ctx: &Context, plant: &dyn PlantType) {
         if self.state.plants {
+            let kind = plant.get_kind();
+            let toxicity = plant.get_toxicity();
+            let plant = plant.get_base();
             let size = plant.size as i32;
             let max_eng = plant.max_eng;
             let eng = plant.eng;
             let lifetime = plant.time;
-            let attributes = format!("ENG: {:.0}/{:.0} | SIZE: {} | LIFETIME: {:.0}",eng, max_eng, size, lifetime);
+            let attributes = format!("{} | ENG: {:.0}/{:.0} | SIZE: {} | LIFETIME: {:.0} | GEN: {} | POISON: {:.2}", kind.name(), eng, max_eng, size, lifetime, plant.generation, toxicity);
+            let genome = &plant.genome;
+            let genes = format!("MAX SIZE: {:.1} | GROWTH: {:.2} | LIFE: {:.0} | CLONE DIST: {:.0} | DENSITY: {:.1} | TOXIC: {:.2}", genome.max_size, genome.growth, genome.lifetime, genome.clone_distance, genome.energy_density, genome.toxicity);
             let title_txt = format!("Plants"); 
             Window::new(RichText::new(title_txt).strong().color(Color32::GREEN)).default_pos((800.0, 0.0)).min_width(100.0).show(egui_ctx, |ui| {
                 ui.horizontal(|row| {
                     row.label(RichText::new(attributes).strong());
                 });
+                ui.horizontal(|row| {
+                    row.label(RichText::new(genes).color(Color32::LIGHT_GREEN));
+                });
             });
         }
     }
@@ -1014,7 +1055,7 @@ impl UISystem {
         }
     }
 
-    fn build_terrain_editor_window(&mut self, egui_ctx: &Context, user_action: &mut UserAction) {
+    fn build_terrain_editor_window(&mut self, egui_ctx: &Context, user_action: &mut UserAction, settings: &mut Settings) {
         if self.state.terrain_tools {
             
             let terrain_down = self.terrain_down.clone().unwrap();
@@ -1075,12 +1116,49 @@ impl UISystem {
                             },
                         }
                     }
+                    let spring_add = matches!(user_action, UserAction::SpringAdd);
+                    if ui.add_sized(UIVec2::new(64.0, 38.0), widgets::SelectableLabel::new(spring_add, "SPRING")).on_hover_text("left click to add or remove spring, right click to add or remove sink").clicked() {
+                        match user_action {
+                            UserAction::SpringAdd => {
+                                *user_action = UserAction::Idle;
+                            },
+                            _ => {
+                                *user_action = UserAction::SpringAdd;
+                            },
+                        }
+                    }
+                    let wall_add = matches!(user_action, UserAction::WallAdd);
+                    if ui.add_sized(UIVec2::new(64.0, 38.0), widgets::SelectableLabel::new(wall_add, "WALL")).on_hover_text("drag with left button to build wall, right click to remove it").clicked() {
+                        match user_action {
+                            UserAction::WallAdd => {
+                                *user_action = UserAction::Idle;
+                            },
+                            _ => {
+                                *user_action = UserAction::WallAdd;
+                            },
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for biome in Biome::ALL.iter() {
+                        let painting = matches!(user_action, UserAction::BiomePaint(b) if b == biome);
+                        let color = biome.color();
+                        let text = RichText::new(biome.name()).color(Color32::from_rgb((color.r*255.0) as u8, (color.g*255.0) as u8, (color.b*255.0) as u8));
+                        if ui.add(widgets::SelectableLabel::new(painting, text)).on_hover_text("paint biome with left button, restore biome derived from terrain with right button").clicked() {
+                            match painting {
+                                true => {
+                                    *user_action = UserAction::Idle;
+                                },
+                                false => {
+                                    *user_action = UserAction::BiomePaint(*biome);
+                                },
+                            }
+                        }
+                    }
                 });
                 ui.horizontal(|ui| {
-                    let mut settings = get_settings();
                     ui.label(RichText::new("Brush Size"));
                     ui.add(widgets::DragValue::new(&mut settings.brush_size).clamp_range(1..=10).speed(1));
-                    set_settings(settings);
                 });
             });
         } else {
@@ -1096,15 +1174,15 @@ impl UISystem {
 
 
                 Environment
-                Environment is a grid structure reflecting terrain height and water level. Terrain height is created based on perlin noise. Water level is dynamically updated between single grid cells. 
+                Environment is a grid structure reflecting terrain height and water level. Terrain height is created based on perlin noise. Water level is dynamically updated between single grid cells. Water evaporates (faster in summer and at high altitude), falls with rain from drifting clouds, flows out of springs and disappears in sinks. Every cell belongs to biome (desert, grassland, forest or swamp) derived from altitude and water or painted in terrain editor. Biome changes plant growth and density and energy cost of agents movement. Dead agents leave carcasses which may be eaten by scavengers (sensed by MEA input) and slowly decay. Bodies of dead agents and plants decompose into nutrients of cell, which feed growing plants. Agents may leave scent of three channels in cells they walk through. Scent spreads to neighbouring cells and fades away, so it can mark trails, territories or danger. 
 
 
                 Plants
-                Semi-active agents without neural network, but with evolution based physical attributes. They can grow, dye, multiply, inherit attributes, and evolve.
+                Semi-active agents without neural network, but with evolution based physical attributes. They can grow, dye, multiply, inherit attributes, and evolve. There are three species: fast growing grass, slow nutritious bushes which regrow after being eaten and toxic plants which poison eaters.
 
 
                 Agents
-                The crucial elements of simulation. Agents have physical attributes and neural networks based on genetic algorithm. They can grow, dye, multiply, inherit attributes, and evolve.
+                The crucial elements of simulation. Agents have physical attributes and neural networks based on genetic algorithm. They can grow, dye, multiply, inherit attributes, and evolve. Agents may also make sounds heard by others within hearing range (gene H), no matter whether they see each other.
             ";
             Window::new("Information").resizable(false).default_pos((SCREEN_WIDTH/2.-150., SCREEN_HEIGHT/6.)).min_height(380.).min_width(400.)
             .title_bar(true).show(egui_ctx, |ui| {
@@ -1131,10 +1209,9 @@ impl UISystem {
         }
     }
 
-    fn build_info_window(&mut self, egui_ctx: &Context) {
+    fn build_info_window(&mut self, egui_ctx: &Context, mutations: &MutationStats) {
         if self.state.info {
-            let mutations = get_mutations();
-            let na = mutations.nodes_added; let nd = mutations.nodes_deleted; let la = mutations.links_added; let ld = mutations.links_deleted;
+                        let na = mutations.nodes_added; let nd = mutations.nodes_deleted; let la = mutations.links_added; let ld = mutations.links_deleted;
             let w = mutations.weights_changed; let b = mutations.biases_changed;
             let text = format!("NODES: [added: {na} | del: {nd}] LINKS: [added: {la} | del: {ld}] MOD: [w: {w} | b: {b}]");
             Window::new("INFO").resizable(false).default_pos((SCREEN_WIDTH/2.-150., SCREEN_HEIGHT/3.)).min_height(380.).min_width(300.)
@@ -1152,11 +1229,10 @@ impl UISystem {
         }
     }
 
-    fn build_settings_agent_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
+    fn build_settings_agent_window(&mut self, egui_ctx: &Context, signals: &mut Signals, settings: &mut Settings) {
         if !self.state.set_agent {
             return;
         }
-        let mut settings = get_settings();
         Window::new("AGENT SETTINGS").id("agent_settings_win".into()).default_pos((SCREEN_WIDTH/2., SCREEN_HEIGHT/2.)).fixed_size([380., 400.])
         .title_bar(true).show(egui_ctx, |ui| {
             ui.heading("AGENT SETTINGS");
@@ -1270,6 +1346,16 @@ impl UISystem {
                     signals.new_settings = true;
                 }
             });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut agent_hearing_range: i32 = settings.agent_hearing_range as i32;
+                column[0].label(RichText::new("HEARING").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut agent_hearing_range, 0..=1000)).changed() {
+                    settings.agent_hearing_range = agent_hearing_range as f32;
+                    signals.new_settings = true;
+                }
+            });
             ui.columns(2, |column| {
                 column[0].set_max_size(UIVec2::new(80., 75.));
                 column[1].set_max_size(UIVec2::new(280., 75.));
@@ -1330,10 +1416,30 @@ impl UISystem {
             ui.columns(2, |column| {
                 column[0].set_max_size(UIVec2::new(80., 75.));
                 column[1].set_max_size(UIVec2::new(280., 75.));
-                let mut rare_specie_mod = settings.rare_specie_mod;
-                column[0].label(RichText::new("SPECIE MODIFY RARITY").color(Color32::WHITE).strong());
-                if column[1].add(Slider::new(&mut rare_specie_mod, 1..=20).step_by(1.0)).changed() {
-                    settings.rare_specie_mod = rare_specie_mod;
+                let mut compat_threshold = settings.compat_threshold;
+                column[0].label(RichText::new("SPECIE THRESHOLD").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut compat_threshold, 0.05..=2.0).step_by(0.05)).changed() {
+                    settings.compat_threshold = compat_threshold;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut compat_disjoint = settings.compat_disjoint;
+                column[0].label(RichText::new("DISJOINT GENES WEIGHT").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut compat_disjoint, 0.0..=3.0).step_by(0.1)).changed() {
+                    settings.compat_disjoint = compat_disjoint;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut compat_weight = settings.compat_weight;
+                column[0].label(RichText::new("WEIGHT DIFF WEIGHT").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut compat_weight, 0.0..=3.0).step_by(0.1)).changed() {
+                    settings.compat_weight = compat_weight;
                     signals.new_settings = true;
                 }
             });
@@ -1377,23 +1483,43 @@ impl UISystem {
                     signals.new_settings = true;
                 }
             });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut sexual = settings.reproduction == ReproductionMode::SEXUAL;
+                column[0].label(RichText::new("SEXUAL REPRODUCTION").color(Color32::WHITE).strong());
+                if column[1].add(Checkbox::without_text(&mut sexual)).changed() {
+                    settings.reproduction = match sexual {
+                        true => ReproductionMode::SEXUAL,
+                        false => ReproductionMode::ASEXUAL,
+                    };
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut mating_distance = settings.mating_distance;
+                column[0].label(RichText::new("MATING DISTANCE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut mating_distance, 0.0..=2.0).step_by(0.05)).changed() {
+                    settings.mating_distance = mating_distance;
+                    signals.new_settings = true;
+                }
+            });
             ui.add_space(2.0);
             ui.style_mut().visuals.widgets.inactive.bg_stroke = Stroke::new(2.0, Color32::DARK_GREEN);
             ui.vertical_centered(|closer| {
                 if closer.button(RichText::new("CLOSE").color(Color32::GREEN).strong()).clicked() {
                     self.state.set_agent = false;
-                    set_settings(settings.clone());
                 }
             });
         });
-        set_settings(settings.clone());
     }
 
-    fn build_settings_enviro_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
+    fn build_settings_enviro_window(&mut self, egui_ctx: &Context, signals: &mut Signals, settings: &mut Settings) {
         if !self.state.environment {
             return;
         }
-        let mut settings = get_settings();
         Window::new("ENVIROMENT SETTINGS").id("enviroment_settings_win".into()).default_pos((SCREEN_WIDTH/2., SCREEN_HEIGHT/2.)).fixed_size([380., 400.])
         .title_bar(true).show(egui_ctx, |ui| {
             ui.columns(2, |column| {
@@ -1486,6 +1612,266 @@ impl UISystem {
                     signals.new_settings = true;
                 }
             });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut season_length = settings.season_length;
+                column[0].label(RichText::new("SEASON LENGTH").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut season_length, 0.0..=3600.0).step_by(10.0)).changed() {
+                    settings.season_length = season_length;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut season_amplitude = settings.season_amplitude;
+                column[0].label(RichText::new("SEASON AMPLITUDE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut season_amplitude, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.season_amplitude = season_amplitude;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut day_length = settings.day_length;
+                column[0].label(RichText::new("DAY LENGTH").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut day_length, 0.0..=600.0).step_by(10.0)).changed() {
+                    settings.day_length = day_length;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut day_amplitude = settings.day_amplitude;
+                column[0].label(RichText::new("DAY AMPLITUDE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut day_amplitude, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.day_amplitude = day_amplitude;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut slope_speed = settings.slope_speed;
+                column[0].label(RichText::new("SLOPE SLOWDOWN").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut slope_speed, 0.0..=1.0).step_by(0.01)).changed() {
+                    settings.slope_speed = slope_speed;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut climb_energy_cost = settings.climb_energy_cost;
+                column[0].label(RichText::new("CLIMB ENERGY COST").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut climb_energy_cost, 0.0..=1.0).step_by(0.01)).changed() {
+                    settings.climb_energy_cost = climb_energy_cost;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut plant_moisture_range = settings.plant_moisture_range;
+                column[0].label(RichText::new("PLANT MOISTURE RANGE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new::<i32>(&mut plant_moisture_range, 0..=10).step_by(1.0)).changed() {
+                    settings.plant_moisture_range = plant_moisture_range;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut plant_drown_depth = settings.plant_drown_depth;
+                column[0].label(RichText::new("PLANT DROWN DEPTH").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new::<i32>(&mut plant_drown_depth, 1..=100).step_by(1.0)).changed() {
+                    settings.plant_drown_depth = plant_drown_depth;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut plant_dry_chance = settings.plant_dry_chance;
+                column[0].label(RichText::new("PLANT DRY CHANCE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut plant_dry_chance, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.plant_dry_chance = plant_dry_chance;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut evaporation = settings.evaporation;
+                column[0].label(RichText::new("EVAPORATION").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut evaporation, 0.0..=1.0).step_by(0.01)).changed() {
+                    settings.evaporation = evaporation;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut rain_chance = settings.rain_chance;
+                column[0].label(RichText::new("RAIN CHANCE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut rain_chance, 0.0..=0.2).step_by(0.005)).changed() {
+                    settings.rain_chance = rain_chance;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut rain_radius = settings.rain_radius;
+                column[0].label(RichText::new("RAIN RADIUS").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut rain_radius, 0.0..=1000.0).step_by(10.0)).changed() {
+                    settings.rain_radius = rain_radius;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut rain_intensity = settings.rain_intensity;
+                column[0].label(RichText::new("RAIN INTENSITY").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut rain_intensity, 0.0..=10.0).step_by(0.1)).changed() {
+                    settings.rain_intensity = rain_intensity;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut rain_duration = settings.rain_duration;
+                column[0].label(RichText::new("RAIN DURATION").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut rain_duration, 1.0..=120.0).step_by(1.0)).changed() {
+                    settings.rain_duration = rain_duration;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut decomposition = settings.decomposition;
+                column[0].label(RichText::new("DECOMPOSITION").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut decomposition, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.decomposition = decomposition;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut nutrient_uptake = settings.nutrient_uptake;
+                column[0].label(RichText::new("NUTRIENT UPTAKE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut nutrient_uptake, 0.0..=10.0).step_by(0.1)).changed() {
+                    settings.nutrient_uptake = nutrient_uptake;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut carcass_share = settings.carcass_share;
+                column[0].label(RichText::new("CARCASS SHARE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut carcass_share, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.carcass_share = carcass_share;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut body_eng = settings.body_eng;
+                column[0].label(RichText::new("BODY ENERGY").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut body_eng, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.body_eng = body_eng;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut carcass_lifetime = settings.carcass_lifetime;
+                column[0].label(RichText::new("CARCASS LIFETIME").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut carcass_lifetime, 1.0..=300.0).step_by(1.0)).changed() {
+                    settings.carcass_lifetime = carcass_lifetime;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut plant_mutations = settings.plant_mutations;
+                column[0].label(RichText::new("PLANT MUTATIONS").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut plant_mutations, 0.0..=1.0).step_by(0.01)).changed() {
+                    settings.plant_mutations = plant_mutations;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut grass_share = settings.grass_share;
+                column[0].label(RichText::new("GRASS SHARE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut grass_share, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.grass_share = grass_share;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut bush_share = settings.bush_share;
+                column[0].label(RichText::new("BUSH SHARE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut bush_share, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.bush_share = bush_share;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut toxic_share = settings.toxic_share;
+                column[0].label(RichText::new("TOXIC SHARE").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut toxic_share, 0.0..=1.0).step_by(0.05)).changed() {
+                    settings.toxic_share = toxic_share;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut scent_decay = settings.scent_decay;
+                column[0].label(RichText::new("SCENT DECAY").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut scent_decay, 0.0..=2.0).step_by(0.01)).changed() {
+                    settings.scent_decay = scent_decay;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut scent_diffusion = settings.scent_diffusion;
+                column[0].label(RichText::new("SCENT DIFFUSION").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut scent_diffusion, 0.0..=5.0).step_by(0.05)).changed() {
+                    settings.scent_diffusion = scent_diffusion;
+                    signals.new_settings = true;
+                }
+            });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(80., 75.));
+                column[1].set_max_size(UIVec2::new(280., 75.));
+                let mut scent_emission = settings.scent_emission;
+                column[0].label(RichText::new("SCENT EMISSION").color(Color32::WHITE).strong());
+                if column[1].add(Slider::new(&mut scent_emission, 0.0..=50.0).step_by(0.5)).changed() {
+                    settings.scent_emission = scent_emission;
+                    signals.new_settings = true;
+                }
+            });
             ui.columns(2, |column| {
                 column[0].set_max_size(UIVec2::new(80., 75.));
                 column[1].set_max_size(UIVec2::new(280., 75.));
@@ -1545,6 +1931,15 @@ impl UISystem {
                     settings.show_plant_rad = show_res_rad;
                 }
             });
+            ui.columns(2, |column| {
+                column[0].set_max_size(UIVec2::new(120., 75.));
+                column[1].set_max_size(UIVec2::new(120., 75.));
+                let mut show_scent: bool = settings.show_scent;
+                column[0].label(RichText::new("SHOW SCENT").color(Color32::WHITE).strong());
+                if column[1].add(Checkbox::without_text(&mut show_scent)).changed() {
+                    settings.show_scent = show_scent;
+                }
+            });
             ui.columns(2, |column| {
                 column[0].set_max_size(UIVec2::new(80., 75.));
                 column[1].set_max_size(UIVec2::new(280., 75.));
@@ -1560,18 +1955,15 @@ impl UISystem {
             ui.vertical_centered(|closer| {
                 if closer.button(RichText::new("CLOSE").color(Color32::GREEN).strong()).clicked() {
                     self.state.environment = false;
-                    set_settings(settings.clone());
                 }
             });
         });
-        set_settings(settings.clone());
     }
 
-    fn build_settings_neuro_window(&mut self, egui_ctx: &Context, signals: &mut Signals) {
+    fn build_settings_neuro_window(&mut self, egui_ctx: &Context, signals: &mut Signals, settings: &mut Settings) {
         if !self.state.neuro_settings {
             return;
         }
-        let mut settings = get_settings();
         Window::new("NEURO SETTINGS").id("neuro_settings_win".into()).default_pos((SCREEN_WIDTH/2., SCREEN_HEIGHT/2.)).fixed_size([380., 400.])
         .title_bar(true).show(egui_ctx, |ui| {
             ui.columns(2, |column| {
@@ -1679,14 +2071,12 @@ impl UISystem {
             ui.vertical_centered(|closer| {
                 if closer.button(RichText::new("CLOSE").color(Color32::GREEN).strong()).clicked() {
                     self.state.neuro_settings = false;
-                    set_settings(settings.clone());
                 }
             });
         });
-        set_settings(settings.clone());
     }
 
-    fn build_left_panel(&mut self, egui_ctx: &Context, state: &SimState, agent: Option<&Agent>, ranking: &Ranking, statistics: &Statistics) {
+    fn build_left_panel(&mut self, egui_ctx: &Context, state: &SimState, agent: Option<&Agent>, ranking: &Ranking, statistics: &Statistics, settings: &Settings) {
         if !self.state.left_panel {
             return;
         }
@@ -1697,7 +2087,7 @@ impl UISystem {
             if self.state.monit {
                 ui.vertical(|ui| {
                     ui.collapsing("Monitor", |ui| {
-                        self.inside_monitor(ui, state)
+                        self.inside_monitor(ui, state, settings)
                     });
                 });
             }
@@ -1808,10 +2198,16 @@ impl UISystem {
         let lifetimes = statistics.get_data_as_slice("lifetimes");
         let agents = statistics.get_data_as_slice("agents");
         let plants = statistics.get_data_as_slice("plants");
+        let grass = statistics.get_data_as_slice("grass");
+        let bushes = statistics.get_data_as_slice("bushes");
+        let toxic_plants = statistics.get_data_as_slice("toxic_plants");
         let inner = plot_lifetimes.show(ui, |plot_ui| {
             plot_ui.line(Line::new(PlotPoints::from(lifetimes)).name("lifetime").color(Color32::YELLOW));
             plot_ui.line(Line::new(PlotPoints::from(agents)).name("agents").color(Color32::BLUE));
             plot_ui.line(Line::new(PlotPoints::from(plants)).name("plants").color(Color32::GREEN));
+            plot_ui.line(Line::new(PlotPoints::from(grass)).name("grass").color(Color32::LIGHT_GREEN));
+            plot_ui.line(Line::new(PlotPoints::from(bushes)).name("bushes").color(Color32::DARK_GREEN));
+            plot_ui.line(Line::new(PlotPoints::from(toxic_plants)).name("toxic").color(Color32::from_rgb(170, 60, 200)));
         });
         _ = Some(inner.response.rect);
     }
@@ -1841,9 +2237,11 @@ impl UISystem {
         let neuro_plot = Plot::new("neuro").legend(legend);
         let nodes = statistics.get_data_as_slice("nodes");
         let links = statistics.get_data_as_slice("links");
+        let species = statistics.get_data_as_slice("species");
         let inner = neuro_plot.show(ui, |plot_ui| {
             plot_ui.line(Line::new(PlotPoints::from(nodes)).name("nodes").color(Color32::BLUE));
             plot_ui.line(Line::new(PlotPoints::from(links)).name("links").color(Color32::GREEN));
+            plot_ui.line(Line::new(PlotPoints::from(species)).name("species").color(Color32::GOLD));
         });
         _ = Some(inner.response.rect);
     }
@@ -1858,6 +2256,7 @@ impl UISystem {
         let powers = statistics.get_data_as_slice("powers");
         let speeds = statistics.get_data_as_slice("speeds");
         let eyes = statistics.get_data_as_slice("eyes");
+        let ears = statistics.get_data_as_slice("ears");
         let shells = statistics.get_data_as_slice("shells");
         let mutations = statistics.get_data_as_slice("mutations");
         let inner = my_plot.show(ui, |plot_ui| {
@@ -1865,13 +2264,14 @@ impl UISystem {
             plot_ui.line(Line::new(PlotPoints::from(powers)).name("power").color(Color32::GREEN));
             plot_ui.line(Line::new(PlotPoints::from(speeds)).name("speed").color(Color32::YELLOW));
             plot_ui.line(Line::new(PlotPoints::from(eyes)).name("eye").color(Color32::RED));
+            plot_ui.line(Line::new(PlotPoints::from(ears)).name("ear").color(Color32::KHAKI));
             plot_ui.line(Line::new(PlotPoints::from(shells)).name("shell").color(Color32::DARK_GRAY));
             plot_ui.line(Line::new(PlotPoints::from(mutations)).name("mutation").color(Color32::LIGHT_BLUE));
         });
         _ = Some(inner.response.rect);
     }
 
-    fn inside_monitor(&mut self, ui: &mut Ui, sim_state: &SimState) {
+    fn inside_monitor(&mut self, ui: &mut Ui, sim_state: &SimState, settings: &Settings) {
         let time = sim_state.sim_time;
         let agents_num = sim_state.agents_num;
         let sources_num = sim_state.sources_num;
@@ -1883,9 +2283,13 @@ impl UISystem {
                 .strong().size(12.0).color(Color32::GOLD));
             ui.label(RichText::new(format!("AGENT: {}", agents_num))
                 .strong().size(12.0).color(Color32::LIGHT_BLUE));
+            ui.label(RichText::new(format!("SPECIES: {}", sim_state.species_num))
+                .strong().size(12.0).color(Color32::LIGHT_BLUE));
             ui.label(RichText::new(format!("PLANT: {}", sources_num))
                 .strong().size(12.0).color(Color32::GREEN));
-            ui.label(RichText::new(format!(">> x{}", sim_speed()))
+            ui.label(RichText::new(format!("NUTRIENTS: {:.0}", sim_state.total_nutrients))
+                .strong().size(12.0).color(Color32::BROWN));
+            ui.label(RichText::new(format!(">> x{}", settings.sim_speed))
                 .strong().size(12.0).color(Color32::YELLOW));
         });
         if self.state.show_fps {
@@ -1929,14 +2333,15 @@ impl UISystem {
             let shell = agent.shell;
             let mutations = agent.mutations;
             let eyes = agent.eyes;
+            let ears = agent.ears;
             let water: bool = if agent.get_water() == 0 {
                 false
             } else {
                 true
             };
             let attributes = format!(
-                "S: {} | M: {} | P: {} | D: {} | X: {} | V: {}", 
-                size, speed, power, shell, mutations, eyes
+                "S: {} | M: {} | P: {} | D: {} | X: {} | V: {} | H: {}", 
+                size, speed, power, shell, mutations, eyes, ears
             );
             ui.horizontal(|ui| {
                 ui.set_max_height(14.0);
@@ -1993,6 +2398,17 @@ impl UISystem {
                 ui.label(RichText::new(txt)
                     .strong().size(12.0).color(color));
             });
+            ui.horizontal(|ui| {
+                ui.set_max_height(14.0);
+                let genome = AgentGenome::from_sketch(&agent.get_sketch());
+                ui.label(RichText::new(format!("GENOME: {:016X}", genome.fingerprint()))
+                    .strong().size(12.0).color(Color32::GRAY).monospace());
+                ui.separator();
+                if ui.button(RichText::new("COPY GENOME").strong().size(12.0).color(Color32::WHITE)).clicked() {
+                    let code = genome.encode();
+                    ui.output_mut(|o| o.copied_text = code);
+                }
+            });
         }
     }
 
@@ -2142,6 +2558,7 @@ pub struct UIState {
     pub set_agent: bool,
     pub load_sim: bool,
     pub load_agent: bool,
+    pub load_scenario: bool,
     pub attributes: bool,
     pub main_menu: bool,
     pub energy_cost: bool,
@@ -2186,6 +2603,7 @@ impl UIState {
             set_agent: false,
             load_sim: false,
             load_agent: false,
+            load_scenario: false,
             attributes: false,
             main_menu: true,
             energy_cost: false,